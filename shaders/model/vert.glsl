@@ -5,7 +5,7 @@ layout(location = 1) in vec2 auv;
 layout(location = 2) in vec3 anormal;
 
 uniform bool is_skin;
-layout(location = 3) in vec4 joint;
+layout(location = 3) in uvec4 joint;
 layout(location = 4) in vec4 weight;
 
 out vec2 uv;
@@ -18,10 +18,10 @@ void main() {
     uv = auv;
     normal = anormal;
     if (is_skin) {
-        mat4 skin_mat = weight.x * joint_mats[int(joint.x)] +
-                weight.y * joint_mats[int(joint.y)] +
-                weight.z * joint_mats[int(joint.z)] +
-                weight.w * joint_mats[int(joint.w)];
+        mat4 skin_mat = weight.x * joint_mats[joint.x] +
+                weight.y * joint_mats[joint.y] +
+                weight.z * joint_mats[joint.z] +
+                weight.w * joint_mats[joint.w];
         vec4 world_pos = skin_mat * vec4(vert, 1);
         gl_Position = project_mat *  world_pos;
     } else {
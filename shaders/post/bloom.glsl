@@ -0,0 +1,10 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    uniform sampler2D bloom;
+    uniform float intensity;
+    out vec4 color;
+    void main(){
+        color = texture(image,tex_uv);
+        color.rgb += texture(bloom,tex_uv).rgb * intensity;
+    }
@@ -0,0 +1,17 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    //texel * 方向 * 半径
+    uniform vec2 direction;
+    out vec4 color;
+    //9 tap高斯,利用线性过滤合成5次采样
+    const float offsets[3] = float[](0.0, 1.3846153846, 3.2307692308);
+    const float weights[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+    void main(){
+        color = texture(image,tex_uv) * weights[0];
+        for(int i = 1; i < 3; i++){
+            vec2 offset = direction * offsets[i];
+            color += texture(image,tex_uv + offset) * weights[i];
+            color += texture(image,tex_uv - offset) * weights[i];
+        }
+    }
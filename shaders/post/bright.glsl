@@ -0,0 +1,10 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    uniform float threshold;
+    out vec4 color;
+    void main(){
+        vec3 rgb = texture(image,tex_uv).rgb;
+        float bright = max(rgb.r, max(rgb.g, rgb.b));
+        color = vec4(rgb * max(bright - threshold, 0.0) / max(bright, 0.0001), 1.0);
+    }
@@ -0,0 +1,31 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    uniform vec2 texel;
+    out vec4 color;
+    const float REDUCE_MIN = 1.0 / 128.0;
+    const float REDUCE_MUL = 1.0 / 8.0;
+    const float SPAN_MAX = 8.0;
+    const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+    void main(){
+        float nw = dot(texture(image, tex_uv + vec2(-1.0, -1.0) * texel).rgb, LUMA);
+        float ne = dot(texture(image, tex_uv + vec2(1.0, -1.0) * texel).rgb, LUMA);
+        float sw = dot(texture(image, tex_uv + vec2(-1.0, 1.0) * texel).rgb, LUMA);
+        float se = dot(texture(image, tex_uv + vec2(1.0, 1.0) * texel).rgb, LUMA);
+        vec4 center = texture(image, tex_uv);
+        float m = dot(center.rgb, LUMA);
+        float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+        float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+        vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+        float reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+        float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+        dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+        vec3 a = 0.5 * (texture(image, tex_uv + dir * (1.0 / 3.0 - 0.5)).rgb
+            + texture(image, tex_uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+        vec3 b = a * 0.5 + 0.25 * (texture(image, tex_uv - dir * 0.5).rgb
+            + texture(image, tex_uv + dir * 0.5).rgb);
+        float luma_b = dot(b, LUMA);
+        color = vec4((luma_b < luma_min || luma_b > luma_max) ? a : b, center.a);
+    }
@@ -0,0 +1,23 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    //横排的lut: 宽size*size,高size,第i块是b=i/(size-1),块内x是r,y是g
+    uniform sampler2D lut;
+    uniform float lut_size;
+    uniform float intensity;
+    out vec4 color;
+    vec3 grade(vec3 rgb){
+        float n = lut_size;
+        rgb = clamp(rgb, 0.0, 1.0);
+        float blue = rgb.b * (n - 1.0);
+        float low = floor(blue);
+        float high = min(low + 1.0, n - 1.0);
+        vec2 uv = vec2((rgb.r * (n - 1.0) + 0.5) / (n * n), (rgb.g * (n - 1.0) + 0.5) / n);
+        vec3 a = texture(lut, uv + vec2(low / n, 0.0)).rgb;
+        vec3 b = texture(lut, uv + vec2(high / n, 0.0)).rgb;
+        return mix(a, b, blue - low);
+    }
+    void main(){
+        color = texture(image,tex_uv);
+        color.rgb = mix(color.rgb, grade(color.rgb), intensity);
+    }
@@ -0,0 +1,17 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    uniform float exposure;
+    uniform float gamma;
+    //0 aces, 1 reinhard
+    uniform int mode;
+    out vec4 color;
+    vec3 aces(vec3 x){
+        return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+    }
+    void main(){
+        color = texture(image,tex_uv);
+        vec3 rgb = color.rgb * exposure;
+        rgb = mode == 0 ? aces(rgb) : rgb / (rgb + vec3(1.0));
+        color.rgb = pow(rgb, vec3(1.0 / gamma));
+    }
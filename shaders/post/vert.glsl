@@ -0,0 +1,8 @@
+#version 330
+    out vec2 tex_uv;
+    //不用顶点buffer,3个顶点盖住整个屏幕
+    void main(){
+        vec2 pos = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+        tex_uv = pos;
+        gl_Position = vec4(pos * 2.0 - 1.0, 0, 1);
+    }
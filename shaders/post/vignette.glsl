@@ -0,0 +1,14 @@
+#version 330
+    in vec2 tex_uv;
+    uniform sampler2D image;
+    uniform float strength;
+    //从中心算,radius以内不变暗,softness是过渡宽度
+    uniform float radius;
+    uniform float softness;
+    out vec4 color;
+    void main(){
+        color = texture(image,tex_uv);
+        float dist = distance(tex_uv, vec2(0.5));
+        float shade = smoothstep(radius + softness, radius, dist);
+        color.rgb *= mix(1.0, shade, strength);
+    }
@@ -0,0 +1,8 @@
+#version 330
+    in vec2 tex_uv;
+    in vec4 tex_color;
+    uniform sampler2D image;
+    out vec4 color;
+    void main(){
+        color = texture(image,tex_uv) * tex_color;
+    }
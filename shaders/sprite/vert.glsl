@@ -0,0 +1,12 @@
+#version 330
+    layout (location = 0) in vec2 vert;
+    layout (location = 1) in vec2 uv;
+    layout (location = 2) in vec4 color;
+    out vec2 tex_uv;
+    out vec4 tex_color;
+    uniform mat4 project_mat;
+    void main(){
+        gl_Position = project_mat * vec4(vert,0,1);
+        tex_uv = uv;
+        tex_color = color;
+    }
@@ -1,11 +1,14 @@
 pub mod model;
+pub mod post_process;
+pub mod sprite;
 pub mod video;
-use crate::gl_unit::define::{DrawMode, VertexArrayAttribPointerGen};
+use crate::gl_unit::define::VertexArrayAttribPointerGen;
 use crate::gl_unit::program::{Program, PROGRAM2D_ONE};
 use crate::gl_unit::texture::{Texture, TextureMap, UVindex};
 use crate::gl_unit::window::Window;
 use crate::gl_unit::{self, ConstBlend};
-use crate::{VAO_MUT, VERTEX_BIG_MUT};
+use crate::{STREAM_VERTEX, VAO_MUT};
+use sprite::SpriteBatch;
 use core::fmt::{Debug, Formatter};
 use glam::{vec2, vec3, vec4, Mat4, Vec2, Vec3, Vec4Swizzles};
 
@@ -434,16 +437,19 @@ impl Tick {
 }
 #[cfg(test)]
 pub mod test {
-    use std::path::Path;
-
     use glam::Mat4;
 
-    use crate::gl_unit::{texture::TextureMap, window::Window, GLcontext};
-
     use super::Reanim;
 
+    //要显示器和test_res/reanim素材,窗口循环要手动关,用 cargo test -- --ignored 跑
+    #[cfg(not(feature = "mock-gl"))]
     #[test]
+    #[ignore]
     fn reanim() {
+        use std::path::Path;
+
+        use crate::gl_unit::{texture::TextureMap, window::Window, GLcontext};
+
         let mut window = Window::new(800, 600, "reanim test", false);
         let mut context = GLcontext::with(&mut window);
 
@@ -459,6 +465,76 @@ pub mod test {
             play.update(window.delta_count.delta as f32);
         }
     }
+
+    //三条带图的轨道,一条不带图的
+    #[cfg(feature = "mock-gl")]
+    const REANIM: &str = "<reanim><fps>12</fps>\
+        <track><name>a</name><t><x>1</x><y>2</y><i>red</i></t></track>\
+        <track><name>b</name><t><sx>2</sx><i>green</i></t></track>\
+        <track><name>c</name><t><kx>30</kx><i>red</i></t></track>\
+        <track><name>d</name><t><x>4</x></t></track></reanim>";
+
+    #[cfg(feature = "mock-gl")]
+    fn mock_map() -> crate::gl_unit::texture::TextureMap<String> {
+        use crate::gl_unit::{
+            define::{TextureParm, TextureType},
+            mock,
+            texture::{Texture2D, TextureMap, TextureWrapper},
+        };
+
+        mock::load();
+        let texture = || {
+            TextureWrapper(Texture2D::load(
+                Some([255u8; 16].as_slice()),
+                TextureType::RGBA8,
+                2,
+                2,
+                TextureParm::new(),
+            ))
+        };
+        let mut map = TextureMap::new(16, 16);
+        map.add(
+            vec![("red".to_string(), texture()), ("green".to_string(), texture())],
+            false,
+        )
+        .unwrap();
+        map
+    }
+
+    //所有轨道一次draw_quads画完
+    #[cfg(feature = "mock-gl")]
+    #[test]
+    fn render_program_one_draw() {
+        use crate::gl_unit::{mock, program::PROGRAM2D_ONE};
+
+        let map = mock_map();
+        let player = Reanim::new(REANIM, &map).make_player();
+        mock::take();
+        player.render_program((64, 64), &map, &PROGRAM2D_ONE, Mat4::IDENTITY);
+        let draws: Vec<_> = mock::take()
+            .into_iter()
+            .filter(|call| call.name.starts_with("DrawElements"))
+            .collect();
+        assert_eq!(draws.len(), 1);
+        //3个quad,18个索引
+        assert_eq!(draws[0].args[1], "18");
+    }
+
+    //放进batch的轨道都在一张图集上,flush也只画一次
+    #[cfg(feature = "mock-gl")]
+    #[test]
+    fn batch_flush_one_draw() {
+        use crate::{draws::sprite::SpriteBatch, gl_unit::mock};
+
+        let map = mock_map();
+        let player = Reanim::new(REANIM, &map).make_player();
+        let mut batch = SpriteBatch::new();
+        player.batch(&mut batch, &map, Mat4::IDENTITY, 0f32);
+        player.batch(&mut batch, &map, Mat4::IDENTITY, 0f32);
+        mock::take();
+        batch.flush(Mat4::IDENTITY);
+        assert_eq!(mock::draw_calls(), 1);
+    }
 }
 
 pub struct Reanim {
@@ -740,12 +816,12 @@ impl ReanimPlayer {
         //render
         gl_unit::const_blend(ConstBlend::Normal);
         program.bind();
+        let first = STREAM_VERTEX.push_vertex(&vertexs, 4);
         VAO_MUT.bind(|vao|{
         vao.bind_pointer(
-            VERTEX_BIG_MUT.deref(),
+            STREAM_VERTEX.deref(),
             VertexArrayAttribPointerGen::new::<f32>(0, 4),
         );
-        VERTEX_BIG_MUT.sub_data(&vertexs, 0);
         program.put_texture(0, program.get_uniform("image"));
         program.put_matrix(mat4, program.get_uniform("model_mat"));
         let (w, h) = window_size;
@@ -755,7 +831,34 @@ impl ReanimPlayer {
             "project_mat",
         );
         
-        vao.draw_arrays(DrawMode::Quads,0,vertexs.len() as i32 / 16);});
+        vao.draw_quads(first, vertexs.len() as i32 / 16);});
+    }
+    //和render_program一样的变换,但只把quad放进batch,由调用者统一flush
+    pub fn batch(&self, batch: &mut SpriteBatch, tex_map: &TextureMap<String>, mat4: Mat4, z: f32) {
+        for tick in self.tracks.iter() {
+            if let Some(texture) = tick.texture.as_ref() {
+                let mat = mat4
+                    * Mat4::from_translation(vec3(tick.x.unwrap(), tick.y.unwrap(), 0f32))
+                    * mat4_skew(
+                        (tick.kx.unwrap()).to_radians(),
+                        tick.ky.unwrap().to_radians(),
+                        tick.sx.unwrap(),
+                        tick.sy.unwrap(),
+                    );
+                let (w, h) = texture.get_pixel_size(tex_map);
+                let corners = [vec2(0f32, h), vec2(w, h), vec2(w, 0f32), vec2(0f32, 0f32)]
+                    .map(|vert| (mat * vert.extend(0f32).extend(1f32)).xy());
+                let color = vec4(1f32, 1f32, 1f32, tick.alpha.unwrap_or(1f32));
+                batch.push_quad(
+                    tex_map.get_tex().texture,
+                    ConstBlend::Normal,
+                    z,
+                    corners,
+                    texture.get_uv(),
+                    [color; 4],
+                );
+            }
+        }
     }
     pub fn render(&self, window_size: (i32, i32), tex_map: &TextureMap<String>, mat: Mat4) {
         self.render_program(window_size, tex_map, &PROGRAM2D_ONE, mat);
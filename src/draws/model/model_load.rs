@@ -7,6 +7,7 @@ use crate::{
     draws::model::Player,
     gl_unit::{
         define::{TextureParm, TextureType},
+        error::{GlError, GlResult},
         texture::{Texture2D, TextureWrapper},
     },
 };
@@ -140,16 +141,27 @@ impl Model {
         )
     }
     pub fn from_path(p: impl AsRef<Path>) -> Self {
-        Self::from_buffer(fs::read(p).unwrap())
+        Self::try_from_path(p).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_from_path(p: impl AsRef<Path>) -> GlResult<Self> {
+        let p = p.as_ref();
+        Self::try_from_buffer(
+            fs::read(p).map_err(|err| GlError::Asset(format!("{:?}:{}", p, err)))?,
+        )
     }
     pub fn from_buffer(buffer: impl AsRef<[u8]>) -> Self {
-        let (document, buffers, images) = gltf::import_slice(buffer.as_ref()).unwrap();
+        Self::try_from_buffer(buffer).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_from_buffer(buffer: impl AsRef<[u8]>) -> GlResult<Self> {
+        let (document, buffers, images) =
+            gltf::import_slice(buffer.as_ref()).map_err(|err| GlError::Asset(err.to_string()))?;
         let textures = images
             .into_iter()
             .map(|data| {
                 let tex_type = match data.format {
                     gltf::image::Format::R8 => TextureType::RED8,
-                    gltf::image::Format::R8G8 | gltf::image::Format::R16G16 => todo!(),
+                    gltf::image::Format::R8G8 => TextureType::RG8,
+                    gltf::image::Format::R16G16 => TextureType::RG16,
                     gltf::image::Format::R8G8B8 => TextureType::RGB8,
                     gltf::image::Format::R8G8B8A8 => TextureType::RGBA8,
                     gltf::image::Format::R16 => TextureType::RED16,
@@ -158,23 +170,24 @@ impl Model {
                     gltf::image::Format::R32G32B32FLOAT => TextureType::RGB32,
                     gltf::image::Format::R32G32B32A32FLOAT => TextureType::RGBA32,
                 };
-                TextureWrapper(Texture2D::load(
+                Ok(TextureWrapper(Texture2D::load(
                     Some(data.pixels.as_slice()),
                     tex_type,
                     data.width,
                     data.height,
+                    //远处不闪:三线性mipmap+各向异性
                     if (data.width) % 2 != 0 {
-                        TextureParm::new().once_load_size(1)
+                        TextureParm::mipmap().anisotropy(8f32).once_load_size(1)
                     } else {
-                        TextureParm::new()
+                        TextureParm::mipmap().anisotropy(8f32)
                     },
-                ))
+                )))
             })
-            .collect::<Vec<TextureWrapper<Texture2D>>>();
-        Self {
+            .collect::<GlResult<Vec<TextureWrapper<Texture2D>>>>()?;
+        Ok(Self {
             texs: textures,
             document,
             data: buffers,
-        }
+        })
     }
 }
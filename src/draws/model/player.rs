@@ -11,10 +11,11 @@ use crate::{
     draws::model::Model,
     gl_unit::{
         VertexArray,
-        define::{BufferTarget, BufferUsage, DrawMode, VertexArrayAttribPointerGen},
+        define::{BufferTarget, BufferUsage, DrawMode},
         program::Program,
         texture::Texture,
     },
+    vertex_layout,
 };
 
 struct Material {
@@ -29,12 +30,20 @@ impl Material {
         Self { texture, color }
     }
 }
+vertex_layout! {
+    #[derive(Clone, Copy, Default)]
+    struct ModelVertex {
+        pos: [f32; 3] = 0,
+        uv: [f32; 2] = 1,
+        normal: [f32; 3] = 2,
+        //没有蒙皮时全0
+        joint: [u16; 4] = 3 as int,
+        weight: [f32; 4] = 4,
+    }
+}
 #[allow(dead_code)]
 struct PrimitiveData {
-    weight_buffer: Option<BufferConst<f32>>,
-    joint_buffer: Option<BufferConst<f32>>,
-    vertex_buffer: BufferConst<f32>,
-    uv_buffer: BufferConst<f32>,
+    vertex_buffer: BufferConst<ModelVertex>,
     vao: VertexArray,
     ebo: BufferConst<u32>,
     draw_mode: DrawMode,
@@ -43,74 +52,44 @@ struct PrimitiveData {
 impl PrimitiveData {
     pub fn new(model: &Model, prim: Primitive) -> Self {
         let reader = prim.reader(|index| Some(&model.data[index.index()]));
-        const PRIM_CAP: usize = 2048;
-        let mut vertex_list: Vec<f32> = Vec::with_capacity(PRIM_CAP);
-        let mut uv_list: Vec<f32> = Vec::with_capacity(PRIM_CAP);
-        let mut indices_list: Vec<u32> = Vec::with_capacity(PRIM_CAP);
+        let mut vertex_list: Vec<ModelVertex> = Vec::new();
+        let mut indices_list: Vec<u32> = Vec::new();
         if let (Some(vertex), Some(uv), Some(indices)) = (
             reader.read_positions(),
             reader.read_tex_coords(0),
             reader.read_indices(),
         ) {
-            for vertex in vertex {
-                vertex_list.extend_from_slice(&vertex);
-            }
-            for uv in uv.into_f32() {
-                uv_list.extend_from_slice(&uv);
+            vertex_list.extend(vertex.zip(uv.into_f32()).map(|(pos, uv)| ModelVertex {
+                pos,
+                uv,
+                ..Default::default()
+            }));
+            indices_list.extend(indices.into_u32());
+        }
+        if let Some(normals) = reader.read_normals() {
+            for (vertex, normal) in vertex_list.iter_mut().zip(normals) {
+                vertex.normal = normal;
             }
-            for index in indices.into_u32() {
-                indices_list.push(index);
+        }
+        if let (Some(joints), Some(weight)) = (reader.read_joints(0), reader.read_weights(0)) {
+            for (vertex, (joint, weight)) in vertex_list
+                .iter_mut()
+                .zip(joints.into_u16().zip(weight.into_f32()))
+            {
+                vertex.joint = joint;
+                vertex.weight = weight;
             }
         }
         let vertex_buffer =
             BufferConst::new(BufferTarget::Vertex, &vertex_list, BufferUsage::Static);
-        let uv_buffer = BufferConst::new(BufferTarget::Vertex, &uv_list, BufferUsage::Static);
         let indices_buffer =
             BufferConst::new(BufferTarget::Element, &indices_list, BufferUsage::Static);
-        let mut joint_list: Vec<f32> = Vec::with_capacity(PRIM_CAP);
-        let mut weight_list: Vec<f32> = Vec::with_capacity(PRIM_CAP);
-        let mut joint_buffer = None;
-        let mut weight_buffer = None;
-        if let (Some(joints), Some(weight)) = (reader.read_joints(0), reader.read_weights(0)) {
-            for (joint, weight) in joints
-                .into_u16()
-                .map(|value| value.map(|value| value as f32))
-                .zip(weight.into_f32())
-            {
-                joint_list.extend_from_slice(&joint);
-                weight_list.extend_from_slice(&weight);
-            }
-            joint_buffer = Some(BufferConst::new(
-                BufferTarget::Vertex,
-                &joint_list,
-                BufferUsage::Static,
-            ));
-            weight_buffer = Some(BufferConst::new(
-                BufferTarget::Vertex,
-                &weight_list,
-                BufferUsage::Static,
-            ));
-        }
 
-        let mut vao = VertexArray::new();
-        vao.bind(|vao| {
-            vao.bind_pointer(
-                &vertex_buffer,
-                VertexArrayAttribPointerGen::new::<f32>(0, 3),
-            );
-            vao.bind_pointer(&uv_buffer, VertexArrayAttribPointerGen::new::<f32>(1, 2));
-            if let (Some(joint), Some(weight)) = (&joint_buffer, &weight_buffer) {
-                vao.bind_pointer(joint, VertexArrayAttribPointerGen::new::<f32>(3, 4));
-                vao.bind_pointer(weight, VertexArrayAttribPointerGen::new::<f32>(4, 4));
-            }
-        });
+        let mut vao = VertexArray::from_layout(&vertex_buffer);
         vao.element_bind(&indices_buffer);
 
         Self {
-            weight_buffer,
-            joint_buffer,
             vertex_buffer,
-            uv_buffer,
             vao,
             ebo: indices_buffer,
             draw_mode: DrawMode::from_gl(prim.mode().as_gl_enum()),
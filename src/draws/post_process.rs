@@ -0,0 +1,399 @@
+use std::sync::LazyLock;
+
+use glam::{Vec2, vec2};
+
+use crate::gl_unit::{
+    FrameBuffer, VertexArray,
+    define::{DrawMode, Filter, TextureParm, TextureType, TextureWarpMode},
+    error::GlResult,
+    framebuffer::DepthFormat,
+    pipeline::PipelineState,
+    program::{Program, Sampler, UniformValue},
+    state,
+    texture::{Texture, Texture2D, TextureWrapper},
+};
+
+const POST_VERT: &str = include_str!("../../shaders/post/vert.glsl");
+
+macro_rules! post_program {
+    ($($name:ident: $file:literal),* $(,)?) => {
+        $(
+            pub static $name: LazyLock<Program> = LazyLock::new(|| {
+                Program::basic_new(
+                    POST_VERT,
+                    include_str!(concat!("../../shaders/post/", $file)),
+                    None,
+                )
+            });
+        )*
+    };
+}
+post_program! {
+    POST_COPY: "copy.glsl",
+    POST_BLUR: "blur.glsl",
+    POST_BRIGHT: "bright.glsl",
+    POST_BLOOM: "bloom.glsl",
+    POST_LUT: "lut.glsl",
+    POST_FXAA: "fxaa.glsl",
+    POST_VIGNETTE: "vignette.glsl",
+    POST_TONEMAP: "tonemap.glsl",
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapMode {
+    Aces,
+    Reinhard,
+}
+
+pub enum PostPass {
+    //image在0号单元,texel是1/输入尺寸,其他uniform在闭包里设置
+    Custom(Program, Box<dyn Fn(&Program)>),
+    //radius按像素,每次迭代横竖各一遍
+    Blur {
+        radius: f32,
+        iterations: u32,
+    },
+    //亮部提出来在半分辨率上模糊再加回去
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: f32,
+        iterations: u32,
+    },
+    //横排的lut纹理,见shaders/post/lut.glsl
+    ColorGrade {
+        lut: TextureWrapper<Texture2D>,
+        intensity: f32,
+    },
+    Fxaa,
+    Vignette {
+        strength: f32,
+        radius: f32,
+        softness: f32,
+    },
+    ToneMap {
+        exposure: f32,
+        gamma: f32,
+        mode: ToneMapMode,
+    },
+}
+
+impl PostPass {
+    pub fn custom(program: Program) -> Self {
+        Self::Custom(program, Box::new(|_| {}))
+    }
+    pub fn custom_with(program: Program, setup: impl Fn(&Program) + 'static) -> Self {
+        Self::Custom(program, Box::new(setup))
+    }
+    pub const fn blur(radius: f32) -> Self {
+        Self::Blur {
+            radius,
+            iterations: 2,
+        }
+    }
+    pub const fn bloom(threshold: f32, intensity: f32) -> Self {
+        Self::Bloom {
+            threshold,
+            intensity,
+            radius: 1f32,
+            iterations: 4,
+        }
+    }
+    pub const fn color_grade(lut: TextureWrapper<Texture2D>) -> Self {
+        Self::ColorGrade {
+            lut,
+            intensity: 1f32,
+        }
+    }
+    pub const fn vignette(strength: f32) -> Self {
+        Self::Vignette {
+            strength,
+            radius: 0.4f32,
+            softness: 0.4f32,
+        }
+    }
+    pub const fn tone_map(exposure: f32) -> Self {
+        Self::ToneMap {
+            exposure,
+            gamma: 2.2f32,
+            mode: ToneMapMode::Aces,
+        }
+    }
+}
+
+//shader里没用到的uniform会被优化掉,直接跳过
+fn put<T: UniformValue>(program: &Program, name: &str, value: T) {
+    if program.uniform_info(name).is_none() {
+        return;
+    }
+    if let Err(err) = program.set_uniform(name, value) {
+        eprintln!("[post process]{}", err);
+    }
+}
+
+fn target_parm() -> TextureParm {
+    TextureParm::new()
+        .min_filter(Filter::Linear)
+        .mag_filter(Filter::Linear)
+        .wrap_s(TextureWarpMode::ClampEdge)
+        .wrap_t(TextureWarpMode::ClampEdge)
+}
+
+const fn half(size: u32) -> u32 {
+    if size > 2 { size / 2 } else { 1 }
+}
+
+//begin返回的fbo里画场景,end把所有pass依次跑完,最后一个pass直接画到屏幕上
+pub struct PostProcessChain {
+    passes: Vec<PostPass>,
+    size: (u32, u32),
+    //0是场景,pass之间在三个里轮流读写
+    targets: [FrameBuffer; 3],
+    //bloom用的半分辨率
+    half: [FrameBuffer; 2],
+    vao: VertexArray,
+}
+
+impl PostProcessChain {
+    pub fn new(w: u32, h: u32) -> GlResult<Self> {
+        Self::with_format(w, h, TextureType::RGBA8)
+    }
+    pub fn with_format(w: u32, h: u32, format: TextureType) -> GlResult<Self> {
+        let color = |w, h| {
+            FrameBuffer::builder(w, h)
+                .color_with(format, target_parm(), [0f32; 4])
+                .build()
+        };
+        let scene = FrameBuffer::builder(w, h)
+            .color_with(format, target_parm(), [0f32; 4])
+            .depth_buffer(DepthFormat::Depth24Stencil8)
+            .build()?;
+        Ok(Self {
+            passes: Vec::new(),
+            size: (w, h),
+            targets: [scene, color(w, h)?, color(w, h)?],
+            half: [color(half(w), half(h))?, color(half(w), half(h))?],
+            vao: VertexArray::new(),
+        })
+    }
+    pub fn pass(mut self, pass: PostPass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+    pub fn push(&mut self, pass: PostPass) {
+        self.passes.push(pass);
+    }
+    pub fn passes_mut(&mut self) -> &mut Vec<PostPass> {
+        &mut self.passes
+    }
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+    //场景画在这里
+    pub fn scene(&self) -> &FrameBuffer {
+        &self.targets[0]
+    }
+    pub fn resize(&mut self, w: u32, h: u32) -> GlResult<()> {
+        if self.size == (w, h) {
+            return Ok(());
+        }
+        for target in self.targets.iter_mut() {
+            target.resize(w, h)?;
+        }
+        for target in self.half.iter_mut() {
+            target.resize(half(w), half(h))?;
+        }
+        self.size = (w, h);
+        Ok(())
+    }
+    //窗口大小变了会自动重建,之后绑定并清空场景fbo
+    pub fn begin(&mut self, window_size: (i32, i32)) -> GlResult<&FrameBuffer> {
+        let (w, h) = window_size;
+        self.resize(w.max(1) as u32, h.max(1) as u32)?;
+        let scene = &self.targets[0];
+        scene.bind(gl::FRAMEBUFFER);
+        scene.view_port();
+        scene.clear();
+        Ok(scene)
+    }
+    //合成到默认帧缓冲
+    pub fn end(&self) {
+        self.end_into(None);
+    }
+    //target为None时画到默认帧缓冲,结束后target保持绑定
+    pub fn end_into(&self, target: Option<&FrameBuffer>) {
+        PipelineState::new().scoped(|| {
+            if self.passes.is_empty() {
+                self.draw(&POST_COPY, &[self.texture(0)], target, |_| {});
+            }
+            let mut input = 0;
+            for (index, pass) in self.passes.iter().enumerate() {
+                let last = index + 1 == self.passes.len();
+                let (output, scratch) = ((input + 1) % 3, (input + 2) % 3);
+                let out = if last {
+                    target
+                } else {
+                    Some(&self.targets[output])
+                };
+                self.run(pass, input, out, scratch);
+                input = output;
+            }
+        });
+        match target {
+            Some(target) => {
+                target.bind(gl::FRAMEBUFFER);
+                target.view_port();
+            }
+            None => {
+                FrameBuffer::unbind();
+                state::viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+            }
+        }
+    }
+
+    fn texture(&self, index: usize) -> &Texture2D {
+        self.targets[index].texture().unwrap()
+    }
+    fn run(&self, pass: &PostPass, input: usize, output: Option<&FrameBuffer>, scratch: usize) {
+        let image = self.texture(input);
+        match pass {
+            PostPass::Custom(program, setup) => self.draw(program, &[image], output, setup),
+            PostPass::Blur { radius, iterations } => {
+                if *iterations == 0 {
+                    self.draw(&POST_COPY, &[image], output, |_| {});
+                }
+                for iteration in 0..*iterations {
+                    let last = iteration + 1 == *iterations;
+                    let into = if last {
+                        output
+                    } else {
+                        Some(&self.targets[input])
+                    };
+                    self.blur(input, scratch, into, *radius);
+                }
+            }
+            PostPass::Bloom {
+                threshold,
+                intensity,
+                radius,
+                iterations,
+            } => {
+                let bright = self.half[0].texture().unwrap();
+                let swap = self.half[1].texture().unwrap();
+                self.draw(&POST_BRIGHT, &[image], Some(&self.half[0]), |program| {
+                    put(program, "threshold", *threshold);
+                });
+                for _ in 0..*iterations {
+                    for (from, into, direction) in [
+                        (bright, &self.half[1], vec2(1f32, 0f32)),
+                        (swap, &self.half[0], vec2(0f32, 1f32)),
+                    ] {
+                        self.draw(&POST_BLUR, &[from], Some(into), |program| {
+                            put(program, "direction", direction * *radius / from.vec2());
+                        });
+                    }
+                }
+                self.draw(&POST_BLOOM, &[image, bright], output, |program| {
+                    put(program, "bloom", Sampler(1));
+                    put(program, "intensity", *intensity);
+                });
+            }
+            PostPass::ColorGrade { lut, intensity } => {
+                self.draw(&POST_LUT, &[image, lut], output, |program| {
+                    put(program, "lut", Sampler(1));
+                    put(program, "lut_size", lut.h as f32);
+                    put(program, "intensity", *intensity);
+                });
+            }
+            PostPass::Fxaa => self.draw(&POST_FXAA, &[image], output, |_| {}),
+            PostPass::Vignette {
+                strength,
+                radius,
+                softness,
+            } => {
+                self.draw(&POST_VIGNETTE, &[image], output, |program| {
+                    put(program, "strength", *strength);
+                    put(program, "radius", *radius);
+                    put(program, "softness", *softness);
+                });
+            }
+            PostPass::ToneMap {
+                exposure,
+                gamma,
+                mode,
+            } => {
+                self.draw(&POST_TONEMAP, &[image], output, |program| {
+                    put(program, "exposure", *exposure);
+                    put(program, "gamma", *gamma);
+                    put(program, "mode", *mode as i32);
+                });
+            }
+        }
+    }
+    //横着画进scratch,再竖着画进into
+    fn blur(&self, input: usize, scratch: usize, into: Option<&FrameBuffer>, radius: f32) {
+        let texel = radius / self.texture(input).vec2();
+        for (from, into, direction) in [
+            (input, Some(&self.targets[scratch]), vec2(texel.x, 0f32)),
+            (scratch, into, vec2(0f32, texel.y)),
+        ] {
+            self.draw(&POST_BLUR, &[self.texture(from)], into, |program| {
+                put(program, "direction", direction);
+            });
+        }
+    }
+    fn draw(
+        &self,
+        program: &Program,
+        textures: &[&Texture2D],
+        target: Option<&FrameBuffer>,
+        setup: impl FnOnce(&Program),
+    ) {
+        match target {
+            Some(target) => {
+                target.bind(gl::FRAMEBUFFER);
+                target.view_port();
+            }
+            None => {
+                FrameBuffer::unbind();
+                state::viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+            }
+        }
+        program.bind();
+        for (unit, texture) in textures.iter().enumerate() {
+            texture.bind_unit(unit as u32);
+        }
+        put(program, "image", Sampler(0));
+        put(program, "texel", Vec2::ONE / textures[0].vec2());
+        setup(program);
+        self.vao
+            .bind(|vao| vao.draw_arrays(DrawMode::Triangles, 0, 3));
+    }
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use crate::gl_unit::golden::with_headless;
+
+    use super::{PostPass, PostProcessChain};
+
+    #[test]
+    fn blur_and_vignette() {
+        with_headless(32, 32, |context| {
+            let mut chain = PostProcessChain::new(32, 32)
+                .unwrap()
+                .pass(PostPass::blur(2f32))
+                .pass(PostPass::vignette(1f32));
+            chain.begin((32, 32)).unwrap();
+            unsafe {
+                gl::ClearColor(1f32, 1f32, 1f32, 1f32);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+            chain.end_into(context.frame_buffer.as_ref());
+            let image = context.read_image().unwrap().to_rgba8();
+            let center = image.get_pixel(16, 16).0;
+            assert!(center[0] > 250);
+            assert!(image.get_pixel(0, 0).0[0] < center[0]);
+        });
+    }
+}
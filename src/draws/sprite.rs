@@ -0,0 +1,276 @@
+use std::{hash::Hash, ops::Deref, sync::LazyLock};
+
+use gl::types::{GLenum, GLuint};
+use glam::{Mat2, Mat4, Vec2, Vec4, vec2};
+
+use crate::{
+    STREAM_VERTEX,
+    draws::window_ort,
+    gl_unit::{
+        self, ConstBlend, VertexArray,
+        state,
+        define::{TextureParm, TextureType, VertexArrayAttribPointerGen},
+        program::Program,
+        texture::{Texture2D, TextureMap, TextureWrapper, UVindex},
+    },
+    setter_gen,
+};
+
+const SPRITE_VERT: &str = include_str!("../../shaders/sprite/vert.glsl");
+const SPRITE_FRAG: &str = include_str!("../../shaders/sprite/frag.glsl");
+
+pub static SPRITE_PROGRAM: LazyLock<Program> =
+    LazyLock::new(|| Program::basic_new(SPRITE_VERT, SPRITE_FRAG, None));
+
+//纯色quad用的1x1白图
+pub static WHITE_TEXTURE: LazyLock<TextureWrapper<Texture2D>> = LazyLock::new(|| {
+    TextureWrapper(Texture2D::load(
+        Some([255u8; 4].as_slice()),
+        TextureType::RGBA8,
+        1,
+        1,
+        TextureParm::new(),
+    ))
+});
+
+//pos vec2 + uv vec2 + color vec4
+const VERTEX_FLOATS: usize = 8;
+const QUAD_FLOATS: usize = VERTEX_FLOATS * 4;
+const VERTEX_STRIDE: i32 = (VERTEX_FLOATS * size_of::<f32>()) as i32;
+const FULL_UV: [f32; 8] = [0f32, 1f32, 1f32, 1f32, 1f32, 0f32, 0f32, 0f32];
+
+pub struct Sprite {
+    pub texture: GLuint,
+    //左下角
+    pub pos: Vec2,
+    pub size: Vec2,
+    //和 UVindex::get_uv 一样的顶点顺序
+    pub uv: [f32; 8],
+    //左上,右上,右下,左下
+    pub colors: [Vec4; 4],
+    //弧度,绕origin转
+    pub rotation: f32,
+    //旋转中心,按size归一化
+    pub origin: Vec2,
+    pub z: f32,
+    pub blend: ConstBlend,
+}
+setter_gen! {
+    impl Sprite{
+        uv: [f32; 8],
+        colors: [Vec4; 4],
+        rotation: f32,
+        origin: Vec2,
+        z: f32,
+        blend: ConstBlend
+    }
+}
+impl Sprite {
+    pub fn new(texture: &Texture2D, pos: Vec2, size: Vec2) -> Self {
+        Self {
+            texture: texture.texture,
+            pos,
+            size,
+            uv: FULL_UV,
+            colors: [Vec4::ONE; 4],
+            rotation: 0f32,
+            origin: Vec2::ZERO,
+            z: 0f32,
+            blend: ConstBlend::Normal,
+        }
+    }
+    pub fn fill(pos: Vec2, size: Vec2, color: Vec4) -> Self {
+        Self::new(&WHITE_TEXTURE, pos, size).color(color)
+    }
+    pub fn from_atlas<T: Hash + Eq>(map: &TextureMap<T>, name: &T, pos: Vec2) -> Option<Self> {
+        let uv = map.get_uv(name)?;
+        let (w, h) = uv.get_pixel_size(map);
+        Some(Self::new(map.get_tex(), pos, vec2(w, h)).uv_index(uv))
+    }
+    pub fn uv_index(mut self, uv: UVindex) -> Self {
+        self.uv = uv.get_uv();
+        self
+    }
+    pub fn color(mut self, color: Vec4) -> Self {
+        self.colors = [color; 4];
+        self
+    }
+    fn corners(&self) -> [Vec2; 4] {
+        let pivot = self.origin * self.size;
+        let rotate = Mat2::from_angle(self.rotation);
+        let (w, h) = (self.size.x, self.size.y);
+        [vec2(0f32, h), vec2(w, h), vec2(w, 0f32), vec2(0f32, 0f32)]
+            .map(|corner| self.pos + pivot + rotate * (corner - pivot))
+    }
+}
+
+struct Quad {
+    texture: GLuint,
+    blend: ConstBlend,
+    z: f32,
+    vertex: [f32; QUAD_FLOATS],
+}
+impl Quad {
+    //ConstBlend带Custom,按实际的gl混合因子比较
+    fn blend_key(&self) -> (GLenum, GLenum) {
+        let (src, dst) = self.blend.blend();
+        (src.as_gl(), dst.as_gl())
+    }
+}
+
+//攒一帧的quad,flush时按(z,混合,纹理)排序,同一z里每种纹理/混合只draw一次
+pub struct SpriteBatch {
+    quads: Vec<Quad>,
+    vao: VertexArray,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self {
+            quads: Vec::new(),
+            vao: VertexArray::new(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.quads.clear();
+    }
+    pub fn push(&mut self, sprite: Sprite) {
+        let corners = sprite.corners();
+        self.push_quad(
+            sprite.texture,
+            sprite.blend,
+            sprite.z,
+            corners,
+            sprite.uv,
+            sprite.colors,
+        );
+    }
+    //任意四边形,顶点顺序同 Sprite::corners
+    pub fn push_quad(
+        &mut self,
+        texture: GLuint,
+        blend: ConstBlend,
+        z: f32,
+        corners: [Vec2; 4],
+        uv: [f32; 8],
+        colors: [Vec4; 4],
+    ) {
+        let mut vertex = [0f32; QUAD_FLOATS];
+        for (index, chunk) in vertex.chunks_exact_mut(VERTEX_FLOATS).enumerate() {
+            let (pos, color) = (corners[index], colors[index]);
+            chunk.copy_from_slice(&[
+                pos.x,
+                pos.y,
+                uv[index * 2],
+                uv[index * 2 + 1],
+                color.x,
+                color.y,
+                color.z,
+                color.w,
+            ]);
+        }
+        self.quads.push(Quad {
+            texture,
+            blend,
+            z,
+            vertex,
+        });
+    }
+    pub fn flush_window(&mut self, window_size: (i32, i32)) {
+        self.flush(window_ort(window_size));
+    }
+    //z小的先画,z相同的按混合和纹理归到一起,同一组里保持push顺序(稳定排序)
+    pub fn flush(&mut self, project: Mat4) {
+        if self.quads.is_empty() {
+            return;
+        }
+        self.quads.sort_by(|a, b| {
+            a.z.total_cmp(&b.z)
+                .then_with(|| a.blend_key().cmp(&b.blend_key()))
+                .then_with(|| a.texture.cmp(&b.texture))
+        });
+
+        let vertex: Vec<f32> = self.quads.iter().flat_map(|quad| quad.vertex).collect();
+        let first = STREAM_VERTEX.push_vertex(&vertex, VERTEX_FLOATS);
+
+        let program = SPRITE_PROGRAM.deref();
+        program.bind();
+        program.put_matrix_name(project, "project_mat");
+        program.put_texture(0, program.get_uniform("image"));
+        state::active_texture(0);
+        self.vao.bind(|vao| {
+            for (index, len) in [(0, 2), (1, 2), (2, 4)] {
+                vao.bind_pointer(
+                    STREAM_VERTEX.deref(),
+                    VertexArrayAttribPointerGen::new::<f32>(index, len)
+                        .stride_size(VERTEX_STRIDE)
+                        .pointer([0, 2, 4][index as usize] * size_of::<f32>()),
+                );
+            }
+            let mut start = 0;
+            for group in self
+                .quads
+                .chunk_by(|a, b| a.texture == b.texture && a.blend_key() == b.blend_key())
+            {
+                gl_unit::const_blend(group[0].blend);
+                state::bind_texture(gl::TEXTURE_2D, group[0].texture);
+                vao.draw_quads(first + start as i32 * 4, group.len() as i32);
+                start += group.len();
+            }
+        });
+        self.quads.clear();
+    }
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "mock-gl"))]
+mod test {
+    use glam::{Mat4, Vec2, Vec4};
+
+    use crate::gl_unit::{
+        ConstBlend,
+        define::{TextureParm, TextureType},
+        mock,
+        texture::Texture2D,
+    };
+
+    use super::{Sprite, SpriteBatch};
+
+    #[test]
+    fn one_draw_per_texture_and_blend() {
+        mock::load();
+        let texture = || {
+            Texture2D::load(
+                Some([255u8; 4].as_slice()),
+                TextureType::RGBA8,
+                1,
+                1,
+                TextureParm::new(),
+            )
+        };
+        let (a, b) = (texture(), texture());
+        let mut batch = SpriteBatch::new();
+        //同一z里纹理交错,排序后A一组B一组
+        for texture in [&a, &b, &a, &b] {
+            batch.push(Sprite::new(texture, Vec2::ZERO, Vec2::ONE));
+        }
+        batch.push(Sprite::new(&a, Vec2::ZERO, Vec2::ONE).blend(ConstBlend::Additive));
+        //z不同的要单独画
+        batch.push(Sprite::fill(Vec2::ZERO, Vec2::ONE, Vec4::ONE).z(1f32));
+        mock::take();
+        batch.flush(Mat4::IDENTITY);
+        assert_eq!(mock::draw_calls(), 4);
+        assert!(batch.is_empty());
+    }
+}
@@ -1,60 +1,76 @@
 use define::*;
 
 use gl::types::{GLenum, GLint, GLsizei, GLuint};
-use glam::IVec2;
 use glfw::Context;
-use image::ImageFormat;
+use image::{DynamicImage, ImageFormat};
 
-use core::panic;
 use std::path::Path;
+use std::sync::Mutex;
 use std::ptr::null;
 
 use std::ffi::c_void;
-use texture::{Texture, Texture2D, TextureWrapper};
+use texture::{Texture2D, TextureWrapper};
 
+pub mod block;
 pub mod buffer;
+pub mod caps;
+pub mod compressed;
 pub mod debug;
 pub mod define;
+pub mod error;
+pub mod framebuffer;
+pub mod golden;
+#[cfg(feature = "mock-gl")]
+pub mod mock;
+pub mod pipeline;
+pub mod profiler;
 pub mod program;
+pub mod query;
+pub mod sampler;
+pub mod state;
+pub mod stream;
 pub mod texture;
+pub mod vertex;
 pub mod window;
 
+use error::{GlError, GlResult};
+pub use framebuffer::{FrameBuffer, RenderBuffer};
+use vertex::{Vertex, VertexAttrib};
 use window::Window;
 
-use crate::Buffer;
-
-#[allow(unused_variables)]
-extern "system" fn debug_callback(
-    source: gl::types::GLenum,
-    gltype: gl::types::GLenum,
-    id: gl::types::GLuint,
-    severity: gl::types::GLenum,
-    length: gl::types::GLsizei,
-    message: *const gl::types::GLchar,
-    user_param: *mut std::ffi::c_void,
-) {
-    let msg = unsafe { std::ffi::CStr::from_ptr(message).to_string_lossy() };
-
-    if severity == gl::DEBUG_SEVERITY_HIGH {
-        eprintln!("[OpenGL HIGH] {}", msg);
-    } else {
-        println!("[OpenGL] {}", msg);
-    }
-}
+use crate::{Buffer, BufferConst, DrawArraysCommand, DrawElementsCommand};
 
 pub fn view_port(x: i32, y: i32, w: i32, h: i32) {
-    unsafe {
-        gl::Viewport(x, y, w, h);
-    }
+    state::viewport(x, y, w, h);
 }
 
-pub struct GLcontext {}
+pub struct GLcontext {
+    //先于headless窗口drop
+    pub frame_buffer: Option<FrameBuffer>,
+    headless: Option<Window>,
+}
 unsafe impl Send for GLcontext {}
 
+fn load_gl(window: &mut Window) {
+    window.window.make_current();
+    gl::load_with(|s| {
+        window
+            .window
+            .glfw
+            .get_proc_address_raw(s)
+            .map_or(null(), |proc| proc as *const c_void)
+    });
+    caps::detect();
+    state::reset();
+    state::query();
+    //release里要的话自己调debug::enable_output
+    #[cfg(debug_assertions)]
+    debug::enable_output();
+}
+
 impl GLcontext {
     pub fn with(window: &mut Window) -> Self {
-        window.window.make_current();
-        gl::load_with(|s| window.window.glfw.get_proc_address_raw(s));
+        load_gl(window);
         let mut screen_w = 0;
         let mut resize_h = 0;
 
@@ -70,17 +86,56 @@ impl GLcontext {
 
         let pos_x = (screen_w as f32 - width) / 2.0f32;
         if window.window.is_maximized() {
-            unsafe {
-                gl::Viewport(pos_x as GLint, 0, width as GLsizei, resize_h as GLsizei);
-            }
+            state::viewport(pos_x as GLint, 0, width as GLsizei, resize_h as GLsizei);
         }
-        #[cfg(debug_assertions)]
-        unsafe {
-            gl::Enable(gl::DEBUG_OUTPUT);
-            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
-            gl::DebugMessageCallback(Some(debug_callback), null());
+        Self {
+            frame_buffer: None,
+            headless: None,
+        }
+    }
+    //离屏context,默认画到frame_buffer里
+    pub fn headless(w: u32, h: u32) -> Self {
+        let mut window = Window::headless(w as usize, h as usize);
+        load_gl(&mut window);
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer.link_texture(
+            TextureWrapper(Texture2D::with_size(
+                w,
+                h,
+                TextureType::RGBA8,
+                TextureParm::new(),
+            )),
+            gl::COLOR_ATTACHMENT0,
+        );
+        frame_buffer.link_buffer(w, h, gl::DEPTH_STENCIL_ATTACHMENT, gl::DEPTH24_STENCIL8);
+        frame_buffer.view_port();
+        Self {
+            frame_buffer: Some(frame_buffer),
+            headless: Some(window),
         }
-        Self {}
+    }
+    pub fn is_headless(&self) -> bool {
+        self.headless.is_some()
+    }
+    //headless时绑定默认frame buffer
+    pub fn bind_target(&self) {
+        match self.frame_buffer.as_ref() {
+            Some(frame_buffer) => {
+                frame_buffer.bind(gl::FRAMEBUFFER);
+                frame_buffer.view_port();
+            }
+            None => FrameBuffer::unbind(),
+        }
+    }
+    pub fn read_image(&self) -> Option<DynamicImage> {
+        finish();
+        Some(self.frame_buffer.as_ref()?.texture()?.get_image())
+    }
+    pub fn draw_headless(&mut self, func: impl FnOnce(&mut GLcontext)) {
+        self.bind_target();
+        self.base_clear();
+        func(self);
+        finish();
     }
     pub fn base_clear(&self) {
         unsafe {
@@ -88,9 +143,24 @@ impl GLcontext {
         }
     }
     pub fn view_size(&self, x: i32, y: i32, w: i32, h: i32) {
-        unsafe {
-            gl::Viewport(x, y, w, h);
-        }
+        state::viewport(x, y, w, h);
+    }
+    //缓存里记录的当前状态
+    pub fn state(&self) -> state::RenderState {
+        state::current()
+    }
+    pub fn push_state(&self) {
+        state::push();
+    }
+    pub fn pop_state(&self) {
+        state::pop();
+    }
+    //func里改的program/fbo/blend/viewport等,返回后还原
+    pub fn with_state<T>(&mut self, func: impl FnOnce(&mut GLcontext) -> T) -> T {
+        state::push();
+        let value = func(self);
+        state::pop();
+        value
     }
 
     pub fn draw(&mut self, window: &mut Window, func: impl FnOnce(&mut GLcontext, &mut Window)) {
@@ -108,193 +178,49 @@ impl GLcontext {
     }
 }
 
-pub struct RenderBuffer {
-    pub w: u32,
-    pub h: u32,
-    pub render_buffer: GLuint,
-}
-
-impl RenderBuffer {
-    pub fn new(type_: GLenum, w: u32, h: u32) -> Self {
-        let mut id = 0;
-        unsafe {
-            gl::GenRenderbuffers(1, &mut id);
-            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
-            gl::RenderbufferStorage(gl::RENDERBUFFER, type_, w as GLsizei, h as GLsizei);
-        }
-        RenderBuffer {
-            w,
-            h,
-            render_buffer: id,
-        }
-    }
-}
-
 pub fn debug_frame_buffer(frame_buffer: &FrameBuffer) {
-    if let Some(texture) = frame_buffer.texture.as_ref() {
+    if let Some(texture) = frame_buffer.texture() {
         let image = texture.get_image();
         image
             .save_with_format(Path::new("./debug/framebuffer.png"), ImageFormat::Png)
             .expect("err save framebuffer");
     }
 }
-pub struct FrameBuffer {
-    pub frame_buffer: GLuint,
-    pub render: Option<RenderBuffer>,
-    pub texture: Option<TextureWrapper<Texture2D>>,
-}
-
-impl Default for FrameBuffer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl FrameBuffer {
-    pub fn new() -> Self {
-        let mut id = 0;
-        unsafe {
-            gl::GenFramebuffers(1, &mut id);
-        }
-        Self {
-            frame_buffer: id,
-            texture: None,
-            render: None,
-        }
-    }
-    pub fn view_port(&self) {
-        unsafe {
-            if let Some(texture) = self.texture.as_ref() {
-                gl::Viewport(0, 0, texture.w as GLsizei, texture.h as GLsizei);
-            }
-            if let Some(render) = self.render.as_ref() {
-                gl::Viewport(0, 0, render.w as GLsizei, render.h as GLsizei);
-            }
-        }
-    }
-    pub fn link_texture(
-        &mut self,
-        texture: texture::TextureWrapper<Texture2D>,
-        attachment: GLenum,
-    ) {
-        unsafe {
-            self.bind(gl::FRAMEBUFFER);
-            texture.send_to_texture();
-            self.texture = Option::Some(texture);
-            gl::FramebufferTexture2D(
-                gl::FRAMEBUFFER,
-                attachment,
-                gl::TEXTURE_2D,
-                self.texture.as_ref().unwrap().texture,
-                0,
-            );
-            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
-            if status != gl::FRAMEBUFFER_COMPLETE {
-                panic!("framebuffer error:{}", status)
-            }
-        }
-    }
-    pub fn link_buffer(&mut self, w: u32, h: u32, attachment: GLenum, _type: GLenum) {
-        self.bind(gl::FRAMEBUFFER);
-        let buffer = RenderBuffer::new(_type, w, h);
-        let id = buffer.render_buffer;
-        self.render = Some(buffer);
-        unsafe {
-            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, id);
-        }
-    }
-    pub fn bind(&self, type_: GLenum) {
-        unsafe {
-            gl::BindFramebuffer(type_, self.frame_buffer);
-        }
-    }
-    pub fn clear(&self) {
-        self.bind(gl::FRAMEBUFFER);
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-        }
-    }
-    pub fn get_size(&self) -> Option<IVec2> {
-        if let Some(texture) = self.texture.as_ref() {
-            return Some(IVec2::new(texture.w as i32, texture.h as i32));
-        }
-        if let Some(render) = self.render.as_ref() {
-            return Some(IVec2::new(render.w as i32, render.h as i32));
-        }
-        None
-    }
 
-    pub fn unbind() {
-        unsafe {
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-        }
-    }
 
-    pub fn blit(
-        &self,
-        other: &Self,
-        src_down: IVec2,
-        src_up: IVec2,
-        dst_down: IVec2,
-        dst_up: IVec2,
-        attachment: GLenum,
-        mode: GLenum,
-    ) {
-        self.bind(gl::READ_FRAMEBUFFER);
-        other.bind(gl::DRAW_FRAMEBUFFER);
-        unsafe {
-            gl::BlitFramebuffer(
-                src_down.x, src_down.y, src_up.x, src_up.y, dst_down.x, dst_down.y, dst_up.x,
-                dst_up.y, attachment, mode,
-            );
-        }
-    }
-    pub fn blit_all(
-        &self,
-        other: &Self,
-        src_size: IVec2,
-        dst_size: IVec2,
-        attachment: GLenum,
-        mode: GLenum,
-    ) {
-        self.blit(
-            other,
-            IVec2::new(0, src_size.y),
-            IVec2::new(src_size.x, 0),
-            IVec2::new(0, dst_size.y),
-            IVec2::new(dst_size.x, 0),
-            attachment,
-            mode,
-        )
-    }
-
-    // pub fn get_now_bind_id() -> GLint {
-    //     unsafe {
-    //         let mut id: GLint = 0;
-    //         gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut id as *mut GLint);
-    //         id
-    //     }
-    // }
-    // pub fn get_now_attachment_id(attachment: GLenum) -> GLint {
-    //     // self.bind(gl::FRAMEBUFFER);
-    //     unsafe {
-    //         let mut id: GLint = 0;
-    //         gl::GetFramebufferAttachmentParameteriv(
-    //             gl::FRAMEBUFFER,
-    //             attachment,
-    //             gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME,
-    //             &mut id as *mut GLint,
-    //         );
-    //         id
-    //     }
-    // }
+//vao上配过的一个属性指针,没有base vertex时靠它挪指针模拟
+#[derive(Clone, Copy)]
+struct BoundAttrib {
+    buffer: GLuint,
+    attrib: VertexAttrib,
+    stride: GLsizei,
+    divisor: u32,
 }
-
-impl Drop for FrameBuffer {
-    fn drop(&mut self) {
-        Self::unbind();
+impl BoundAttrib {
+    //shift按顶点/实例个数
+    unsafe fn pointer(&self, shift: isize) {
+        let attrib = &self.attrib;
+        let pointer = (attrib.offset as isize + shift * self.stride as isize) as *const c_void;
         unsafe {
-            gl::DeleteFramebuffers(1, &self.frame_buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+            if attrib.integer {
+                gl::VertexAttribIPointer(
+                    attrib.index,
+                    attrib.len,
+                    attrib.type_,
+                    self.stride,
+                    pointer,
+                );
+            } else {
+                gl::VertexAttribPointer(
+                    attrib.index,
+                    attrib.len,
+                    attrib.type_,
+                    if attrib.normalized { gl::TRUE } else { gl::FALSE },
+                    self.stride,
+                    pointer,
+                );
+            }
         }
     }
 }
@@ -302,6 +228,9 @@ impl Drop for FrameBuffer {
 pub struct VertexArray {
     array_id: GLuint,
     pub element_type: Option<GLenum>,
+    //element_bind绑的索引buffer,draw_quads借用完要换回来
+    element_id: GLuint,
+    attribs: Mutex<Vec<BoundAttrib>>,
 }
 impl VertexArray {
     pub fn new() -> Self {
@@ -312,6 +241,29 @@ impl VertexArray {
         Self {
             array_id: id,
             element_type: None,
+            element_id: 0,
+            attribs: Mutex::new(Vec::new()),
+        }
+    }
+
+    //一个交错的顶点buffer配好所有属性
+    pub fn from_layout<V: Vertex + 'static>(buffer: &BufferConst<V>) -> Self {
+        let vao = Self::new();
+        vao.bind(|vao| vao.bind_layout(buffer, 0));
+        vao
+    }
+    //要在bind里调用;divisor给1就是每实例一份的数据
+    pub fn bind_layout<V: Vertex + 'static>(&self, buffer: &BufferConst<V>, divisor: u32) {
+        if buffer.target() != BufferTarget::Vertex {
+            panic!("[VAO err]buffer target != vertex");
+        }
+        for attrib in V::ATTRIBS {
+            self.set_attrib(BoundAttrib {
+                buffer: buffer.id(),
+                attrib: *attrib,
+                stride: size_of::<V>() as GLsizei,
+                divisor,
+            });
         }
     }
 
@@ -322,6 +274,7 @@ impl VertexArray {
 
         println!("element bind:{}", data.id());
         self.element_type = Some(data.type_as_gl());
+        self.element_id = data.id();
     }
     pub fn bind_pointer(&self, date: &dyn Buffer, pointer: VertexArrayAttribPointerGen) {
         {
@@ -329,49 +282,122 @@ impl VertexArray {
                 panic!("[VAO err]buffer target != vertex");
             }
         }
-        date.bind_target();
-
-        let (index, once_size, is_normalized, stride, pointer) = (
-            pointer.index,
-            pointer.len,
-            pointer.is_normalized,
-            pointer.stride_size,
-            pointer.pointer,
-        );
-
+        self.set_attrib(BoundAttrib {
+            buffer: date.id(),
+            attrib: VertexAttrib {
+                index: pointer.index,
+                len: pointer.len,
+                type_: date.type_as_gl(),
+                normalized: pointer.is_normalized,
+                integer: false,
+                offset: pointer.pointer,
+            },
+            stride: pointer.stride_size,
+            divisor: pointer.divisor,
+        });
+    }
+    fn set_attrib(&self, bound: BoundAttrib) {
+        let index = bound.attrib.index;
         unsafe {
             gl::EnableVertexAttribArray(index);
-            gl::VertexAttribPointer(
-                index,
-                once_size,
-                date.type_as_gl(),
-                if is_normalized { gl::TRUE } else { gl::FALSE },
-                stride,
-                pointer as *const c_void,
-            );
+            bound.pointer(0);
+            gl::VertexAttribDivisor(index, bound.divisor);
+        }
+        let mut attribs = self.attribs.lock().unwrap();
+        attribs.retain(|attrib| attrib.attrib.index != index);
+        attribs.push(bound);
+    }
+    //要在bind里调用
+    pub fn disable_pointer(&self, index: u32) {
+        unsafe {
+            gl::DisableVertexAttribArray(index);
+        }
+        self.attribs
+            .lock()
+            .unwrap()
+            .retain(|attrib| attrib.attrib.index != index);
+    }
+    //把每顶点的属性挪base_vertex个顶点、每实例的挪base_instance个实例再画,画完挪回来
+    fn rebased(&self, base_vertex: i32, base_instance: u32, draw: impl FnOnce()) {
+        if base_vertex == 0 && base_instance == 0 {
+            draw();
+            return;
+        }
+        let attribs = self.attribs.lock().unwrap();
+        let shift = |bound: &BoundAttrib| {
+            if bound.divisor == 0 {
+                base_vertex as isize
+            } else {
+                base_instance as isize
+            }
+        };
+        unsafe {
+            for bound in attribs.iter() {
+                bound.pointer(shift(bound));
+            }
+            draw();
+            for bound in attribs.iter() {
+                bound.pointer(0);
+            }
         }
     }
 
+    //func里的gl错误(debug的panic_on_error)在返回前panic
     pub fn bind(&self, func: impl FnOnce(&Self)) {
         Self::bind_id(self.array_id);
         func(self);
+        debug::raise_pending();
         Self::bind_id(0);
     }
     pub fn bind_mut(&mut self, func: impl FnOnce(&mut Self)) {
         Self::bind_id(self.array_id);
         func(self);
+        debug::raise_pending();
         Self::bind_id(0);
     }
     fn bind_id(id: u32) {
-        unsafe {
-            gl::BindVertexArray(id);
-        }
+        state::bind_vertex_array(id);
+    }
+    pub fn set_label(&self, label: &str) {
+        debug::set_label(gl::VERTEX_ARRAY, self.array_id, label);
     }
     pub fn draw_arrays(&self, mode: DrawMode, offset: i32, vertex_count: i32) {
         unsafe {
             gl::DrawArrays(mode.as_gl(), offset, vertex_count);
         }
     }
+    //画连续的quad(每个4个顶点,左上/右上/右下/左下绕序),借用共享的quad索引,画完换回element_bind的
+    pub fn draw_quads(&self, first_vertex: i32, quads: i32) {
+        crate::QUAD_INDEX.bind_target();
+        let base_vertex = caps::caps().has_base_vertex();
+        let mut drawn = 0;
+        while drawn < quads {
+            let count = (quads - drawn).min(crate::QUAD_INDEX_MAX as i32);
+            let first = first_vertex + drawn * 4;
+            if base_vertex {
+                unsafe {
+                    gl::DrawElementsBaseVertex(
+                        gl::TRIANGLES,
+                        count * 6,
+                        gl::UNSIGNED_INT,
+                        null(),
+                        first,
+                    );
+                }
+            } else {
+                self.rebased(first, 0, || unsafe {
+                    gl::DrawElements(gl::TRIANGLES, count * 6, gl::UNSIGNED_INT, null());
+                });
+            }
+            drawn += count;
+        }
+        //索引buffer的绑定是vao的状态,没有自己的就不用换回0
+        if self.element_id != 0 {
+            unsafe {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.element_id);
+            }
+        }
+    }
     pub fn draw_element(&self, mode: DrawMode, offset: u32, count: i32) {
         unsafe {
             gl::DrawElements(
@@ -382,25 +408,200 @@ impl VertexArray {
             );
         }
     }
+    //base_instance要gl4.2,没有时挪每实例属性的指针模拟
+    pub fn draw_arrays_instanced(
+        &self,
+        mode: DrawMode,
+        offset: i32,
+        vertex_count: i32,
+        instances: i32,
+        base_instance: u32,
+    ) {
+        let (base_instance, shift_instance) =
+            split_base(base_instance, caps::caps().has_base_instance());
+        self.rebased(0, shift_instance, || unsafe {
+            if base_instance != 0 {
+                gl::DrawArraysInstancedBaseInstance(
+                    mode.as_gl(),
+                    offset,
+                    vertex_count,
+                    instances,
+                    base_instance,
+                );
+            } else {
+                gl::DrawArraysInstanced(mode.as_gl(), offset, vertex_count, instances);
+            }
+        });
+    }
+    //offset按字节,base_vertex加到每个索引上;context不支持的base vertex/base instance挪属性指针模拟
+    pub fn draw_elements_instanced(
+        &self,
+        mode: DrawMode,
+        offset: u32,
+        count: i32,
+        instances: i32,
+        base_vertex: i32,
+        base_instance: u32,
+    ) {
+        let type_ = self.element_type.expect("vao no element bind");
+        let indices = offset as *const c_void;
+        let caps = caps::caps();
+        let (base_vertex, shift_vertex) = split_base(base_vertex, caps.has_base_vertex());
+        let (base_instance, shift_instance) = split_base(base_instance, caps.has_base_instance());
+        self.rebased(shift_vertex, shift_instance, || unsafe {
+            if base_instance != 0 {
+                gl::DrawElementsInstancedBaseVertexBaseInstance(
+                    mode.as_gl(),
+                    count,
+                    type_,
+                    indices,
+                    instances,
+                    base_vertex,
+                    base_instance,
+                );
+            } else if base_vertex != 0 {
+                gl::DrawElementsInstancedBaseVertex(
+                    mode.as_gl(),
+                    count,
+                    type_,
+                    indices,
+                    instances,
+                    base_vertex,
+                );
+            } else {
+                gl::DrawElementsInstanced(mode.as_gl(), count, type_, indices, instances);
+            }
+        });
+    }
+    //commands[first..first+count]一次画完;没有multi draw时逐条glDrawArraysIndirect,都没有(gl4.0/es3.1以下)时报错
+    pub fn multi_draw_arrays_indirect(
+        &self,
+        mode: DrawMode,
+        commands: &BufferConst<DrawArraysCommand>,
+        first: usize,
+        count: usize,
+    ) -> GlResult<()> {
+        let caps = caps::caps();
+        if !caps.has_draw_indirect() {
+            return Err(GlError::Unsupported("glDrawArraysIndirect".to_string()));
+        }
+        //没有base instance时命令里的base_instance不生效,读回来逐条画,挪指针模拟
+        if !caps.has_base_instance() {
+            let list = commands.read(first, count)?;
+            if list.iter().any(|command| command.base_instance != 0) {
+                for command in list {
+                    self.draw_arrays_instanced(
+                        mode,
+                        command.first as i32,
+                        command.count as i32,
+                        command.instance_count as i32,
+                        command.base_instance,
+                    );
+                }
+                return Ok(());
+            }
+        }
+        let stride = size_of::<DrawArraysCommand>();
+        commands.bind_target();
+        unsafe {
+            if caps.has_multi_draw_indirect() {
+                gl::MultiDrawArraysIndirect(
+                    mode.as_gl(),
+                    (first * stride) as *const c_void,
+                    count as GLsizei,
+                    0,
+                );
+            } else {
+                for index in first..first + count {
+                    gl::DrawArraysIndirect(mode.as_gl(), (index * stride) as *const c_void);
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn multi_draw_elements_indirect(
+        &self,
+        mode: DrawMode,
+        commands: &BufferConst<DrawElementsCommand>,
+        first: usize,
+        count: usize,
+    ) -> GlResult<()> {
+        let caps = caps::caps();
+        if !caps.has_draw_indirect() {
+            return Err(GlError::Unsupported("glDrawElementsIndirect".to_string()));
+        }
+        let type_ = self.element_type.expect("vao no element bind");
+        //同multi_draw_arrays_indirect
+        if !caps.has_base_instance() {
+            let list = commands.read(first, count)?;
+            if list.iter().any(|command| command.base_instance != 0) {
+                let index_size = match type_ {
+                    gl::UNSIGNED_BYTE => 1,
+                    gl::UNSIGNED_SHORT => 2,
+                    _ => 4,
+                };
+                for command in list {
+                    self.draw_elements_instanced(
+                        mode,
+                        command.first_index * index_size,
+                        command.count as i32,
+                        command.instance_count as i32,
+                        command.base_vertex,
+                        command.base_instance,
+                    );
+                }
+                return Ok(());
+            }
+        }
+        let stride = size_of::<DrawElementsCommand>();
+        commands.bind_target();
+        unsafe {
+            if caps.has_multi_draw_indirect() {
+                gl::MultiDrawElementsIndirect(
+                    mode.as_gl(),
+                    type_,
+                    (first * stride) as *const c_void,
+                    count as GLsizei,
+                    0,
+                );
+            } else {
+                for index in first..first + count {
+                    gl::DrawElementsIndirect(
+                        mode.as_gl(),
+                        type_,
+                        (index * stride) as *const c_void,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//(交给gl的, 要自己挪指针模拟的)
+fn split_base<T: Default>(base: T, supported: bool) -> (T, T) {
+    if supported {
+        (base, T::default())
+    } else {
+        (T::default(), base)
+    }
 }
 
 impl Drop for VertexArray {
     fn drop(&mut self) {
         println!("VAO {} leave", self.array_id);
         unsafe {
-            gl::BindVertexArray(0);
             gl::DeleteVertexArrays(1, &self.array_id as *const GLuint);
         }
+        state::forget_vertex_array(self.array_id);
     }
 }
 
 fn blend(src: Blend, dst: Blend) {
-    unsafe {
-        gl::Enable(gl::BLEND);
-        gl::BlendFunc(src.as_gl(), dst.as_gl());
-    }
+    state::blend(Some(pipeline::BlendState::new(src, dst)));
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum ConstBlend {
     SrcOnly,
     // 正常混合 (src alpha, 1 - src alpha)
@@ -412,6 +613,7 @@ pub enum ConstBlend {
     // 屏幕混合 (1 - (1 - src color) * (1 - dst color))
     Screen,
     // 叠加混合 (根据底色决定 multiply 或 screen)
+    // 固定管线做不到按底色切换,这里只是近似,要准确得在shader里读底色
     Overlay,
     // 预乘 alpha 混合 (src alpha, 1)
     Premultiplied,
@@ -440,6 +642,18 @@ pub fn const_blend(b: ConstBlend) {
 }
 
 pub fn polygon_mode(face: Face, mode: PolygonMode) {
+    if let Err(err) = try_polygon_mode(face, mode) {
+        eprintln!("[polygon mode]{}", err);
+    }
+}
+//es里没有glPolygonMode,只能画fill
+pub fn try_polygon_mode(face: Face, mode: PolygonMode) -> GlResult<()> {
+    if !caps::caps().has_polygon_mode() {
+        return match mode {
+            PolygonMode::Fill => Ok(()),
+            _ => Err(GlError::Unsupported("glPolygonMode".to_string())),
+        };
+    }
     unsafe {
         match mode {
             PolygonMode::Fill => {}
@@ -448,6 +662,7 @@ pub fn polygon_mode(face: Face, mode: PolygonMode) {
         }
         gl::PolygonMode(face.as_gl(), mode.as_gl());
     }
+    Ok(())
 }
 
 pub fn flush() {
@@ -461,12 +676,129 @@ pub fn finish() {
     }
 }
 
-pub fn depth_test(value: bool) {
+pub fn memory_barrier(barriers: &[MemoryBarrier]) {
+    let bits = barriers
+        .iter()
+        .fold(0, |bits, barrier| bits | barrier.as_gl());
     unsafe {
-        if value {
-            gl::Enable(gl::DEPTH_TEST);
-        } else {
-            gl::Disable(gl::DEPTH_TEST);
-        }
+        gl::MemoryBarrier(bits);
+    }
+}
+
+pub fn depth_test(value: bool) {
+    state::depth_test(value);
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use super::{
+        GLcontext, VertexArray,
+        caps::{self, Caps, set_caps},
+        define::{BufferTarget, BufferUsage, DrawMode, VertexArrayAttribPointerGen},
+        golden::with_headless,
+        program::Program,
+    };
+    use crate::{BufferConst, DrawElementsCommand};
+
+    #[test]
+    fn headless() {
+        let image = with_headless(64, 32, |context| {
+            context.draw_headless(|_| unsafe {
+                gl::ClearColor(1f32, 0f32, 1f32, 1f32);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            });
+            context.read_image().unwrap().to_rgba8()
+        });
+        assert_eq!((image.width(), image.height()), (64, 32));
+        assert_eq!(image.get_pixel(10, 10).0, [255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn instanced_and_indirect() {
+        with_headless(64, 32, |context| {
+            let program = Program::basic_new(
+                "#version 330 core
+                layout(location = 0) in vec2 pos;
+                layout(location = 1) in vec2 offset;
+                void main() { gl_Position = vec4(pos + offset, 0.0, 1.0); }",
+                "#version 330 core
+                out vec4 color;
+                void main() { color = vec4(1.0, 0.0, 0.0, 1.0); }",
+                None,
+            );
+            //左边1/4宽的竖条,第二个实例往右挪半屏
+            let quad = BufferConst::new(
+                BufferTarget::Vertex,
+                &[-1f32, -1f32, -0.5, -1f32, -0.5, 1f32, -1f32, 1f32],
+                BufferUsage::Static,
+            );
+            let offsets = BufferConst::new(
+                BufferTarget::Vertex,
+                &[0f32, 0f32, 1f32, 0f32],
+                BufferUsage::Static,
+            );
+            let index = BufferConst::new(
+                BufferTarget::Element,
+                &[0u32, 1, 2, 2, 3, 0],
+                BufferUsage::Static,
+            );
+            let mut vao = VertexArray::new();
+            vao.element_bind(&index);
+            vao.bind(|vao| {
+                vao.bind_pointer(&quad, VertexArrayAttribPointerGen::new::<f32>(0, 2));
+                vao.bind_pointer(
+                    &offsets,
+                    VertexArrayAttribPointerGen::instanced::<f32>(1, 2),
+                );
+            });
+            let red = |context: &GLcontext| {
+                let image = context.read_image().unwrap().to_rgba8();
+                [8, 24, 40, 56].map(|x| image.get_pixel(x, 16).0[0] == 255)
+            };
+
+            context.draw_headless(|_| {
+                program.bind();
+                vao.bind(|vao| vao.draw_elements_instanced(DrawMode::Triangles, 0, 6, 2, 0, 0));
+            });
+            assert_eq!(red(context), [true, false, true, false]);
+
+            //只画第二个实例
+            let commands = BufferConst::new(
+                BufferTarget::DrawIndirect,
+                &[DrawElementsCommand {
+                    count: 6,
+                    instance_count: 1,
+                    first_index: 0,
+                    base_vertex: 0,
+                    base_instance: 1,
+                }],
+                BufferUsage::Static,
+            );
+            context.draw_headless(|_| {
+                program.bind();
+                vao.bind(|vao| {
+                    vao.multi_draw_elements_indirect(DrawMode::Triangles, &commands, 0, 1)
+                        .unwrap()
+                });
+            });
+            assert_eq!(red(context), [false, false, true, false]);
+
+            //gl4.1没有base instance,读回命令挪指针模拟,画出来一样
+            let real = caps::caps();
+            set_caps(Caps {
+                major: 4,
+                minor: 1,
+                ..real
+            });
+            context.draw_headless(|_| {
+                program.bind();
+                vao.bind(|vao| {
+                    vao.multi_draw_elements_indirect(DrawMode::Triangles, &commands, 0, 1)
+                        .unwrap()
+                });
+            });
+            set_caps(real);
+            assert_eq!(red(context), [false, false, true, false]);
+        });
     }
 }
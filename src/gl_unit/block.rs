@@ -0,0 +1,228 @@
+use glam::{IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+//uniform block 用std140,shader storage block 一般用std430
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlockLayout {
+    Std140,
+    Std430,
+}
+
+impl BlockLayout {
+    //数组元素和结构体在std140下要对齐到vec4
+    fn round(&self, align: usize) -> usize {
+        match self {
+            BlockLayout::Std140 => align_to(align, 16),
+            BlockLayout::Std430 => align,
+        }
+    }
+}
+
+const fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+pub trait BlockField {
+    //基准对齐(字节)
+    fn align(layout: BlockLayout) -> usize;
+    fn write(&self, layout: BlockLayout, out: &mut Vec<u8>);
+}
+
+macro_rules! block_scalar {
+    ($($type:ty: $align:expr),*) => {
+        $(
+            impl BlockField for $type {
+                fn align(_: BlockLayout) -> usize {
+                    $align
+                }
+                fn write(&self, _: BlockLayout, out: &mut Vec<u8>) {
+                    for value in self.to_array().iter() {
+                        out.extend_from_slice(&value.to_ne_bytes());
+                    }
+                }
+            }
+        )*
+    };
+}
+
+block_scalar! {
+    Vec2: 8, Vec3: 16, Vec4: 16,
+    IVec2: 8, IVec3: 16, IVec4: 16,
+    UVec2: 8, UVec3: 16, UVec4: 16
+}
+
+macro_rules! block_number {
+    ($($type:ty),*) => {
+        $(
+            impl BlockField for $type {
+                fn align(_: BlockLayout) -> usize {
+                    4
+                }
+                fn write(&self, _: BlockLayout, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_ne_bytes());
+                }
+            }
+        )*
+    };
+}
+
+block_number!(f32, i32, u32);
+
+//glsl的bool占4字节
+impl BlockField for bool {
+    fn align(_: BlockLayout) -> usize {
+        4
+    }
+    fn write(&self, layout: BlockLayout, out: &mut Vec<u8>) {
+        (*self as u32).write(layout, out);
+    }
+}
+
+//矩阵按列存,每列当成一个数组元素
+macro_rules! block_matrix {
+    ($($type:ty: $col:ty),*) => {
+        $(
+            impl BlockField for $type {
+                fn align(layout: BlockLayout) -> usize {
+                    layout.round(<$col>::align(layout))
+                }
+                fn write(&self, layout: BlockLayout, out: &mut Vec<u8>) {
+                    let stride = Self::align(layout);
+                    for col in self.to_cols_array().chunks_exact(size_of::<$col>() / 4) {
+                        let start = out.len();
+                        <$col>::from_slice(col).write(layout, out);
+                        out.resize(start + stride, 0);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+block_matrix!(Mat2: Vec2, Mat3: Vec3, Mat4: Vec4);
+
+//按glsl block的成员顺序依次写入,自动补齐padding
+pub struct BlockWriter {
+    layout: BlockLayout,
+    data: Vec<u8>,
+    max_align: usize,
+}
+
+impl BlockWriter {
+    pub fn new(layout: BlockLayout) -> Self {
+        Self {
+            layout,
+            data: Vec::new(),
+            max_align: 4,
+        }
+    }
+    pub fn std140() -> Self {
+        Self::new(BlockLayout::Std140)
+    }
+    pub fn std430() -> Self {
+        Self::new(BlockLayout::Std430)
+    }
+    fn align(&mut self, align: usize) {
+        self.max_align = self.max_align.max(align);
+        self.data.resize(align_to(self.data.len(), align), 0);
+    }
+    //当前写到的字节偏移
+    pub fn offset(&self) -> usize {
+        self.data.len()
+    }
+    pub fn field<T: BlockField>(&mut self, value: &T) -> &mut Self {
+        self.align(T::align(self.layout));
+        value.write(self.layout, &mut self.data);
+        self
+    }
+    pub fn array<T: BlockField>(&mut self, values: &[T]) -> &mut Self {
+        let stride = self.layout.round(T::align(self.layout));
+        self.align(stride);
+        for value in values.iter() {
+            let start = self.data.len();
+            value.write(self.layout, &mut self.data);
+            self.data.resize(align_to(self.data.len() - start, stride) + start, 0);
+        }
+        self
+    }
+    //嵌套结构体
+    pub fn structure<T: UniformBlock>(&mut self, value: &T) -> &mut Self {
+        let mut writer = BlockWriter::new(self.layout);
+        value.write_block(&mut writer);
+        let align = self.layout.round(writer.max_align);
+        self.align(align);
+        self.data.extend(writer.finish());
+        self
+    }
+    pub fn structure_array<T: UniformBlock>(&mut self, values: &[T]) -> &mut Self {
+        for value in values.iter() {
+            self.structure(value);
+        }
+        self
+    }
+    pub fn finish(mut self) -> Vec<u8> {
+        let align = self.layout.round(self.max_align);
+        self.align(align);
+        self.data
+    }
+}
+
+//给rust结构体实现,按glsl里声明的顺序调用writer
+pub trait UniformBlock {
+    fn write_block(&self, writer: &mut BlockWriter);
+    fn to_std140(&self) -> Vec<u8> {
+        let mut writer = BlockWriter::std140();
+        self.write_block(&mut writer);
+        writer.finish()
+    }
+    fn to_std430(&self) -> Vec<u8> {
+        let mut writer = BlockWriter::std430();
+        self.write_block(&mut writer);
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::{Mat3, Vec2, Vec3, Vec4};
+
+    use super::{BlockWriter, UniformBlock};
+
+    struct Light {
+        pos: Vec3,
+        power: f32,
+        weights: [f32; 2],
+    }
+
+    impl UniformBlock for Light {
+        fn write_block(&self, writer: &mut BlockWriter) {
+            writer
+                .field(&self.pos)
+                .field(&self.power)
+                .array(&self.weights);
+        }
+    }
+
+    #[test]
+    fn layout_offsets() {
+        let light = Light {
+            pos: Vec3::ONE,
+            power: 2.0,
+            weights: [1.0, 2.0],
+        };
+        //vec3后面的float可以塞进第四个分量;std140的float数组步长16
+        assert_eq!(light.to_std140().len(), 16 + 32);
+        assert_eq!(light.to_std430().len(), 16 + 8 + 8);
+
+        let mut writer = BlockWriter::std140();
+        writer.field(&1f32).field(&Vec2::ONE);
+        assert_eq!(writer.offset(), 16);
+        writer.field(&Mat3::IDENTITY);
+        assert_eq!(writer.offset(), 16 + 48);
+        writer.array(&[Vec4::ONE; 2]).structure(&light);
+        assert_eq!(writer.finish().len(), 16 + 48 + 32 + 48);
+
+        let mut writer = BlockWriter::std430();
+        writer.field(&1f32).array(&[Vec2::ONE; 3]);
+        assert_eq!(writer.offset(), 8 + 24);
+    }
+}
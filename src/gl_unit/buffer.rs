@@ -1,11 +1,12 @@
-use core::panic;
 use std::{ffi::c_void, marker::PhantomData, ops::Deref, ptr::null, sync::LazyLock};
 
 use gl::types::{GLenum, GLuint};
 
 use super::{
-    VertexArray,
+    VertexArray, debug,
     define::{BufferTarget, BufferUsage, TypeGL, VertexArrayAttribPointerGen},
+    error::{GlError, GlResult},
+    stream::StreamBuffer,
 };
 //do not edit
 // down left
@@ -39,6 +40,16 @@ pub static VAO_STATIC: LazyLock<VertexArray> = LazyLock::new(|| {
     vao
 });
 
+//core profile没有GL_QUADS,所有quad共用这份 0,1,2,2,3,0 的索引,配合base vertex画
+pub const QUAD_INDEX_MAX: usize = 1 << 16;
+pub static QUAD_INDEX: LazyLock<BufferConst<u32>> = LazyLock::new(|| {
+    BufferConst::from_iter(
+        BufferTarget::Element,
+        (0..QUAD_INDEX_MAX as u32).flat_map(|quad| [0, 1, 2, 2, 3, 0].map(|index| quad * 4 + index)),
+        BufferUsage::Static,
+    )
+});
+
 //mutable
 pub const VERTEX_BIG: usize = 2 * 4096;
 pub static VERTEX_BIG_MUT: LazyLock<BufferConst<f32>> = LazyLock::new(|| {
@@ -62,8 +73,42 @@ pub static TEX_VERTEX_MUT: LazyLock<BufferConst<f32>> = LazyLock::new(|| {
         BufferUsage::Dynamic,
     )
 });
+//每帧的动态顶点都往这里push,写满了自动orphan/扩容
+pub static STREAM_VERTEX: LazyLock<StreamBuffer<f32>> =
+    LazyLock::new(|| StreamBuffer::new(BufferTarget::Vertex, VERTEX_BIG));
 pub static VAO_MUT: LazyLock<VertexArray> = LazyLock::new(|| VertexArray::new());
 
+//glMultiDrawArraysIndirect的一条命令,放进BufferTarget::DrawIndirect的BufferConst里
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct DrawArraysCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first: u32,
+    //es上必须是0
+    pub base_instance: u32,
+}
+//glMultiDrawElementsIndirect的一条命令,first_index按索引个数不按字节
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct DrawElementsCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+impl TypeGL for DrawArraysCommand {
+    fn as_gl() -> GLenum {
+        gl::UNSIGNED_INT
+    }
+}
+impl TypeGL for DrawElementsCommand {
+    fn as_gl() -> GLenum {
+        gl::UNSIGNED_INT
+    }
+}
+
 pub trait Buffer {
     fn type_as_gl(&self) -> GLenum;
     fn target(&self) -> BufferTarget;
@@ -75,6 +120,27 @@ pub trait Buffer {
     fn unbind_target(&self) {
         bind_buffer(self.target(), 0);
     }
+    fn set_label(&self, label: &str) {
+        debug::set_label(gl::BUFFER, self.id(), label);
+    }
+    //只对 Uniform/ShaderStorage 这类带绑定点的target有效
+    fn bind_base(&self, index: u32) {
+        unsafe {
+            gl::BindBufferBase(self.target().as_gl(), index, self.id());
+        }
+    }
+    //offset/size按字节,uniform buffer的offset要对齐到 GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT
+    fn bind_range(&self, index: u32, offset: usize, size: usize) {
+        unsafe {
+            gl::BindBufferRange(
+                self.target().as_gl(),
+                index,
+                self.id(),
+                offset as isize,
+                size as isize,
+            );
+        }
+    }
 }
 fn bind_buffer(target: BufferTarget, id: GLuint) {
     unsafe {
@@ -160,18 +226,67 @@ impl<T: TypeGL + 'static> BufferConst<T> {
         unsafe { Self::new_raw(target, null(), len, usage) }
     }
     pub fn sub_data(&self, data: &[T], offset: usize) {
-        if data.len() > self.count() {
-            panic!("[sub data err]data's len > buffer");
+        if let Err(err) = self.try_sub_data(data, offset) {
+            panic!("[sub data err]{}", err);
+        }
+    }
+    //offset按字节
+    pub fn try_sub_data(&self, data: &[T], offset: usize) -> GlResult<()> {
+        let size = std::mem::size_of_val(data);
+        let capacity = self.count() * size_of::<T>();
+        if offset + size > capacity {
+            return Err(GlError::BufferOverflow {
+                offset,
+                size,
+                capacity,
+            });
         }
         self.bind_target();
         unsafe {
             gl::BufferSubData(
                 self.target().as_gl(),
                 offset as isize,
-                std::mem::size_of_val(data) as isize,
+                size as isize,
                 data.as_ptr() as *const c_void,
             );
         }
+        Ok(())
+    }
+    //读回[first..first+count],会等gpu用完这段
+    pub fn read(&self, first: usize, count: usize) -> GlResult<Vec<T>>
+    where
+        T: Copy,
+    {
+        let offset = first * size_of::<T>();
+        let size = count * size_of::<T>();
+        let capacity = self.count() * size_of::<T>();
+        if offset + size > capacity {
+            return Err(GlError::BufferOverflow {
+                offset,
+                size,
+                capacity,
+            });
+        }
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        self.bind_target();
+        let mut data = Vec::with_capacity(count);
+        unsafe {
+            let ptr = gl::MapBufferRange(
+                self.target().as_gl(),
+                offset as isize,
+                size as isize,
+                gl::MAP_READ_BIT,
+            );
+            if ptr.is_null() {
+                return Err(GlError::Unsupported("glMapBufferRange read".to_string()));
+            }
+            std::ptr::copy_nonoverlapping(ptr as *const T, data.as_mut_ptr(), count);
+            data.set_len(count);
+            gl::UnmapBuffer(self.target().as_gl());
+        }
+        Ok(data)
     }
 
     pub fn buffer_object(self) -> BufferObject {
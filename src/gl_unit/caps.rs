@@ -0,0 +1,211 @@
+use std::{cell::Cell, ffi::CStr};
+
+use gl::types::GLint;
+
+use super::texture::MAX_TEXTURE_MAX_ANISOTROPY;
+
+//当前context的能力,load_gl时检测一次;每个线程自己的context各存一份
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Caps {
+    //OpenGL ES / WebGL2
+    pub es: bool,
+    pub major: u32,
+    pub minor: u32,
+    //各向异性过滤上限,不支持时是0
+    pub max_anisotropy: f32,
+    //fbo颜色附件个数上限,查不到时按es3的最低要求4
+    pub max_color_attachments: u32,
+}
+
+thread_local! {
+    static CAPS: Cell<Option<Caps>> = const { Cell::new(None) };
+}
+
+impl Caps {
+    pub const fn desktop(major: u32, minor: u32) -> Self {
+        Self {
+            es: false,
+            major,
+            minor,
+            max_anisotropy: 0f32,
+            max_color_attachments: 4,
+        }
+    }
+    pub const fn at_least(&self, major: u32, minor: u32) -> bool {
+        self.major > major || (self.major == major && self.minor >= minor)
+    }
+    //glGetTexImage
+    pub const fn has_get_tex_image(&self) -> bool {
+        !self.es
+    }
+    pub const fn has_polygon_mode(&self) -> bool {
+        !self.es
+    }
+    pub const fn has_clamp_border(&self) -> bool {
+        !self.es || self.at_least(3, 2)
+    }
+    //glDrawElements*BaseVertex,es要3.2
+    pub const fn has_base_vertex(&self) -> bool {
+        self.at_least(3, 2)
+    }
+    //glDraw*BaseInstance,es没有
+    pub const fn has_base_instance(&self) -> bool {
+        !self.es && self.at_least(4, 2)
+    }
+    //glDraw*Indirect
+    pub const fn has_draw_indirect(&self) -> bool {
+        if self.es {
+            self.at_least(3, 1)
+        } else {
+            self.at_least(4, 0)
+        }
+    }
+    pub const fn has_multi_draw_indirect(&self) -> bool {
+        !self.es && self.at_least(4, 3)
+    }
+    //TIME_ELAPSED/TIMESTAMP,es只有扩展
+    pub const fn has_timer_query(&self) -> bool {
+        !self.es && self.at_least(3, 3)
+    }
+    pub const fn has_compute(&self) -> bool {
+        if self.es {
+            self.at_least(3, 1)
+        } else {
+            self.at_least(4, 3)
+        }
+    }
+}
+
+//"OpenGL ES 3.0 Mesa ..." / "4.6 (Core Profile) Mesa ..."
+pub(crate) fn parse_version(version: &str) -> Caps {
+    let (es, rest) = match version.strip_prefix("OpenGL ES") {
+        Some(rest) => (true, rest.trim_start_matches(|c: char| !c.is_ascii_digit())),
+        None => (false, version.trim_start()),
+    };
+    let mut numbers = rest
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|num| num.parse::<u32>().unwrap_or(0));
+    Caps {
+        es,
+        major: numbers.next().unwrap_or(0),
+        minor: numbers.next().unwrap_or(0),
+        max_anisotropy: 0f32,
+        max_color_attachments: 4,
+    }
+}
+
+pub fn detect() -> Caps {
+    let version = unsafe {
+        let ptr = gl::GetString(gl::VERSION);
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr as *const _).to_string_lossy().to_string()
+        }
+    };
+    let mut caps = parse_version(&version);
+    let (mut major, mut minor): (GLint, GLint) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    if major > 0 {
+        caps.major = major as u32;
+        caps.minor = minor as u32;
+    }
+    let mut attachments: GLint = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut attachments);
+    }
+    if attachments > 0 {
+        caps.max_color_attachments = attachments as u32;
+    }
+    //没有扩展时是INVALID_ENUM,值保持0
+    unsafe {
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut caps.max_anisotropy);
+        gl::GetError();
+    }
+    CAPS.set(Some(caps));
+    caps
+}
+
+pub fn caps() -> Caps {
+    CAPS.get().unwrap_or_else(detect)
+}
+//测试里假装是别的context,只影响当前线程
+#[cfg(test)]
+pub(crate) fn set_caps(caps: Caps) {
+    CAPS.set(Some(caps));
+}
+
+//把桌面glsl的#version换成es的,并补上默认精度;返回(新源码,多出来的行数)
+pub fn rewrite_version(code: &str, caps: Caps) -> (String, usize) {
+    if !caps.es {
+        return (code.to_string(), 0);
+    }
+    let trimmed = code.trim_start();
+    let (desktop, body) = match trimmed.strip_prefix("#version") {
+        Some(rest) => {
+            let (line, body) = rest.split_once('\n').unwrap_or((rest, ""));
+            (
+                line.split_whitespace()
+                    .next()
+                    .and_then(|num| num.parse::<u32>().ok()),
+                body,
+            )
+        }
+        None => (None, trimmed),
+    };
+    //#version必须在第一行,前面的空行挪到头后面,行号不会比原来少
+    let blank = code[..code.len() - trimmed.len()].matches('\n').count();
+    let version = match desktop {
+        Some(version) if version >= 430 || caps.at_least(3, 1) => "310 es",
+        _ => "300 es",
+    };
+    let header = format!(
+        "#version {}\nprecision highp float;\nprecision highp int;\nprecision highp sampler2DArray;\nprecision highp sampler3D;\n",
+        version
+    );
+    //插入的头减去被替换掉的原#version行
+    let added = header.lines().count() - usize::from(desktop.is_some());
+    (header + &"\n".repeat(blank) + body, added)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Caps, parse_version, rewrite_version};
+
+    #[test]
+    fn version_string() {
+        let caps = parse_version("OpenGL ES 3.0 Mesa 23.2.1");
+        assert!(caps.es);
+        assert_eq!((caps.major, caps.minor), (3, 0));
+        assert!(!caps.has_get_tex_image());
+        assert!(!caps.has_base_vertex());
+
+        let caps = parse_version("4.6 (Core Profile) Mesa 23.2.1");
+        assert_eq!(caps, Caps::desktop(4, 6));
+        assert!(caps.has_compute());
+    }
+
+    #[test]
+    fn rewrite_header() {
+        let code = "#version 330\nin vec2 uv;\nvoid main(){}";
+        let es = parse_version("OpenGL ES 3.0");
+        let (rewrite, added) = rewrite_version(code, es);
+        assert!(rewrite.starts_with("#version 300 es\nprecision highp float;"));
+        //原来第2行的 in vec2 uv 挪到了 2+added 行
+        assert_eq!(rewrite.lines().nth(1 + added), Some("in vec2 uv;"));
+
+        let (same, added) = rewrite_version(code, Caps::desktop(3, 3));
+        assert_eq!((same.as_str(), added), (code, 0));
+
+        //前面的空行比插入的头还多,行号也不能错
+        let padded = "\n".repeat(8) + code;
+        let (rewrite, added) = rewrite_version(&padded, es);
+        assert_eq!(rewrite.lines().nth(9 + added), Some("in vec2 uv;"));
+        //没有#version的只多出整个头
+        let (rewrite, added) = rewrite_version("\n\nin vec2 uv;", es);
+        assert_eq!(rewrite.lines().nth(2 + added), Some("in vec2 uv;"));
+    }
+}
@@ -0,0 +1,379 @@
+use std::{fs, path::Path};
+
+use gl::types::GLenum;
+
+use super::error::{GlError, GlResult};
+
+//s3tc和astc只有扩展,gl crate里没有这几个常量
+const COMPRESSED_RGB_S3TC_DXT1: GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+const COMPRESSED_RGBA_ASTC_4X4: GLenum = 0x93B0;
+const COMPRESSED_RGBA_ASTC_6X6: GLenum = 0x93B4;
+const COMPRESSED_RGBA_ASTC_8X8: GLenum = 0x93B7;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: GLenum = 0x93D0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_6X6: GLenum = 0x93D4;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_8X8: GLenum = 0x93D7;
+
+//gpu压缩格式,块压缩,上传要用glCompressedTexImage2D
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CompressedFormat {
+    Bc1,
+    Bc1Alpha,
+    Bc1Srgb,
+    Bc2,
+    Bc2Srgb,
+    Bc3,
+    Bc3Srgb,
+    Bc4,
+    Bc5,
+    Bc6hUfloat,
+    Bc6hSfloat,
+    Bc7,
+    Bc7Srgb,
+    Etc2Rgb,
+    Etc2Srgb,
+    Etc2Rgba,
+    Etc2SrgbAlpha,
+    EacR11,
+    EacRg11,
+    Astc4x4,
+    Astc4x4Srgb,
+    Astc6x6,
+    Astc6x6Srgb,
+    Astc8x8,
+    Astc8x8Srgb,
+}
+
+impl CompressedFormat {
+    pub const fn as_gl(self) -> GLenum {
+        match self {
+            CompressedFormat::Bc1 => COMPRESSED_RGB_S3TC_DXT1,
+            CompressedFormat::Bc1Alpha => COMPRESSED_RGBA_S3TC_DXT1,
+            CompressedFormat::Bc1Srgb => COMPRESSED_SRGB_S3TC_DXT1,
+            CompressedFormat::Bc2 => COMPRESSED_RGBA_S3TC_DXT3,
+            CompressedFormat::Bc2Srgb => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            CompressedFormat::Bc3 => COMPRESSED_RGBA_S3TC_DXT5,
+            CompressedFormat::Bc3Srgb => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            CompressedFormat::Bc4 => gl::COMPRESSED_RED_RGTC1,
+            CompressedFormat::Bc5 => gl::COMPRESSED_RG_RGTC2,
+            CompressedFormat::Bc6hUfloat => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            CompressedFormat::Bc6hSfloat => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+            CompressedFormat::Bc7 => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            CompressedFormat::Bc7Srgb => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            CompressedFormat::Etc2Rgb => gl::COMPRESSED_RGB8_ETC2,
+            CompressedFormat::Etc2Srgb => gl::COMPRESSED_SRGB8_ETC2,
+            CompressedFormat::Etc2Rgba => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            CompressedFormat::Etc2SrgbAlpha => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            CompressedFormat::EacR11 => gl::COMPRESSED_R11_EAC,
+            CompressedFormat::EacRg11 => gl::COMPRESSED_RG11_EAC,
+            CompressedFormat::Astc4x4 => COMPRESSED_RGBA_ASTC_4X4,
+            CompressedFormat::Astc4x4Srgb => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4,
+            CompressedFormat::Astc6x6 => COMPRESSED_RGBA_ASTC_6X6,
+            CompressedFormat::Astc6x6Srgb => COMPRESSED_SRGB8_ALPHA8_ASTC_6X6,
+            CompressedFormat::Astc8x8 => COMPRESSED_RGBA_ASTC_8X8,
+            CompressedFormat::Astc8x8Srgb => COMPRESSED_SRGB8_ALPHA8_ASTC_8X8,
+        }
+    }
+    //(块宽,块高,每块字节数)
+    pub const fn block(self) -> (u32, u32, u32) {
+        match self {
+            CompressedFormat::Bc1
+            | CompressedFormat::Bc1Alpha
+            | CompressedFormat::Bc1Srgb
+            | CompressedFormat::Bc4
+            | CompressedFormat::Etc2Rgb
+            | CompressedFormat::Etc2Srgb
+            | CompressedFormat::EacR11 => (4, 4, 8),
+            CompressedFormat::Astc6x6 | CompressedFormat::Astc6x6Srgb => (6, 6, 16),
+            CompressedFormat::Astc8x8 | CompressedFormat::Astc8x8Srgb => (8, 8, 16),
+            _ => (4, 4, 16),
+        }
+    }
+    //一层mip的字节数,不满一块按一块算;宽高来自文件头,乘法要防溢出
+    pub fn level_size(self, w: u32, h: u32) -> GlResult<usize> {
+        let (bw, bh, bytes) = self.block();
+        let x = w.div_ceil(bw).max(1) as usize;
+        let y = h.div_ceil(bh).max(1) as usize;
+        x.checked_mul(y)
+            .and_then(|blocks| blocks.checked_mul(bytes as usize))
+            .ok_or_else(|| GlError::Asset(format!("{:?} level {}x{} too large", self, w, h)))
+    }
+    //ktx2头里的VkFormat
+    pub const fn from_vk(format: u32) -> Option<Self> {
+        Some(match format {
+            131 => CompressedFormat::Bc1,
+            132 => CompressedFormat::Bc1Srgb,
+            133 => CompressedFormat::Bc1Alpha,
+            135 => CompressedFormat::Bc2,
+            136 => CompressedFormat::Bc2Srgb,
+            137 => CompressedFormat::Bc3,
+            138 => CompressedFormat::Bc3Srgb,
+            139 => CompressedFormat::Bc4,
+            141 => CompressedFormat::Bc5,
+            143 => CompressedFormat::Bc6hUfloat,
+            144 => CompressedFormat::Bc6hSfloat,
+            145 => CompressedFormat::Bc7,
+            146 => CompressedFormat::Bc7Srgb,
+            147 => CompressedFormat::Etc2Rgb,
+            148 => CompressedFormat::Etc2Srgb,
+            151 => CompressedFormat::Etc2Rgba,
+            152 => CompressedFormat::Etc2SrgbAlpha,
+            153 => CompressedFormat::EacR11,
+            155 => CompressedFormat::EacRg11,
+            157 => CompressedFormat::Astc4x4,
+            158 => CompressedFormat::Astc4x4Srgb,
+            165 => CompressedFormat::Astc6x6,
+            166 => CompressedFormat::Astc6x6Srgb,
+            171 => CompressedFormat::Astc8x8,
+            172 => CompressedFormat::Astc8x8Srgb,
+            _ => return None,
+        })
+    }
+    //dds dx10头里的DXGI_FORMAT
+    pub const fn from_dxgi(format: u32) -> Option<Self> {
+        Some(match format {
+            71 => CompressedFormat::Bc1Alpha,
+            72 => CompressedFormat::Bc1Srgb,
+            74 => CompressedFormat::Bc2,
+            75 => CompressedFormat::Bc2Srgb,
+            77 => CompressedFormat::Bc3,
+            78 => CompressedFormat::Bc3Srgb,
+            80 => CompressedFormat::Bc4,
+            83 => CompressedFormat::Bc5,
+            95 => CompressedFormat::Bc6hUfloat,
+            96 => CompressedFormat::Bc6hSfloat,
+            98 => CompressedFormat::Bc7,
+            99 => CompressedFormat::Bc7Srgb,
+            _ => return None,
+        })
+    }
+}
+
+//从容器里读出来的压缩数据,levels[0]是最大的一层
+#[derive(Clone, Debug)]
+pub struct CompressedImage {
+    pub format: CompressedFormat,
+    pub w: u32,
+    pub h: u32,
+    pub levels: Vec<Vec<u8>>,
+}
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+//caps2里的cubemap标记
+const DDS_CUBEMAP: u32 = 0x200;
+
+//offset/len都可能来自文件,加法要防溢出
+fn read_u32(bytes: &[u8], offset: usize) -> GlResult<u32> {
+    offset
+        .checked_add(4)
+        .and_then(|end| bytes.get(offset..end))
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| GlError::Asset(format!("unexpected end of file at {}", offset)))
+}
+fn read_u64(bytes: &[u8], offset: usize) -> GlResult<u64> {
+    Ok(read_u32(bytes, offset)? as u64 | (read_u32(bytes, offset + 4)? as u64) << 32)
+}
+fn slice(bytes: &[u8], offset: usize, len: usize) -> GlResult<Vec<u8>> {
+    offset
+        .checked_add(len)
+        .and_then(|end| bytes.get(offset..end))
+        .map(|b| b.to_vec())
+        .ok_or_else(|| {
+            GlError::Asset(format!(
+                "level data {}+{} out of {} bytes",
+                offset,
+                len,
+                bytes.len()
+            ))
+        })
+}
+fn to_usize(value: u64) -> GlResult<usize> {
+    usize::try_from(value).map_err(|_| GlError::Asset(format!("size {} too large", value)))
+}
+
+impl CompressedImage {
+    pub fn load_path(path: &Path) -> Self {
+        Self::try_load_path(path).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load_path(path: &Path) -> GlResult<Self> {
+        let bytes = fs::read(path).map_err(|err| GlError::Asset(format!("{:?}:{}", path, err)))?;
+        Self::parse(&bytes)
+    }
+    //按文件头判断是ktx2还是dds
+    pub fn parse(bytes: &[u8]) -> GlResult<Self> {
+        if bytes.starts_with(&KTX2_MAGIC) {
+            Self::parse_ktx2(bytes)
+        } else if bytes.starts_with(&DDS_MAGIC) {
+            Self::parse_dds(bytes)
+        } else {
+            Err(GlError::Asset("not a ktx2 or dds file".to_string()))
+        }
+    }
+
+    //只支持没有超压缩的2d纹理
+    pub fn parse_ktx2(bytes: &[u8]) -> GlResult<Self> {
+        if !bytes.starts_with(&KTX2_MAGIC) {
+            return Err(GlError::Asset("bad ktx2 identifier".to_string()));
+        }
+        let vk_format = read_u32(bytes, 12)?;
+        let w = read_u32(bytes, 20)?;
+        let h = read_u32(bytes, 24)?;
+        let depth = read_u32(bytes, 28)?;
+        let layers = read_u32(bytes, 32)?;
+        let faces = read_u32(bytes, 36)?;
+        let level_count = read_u32(bytes, 40)?.max(1);
+        let supercompression = read_u32(bytes, 44)?;
+        let format = CompressedFormat::from_vk(vk_format)
+            .ok_or_else(|| GlError::TextureFormat(format!("ktx2 vkFormat {}", vk_format)))?;
+        if supercompression != 0 {
+            return Err(GlError::Unsupported(format!(
+                "ktx2 supercompression {}",
+                supercompression
+            )));
+        }
+        if depth > 1 || layers > 1 || faces > 1 {
+            return Err(GlError::Unsupported("ktx2 array/cube/3d".to_string()));
+        }
+        //level index从80开始,每项(offset,length,uncompressed length)
+        let levels = (0..level_count as usize)
+            .map(|level| {
+                let offset = to_usize(read_u64(bytes, 80 + level * 24)?)?;
+                let len = to_usize(read_u64(bytes, 80 + level * 24 + 8)?)?;
+                slice(bytes, offset, len)
+            })
+            .collect::<GlResult<Vec<_>>>()?;
+        Ok(Self {
+            format,
+            w,
+            h,
+            levels,
+        })
+    }
+
+    //只支持块压缩的2d纹理,没有dx10头的按fourcc判断
+    pub fn parse_dds(bytes: &[u8]) -> GlResult<Self> {
+        if !bytes.starts_with(&DDS_MAGIC) || read_u32(bytes, 4)? != 124 {
+            return Err(GlError::Asset("bad dds header".to_string()));
+        }
+        let h = read_u32(bytes, 12)?;
+        let w = read_u32(bytes, 16)?;
+        let level_count = read_u32(bytes, 28)?.max(1);
+        let four_cc = read_u32(bytes, 84)?.to_le_bytes();
+        if read_u32(bytes, 112)? & DDS_CUBEMAP != 0 {
+            return Err(GlError::Unsupported("dds cubemap".to_string()));
+        }
+        let (format, mut offset) = match &four_cc {
+            b"DXT1" => (CompressedFormat::Bc1Alpha, 128),
+            b"DXT3" => (CompressedFormat::Bc2, 128),
+            b"DXT5" => (CompressedFormat::Bc3, 128),
+            b"ATI1" | b"BC4U" => (CompressedFormat::Bc4, 128),
+            b"ATI2" | b"BC5U" => (CompressedFormat::Bc5, 128),
+            b"DX10" => {
+                let dxgi = read_u32(bytes, 128)?;
+                if read_u32(bytes, 140)? > 1 {
+                    return Err(GlError::Unsupported("dds array".to_string()));
+                }
+                let format = CompressedFormat::from_dxgi(dxgi)
+                    .ok_or_else(|| GlError::TextureFormat(format!("dds dxgi {}", dxgi)))?;
+                (format, 148)
+            }
+            other => {
+                return Err(GlError::TextureFormat(format!(
+                    "dds fourcc {}",
+                    String::from_utf8_lossy(other)
+                )));
+            }
+        };
+        //dds的mip是紧挨着的,大小靠块算
+        let levels = (0..level_count)
+            .map(|level| {
+                let size = |side: u32| side.checked_shr(level).unwrap_or(0).max(1);
+                let len = format.level_size(size(w), size(h))?;
+                let data = slice(bytes, offset, len)?;
+                offset += len;
+                Ok(data)
+            })
+            .collect::<GlResult<Vec<_>>>()?;
+        Ok(Self {
+            format,
+            w,
+            h,
+            levels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CompressedFormat, CompressedImage, DDS_MAGIC, KTX2_MAGIC};
+
+    fn put(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn parse_headers() {
+        //8x8 bc7,两层mip:4块+1块
+        let mut ktx2 = vec![0u8; 80 + 2 * 24];
+        ktx2[..12].copy_from_slice(&KTX2_MAGIC);
+        put(&mut ktx2, 12, 145);
+        put(&mut ktx2, 20, 8);
+        put(&mut ktx2, 24, 8);
+        put(&mut ktx2, 40, 2);
+        //ktx2里小的mip放在前面
+        let level1 = ktx2.len();
+        put(&mut ktx2, 80, level1 as u32 + 16);
+        put(&mut ktx2, 88, 64);
+        put(&mut ktx2, 104, level1 as u32);
+        put(&mut ktx2, 112, 16);
+        ktx2.extend((0..80).map(|i| i as u8));
+        let image = CompressedImage::parse(&ktx2).unwrap();
+        assert_eq!(image.format, CompressedFormat::Bc7);
+        assert_eq!((image.w, image.h), (8, 8));
+        assert_eq!(image.levels.len(), 2);
+        assert_eq!(image.levels[0].len(), image.format.level_size(8, 8).unwrap());
+        assert_eq!(image.levels[0][0], 16);
+        assert_eq!(image.levels[1].len(), image.format.level_size(4, 4).unwrap());
+
+        //offset+length溢出时是错误不是panic
+        let mut broken = ktx2.clone();
+        put(&mut broken, 80, u32::MAX);
+        put(&mut broken, 84, u32::MAX);
+        put(&mut broken, 88, 64);
+        assert!(CompressedImage::parse(&broken).is_err());
+
+        //6x4 dxt5,三层mip:2块,1块,1块
+        let mut dds = vec![0u8; 128];
+        dds[..4].copy_from_slice(&DDS_MAGIC);
+        put(&mut dds, 4, 124);
+        put(&mut dds, 12, 4);
+        put(&mut dds, 16, 6);
+        put(&mut dds, 28, 3);
+        dds[84..88].copy_from_slice(b"DXT5");
+        dds.extend(vec![7u8; 64]);
+        let image = CompressedImage::parse(&dds).unwrap();
+        assert_eq!(image.format, CompressedFormat::Bc3);
+        assert_eq!((image.w, image.h), (6, 4));
+        let sizes: Vec<usize> = image.levels.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![32, 16, 16]);
+
+        //块数乘字节数溢出也是错误
+        let mut huge = dds.clone();
+        put(&mut huge, 12, u32::MAX);
+        put(&mut huge, 16, u32::MAX);
+        assert!(CompressedImage::parse(&huge).is_err());
+        assert!(CompressedFormat::Bc7.level_size(u32::MAX, u32::MAX).is_err());
+
+        dds.truncate(150);
+        assert!(CompressedImage::parse(&dds).is_err());
+        assert!(CompressedImage::parse(b"PNG").is_err());
+    }
+}
@@ -1,3 +1,14 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, c_void},
+    fmt::{Display, Formatter},
+    ptr::null,
+    sync::{Mutex, PoisonError},
+};
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+use crate::setter_gen;
 
 pub fn check_vao_state() {
     unsafe {
@@ -32,3 +43,313 @@ pub fn check_vao_state() {
         println!("属性 0 是否启用: {}", enabled != 0);
     }
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+impl DebugSeverity {
+    pub const fn from_gl(severity: GLenum) -> Self {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+}
+
+//驱动通过KHR_debug发来的一条消息
+#[derive(Clone, PartialEq, Debug)]
+pub struct DebugMessage {
+    pub source: GLenum,
+    pub type_: GLenum,
+    pub id: GLuint,
+    pub severity: DebugSeverity,
+    pub message: String,
+}
+impl DebugMessage {
+    pub const fn source_name(&self) -> &'static str {
+        match self.source {
+            gl::DEBUG_SOURCE_API => "api",
+            gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+            gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+            gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+            gl::DEBUG_SOURCE_APPLICATION => "application",
+            _ => "other",
+        }
+    }
+    pub const fn type_name(&self) -> &'static str {
+        match self.type_ {
+            gl::DEBUG_TYPE_ERROR => "error",
+            gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated",
+            gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+            gl::DEBUG_TYPE_PORTABILITY => "portability",
+            gl::DEBUG_TYPE_PERFORMANCE => "performance",
+            gl::DEBUG_TYPE_MARKER => "marker",
+            gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+            gl::DEBUG_TYPE_POP_GROUP => "pop group",
+            _ => "other",
+        }
+    }
+    pub const fn is_error(&self) -> bool {
+        self.type_ == gl::DEBUG_TYPE_ERROR
+    }
+}
+impl Display for DebugMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[OpenGL {:?}] {} {} 0x{:X}: {}",
+            self.severity,
+            self.source_name(),
+            self.type_name(),
+            self.id,
+            self.message
+        )
+    }
+}
+
+setter_gen! {
+    DebugFilter {
+        //低于这个等级的丢掉
+        min_severity: DebugSeverity,
+        //驱动的噪音消息,比如nvidia的buffer放在显存里
+        ignore_ids: Vec<GLuint>,
+        //DEBUG_TYPE_ERROR在下一次raise_pending时panic
+        panic_on_error: bool
+    }
+}
+impl DebugFilter {
+    pub const fn new() -> Self {
+        Self {
+            min_severity: DebugSeverity::Low,
+            ignore_ids: Vec::new(),
+            panic_on_error: false,
+        }
+    }
+    pub fn pass(&self, message: &DebugMessage) -> bool {
+        message.severity >= self.min_severity && !self.ignore_ids.contains(&message.id)
+    }
+}
+impl Default for DebugFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type DebugSink = Box<dyn FnMut(&DebugMessage) + Send>;
+static FILTER: Mutex<DebugFilter> = Mutex::new(DebugFilter::new());
+//None时用默认的打印
+static SINK: Mutex<Option<DebugSink>> = Mutex::new(None);
+
+fn print_sink(message: &DebugMessage) {
+    if message.severity == DebugSeverity::High {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+thread_local! {
+    //回调是extern "system",不能在里面panic,先存下来
+    static PENDING: RefCell<Option<DebugMessage>> = const { RefCell::new(None) };
+}
+
+pub fn set_filter(filter: DebugFilter) {
+    *FILTER.lock().unwrap_or_else(PoisonError::into_inner) = filter;
+}
+//消息转给sink,比如写进日志或者收集起来给测试检查
+pub fn set_sink(sink: impl FnMut(&DebugMessage) + Send + 'static) {
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(sink));
+}
+pub fn reset_sink() {
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+//过滤后交给sink;sink调用时不持有锁,sink里再触发的消息走默认打印
+pub fn dispatch(message: DebugMessage) {
+    dispatch_to(&FILTER, &SINK, message);
+}
+//测试里传自己的filter/sink,并行测试里真context发来的消息只会进全局的那一份
+fn dispatch_to(
+    filter: &Mutex<DebugFilter>,
+    sink: &Mutex<Option<DebugSink>>,
+    message: DebugMessage,
+) {
+    let filter = filter.lock().unwrap_or_else(PoisonError::into_inner);
+    if !filter.pass(&message) {
+        return;
+    }
+    let panic_on_error = filter.panic_on_error;
+    drop(filter);
+    let taken = sink.lock().unwrap_or_else(PoisonError::into_inner).take();
+    match taken {
+        Some(mut taken) => {
+            taken(&message);
+            //sink里set_sink换过的话用新的
+            sink.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get_or_insert(taken);
+        }
+        None => print_sink(&message),
+    }
+    if panic_on_error && message.is_error() {
+        PENDING.with_borrow_mut(|pending| {
+            pending.get_or_insert(message);
+        });
+    }
+}
+
+//panic_on_error记下的错误在这里panic;vao bind和状态切换时会调用,测试里也可以直接调
+//只有DEBUG_OUTPUT_SYNCHRONOUS下回调才在出错的线程上
+pub fn raise_pending() {
+    if let Some(message) = PENDING.with_borrow_mut(Option::take) {
+        panic!("{}", message);
+    }
+}
+
+extern "system" fn debug_callback(
+    source: GLenum,
+    type_: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = unsafe { CStr::from_ptr(message).to_string_lossy().to_string() };
+    dispatch(DebugMessage {
+        source,
+        type_,
+        id,
+        severity: DebugSeverity::from_gl(severity),
+        message,
+    });
+}
+
+//es 3.0没有debug output,这时返回false
+pub fn enable_output() -> bool {
+    if !gl::DebugMessageCallback::is_loaded() {
+        return false;
+    }
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        //同步才能在出错的gl调用里拿到调用栈
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(debug_callback), null());
+    }
+    true
+}
+pub fn disable_output() {
+    if gl::DebugMessageCallback::is_loaded() {
+        unsafe {
+            gl::Disable(gl::DEBUG_OUTPUT);
+        }
+    }
+}
+
+//给gl对象起名字,renderdoc之类的工具里显示;identifier是gl::BUFFER/gl::TEXTURE...
+pub fn set_label(identifier: GLenum, name: GLuint, label: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(
+            identifier,
+            name,
+            label.len() as GLsizei,
+            label.as_ptr() as *const GLchar,
+        );
+    }
+}
+pub fn push_debug_group(name: &str) {
+    if !gl::PushDebugGroup::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::PushDebugGroup(
+            gl::DEBUG_SOURCE_APPLICATION,
+            0,
+            name.len() as GLsizei,
+            name.as_ptr() as *const GLchar,
+        );
+    }
+}
+pub fn pop_debug_group() {
+    if gl::PopDebugGroup::is_loaded() {
+        unsafe {
+            gl::PopDebugGroup();
+        }
+    }
+}
+//func里的gl调用在抓帧工具里归到name下面
+pub fn debug_group<T>(name: &str, func: impl FnOnce() -> T) -> T {
+    push_debug_group(name);
+    let value = func();
+    pop_debug_group();
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::{DebugFilter, DebugMessage, DebugSeverity, DebugSink, dispatch_to, raise_pending};
+
+    //不碰全局的FILTER/SINK,别的测试的headless context也在发消息
+    fn route(
+        filter: DebugFilter,
+        sink: DebugSink,
+    ) -> (Mutex<DebugFilter>, Mutex<Option<DebugSink>>) {
+        (Mutex::new(filter), Mutex::new(Some(sink)))
+    }
+
+    #[test]
+    fn filter_and_sink() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let (filter, sink) = route(
+            DebugFilter::new()
+                .min_severity(DebugSeverity::Medium)
+                .ignore_ids(vec![0x20071]),
+            Box::new(move |message: &DebugMessage| sink.lock().unwrap().push(message.id)),
+        );
+        let message = |id, severity| DebugMessage {
+            source: gl::DEBUG_SOURCE_API,
+            type_: gl::DEBUG_TYPE_OTHER,
+            id,
+            severity,
+            message: String::new(),
+        };
+        dispatch_to(&filter, &sink, message(1, DebugSeverity::High));
+        dispatch_to(&filter, &sink, message(2, DebugSeverity::Low));
+        dispatch_to(&filter, &sink, message(0x20071, DebugSeverity::High));
+        dispatch_to(&filter, &sink, message(3, DebugSeverity::Medium));
+        assert_eq!(*received.lock().unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid enum")]
+    fn panic_on_error() {
+        let (filter, sink) = route(
+            DebugFilter::new().panic_on_error(true),
+            Box::new(|_: &DebugMessage| {}),
+        );
+        dispatch_to(
+            &filter,
+            &sink,
+            DebugMessage {
+                source: gl::DEBUG_SOURCE_API,
+                type_: gl::DEBUG_TYPE_ERROR,
+                id: 0x500,
+                severity: DebugSeverity::High,
+                message: "invalid enum".to_string(),
+            },
+        );
+        raise_pending();
+    }
+}
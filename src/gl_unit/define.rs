@@ -2,6 +2,11 @@
 use gl::types::GLenum;
 use paste::paste;
 
+use super::{
+    caps::caps,
+    error::{GlError, GlResult},
+};
+
 
 macro_rules! gl_enum {
     (Zero) => {
@@ -34,6 +39,75 @@ macro_rules! gl_enum {
     (ConstAlpha) => {
         gl::CONSTANT_ALPHA
     };
+    (OneMinusConstColor) => {
+        gl::ONE_MINUS_CONSTANT_COLOR
+    };
+    (OneMinusConstAlpha) => {
+        gl::ONE_MINUS_CONSTANT_ALPHA
+    };
+    (SrcAlphaSaturate) => {
+        gl::SRC_ALPHA_SATURATE
+    };
+    (Add) => {
+        gl::FUNC_ADD
+    };
+    (Subtract) => {
+        gl::FUNC_SUBTRACT
+    };
+    (ReverseSubtract) => {
+        gl::FUNC_REVERSE_SUBTRACT
+    };
+    (Min) => {
+        gl::MIN
+    };
+    (Max) => {
+        gl::MAX
+    };
+    (Never) => {
+        gl::NEVER
+    };
+    (Less) => {
+        gl::LESS
+    };
+    (Equal) => {
+        gl::EQUAL
+    };
+    (LessEqual) => {
+        gl::LEQUAL
+    };
+    (Greater) => {
+        gl::GREATER
+    };
+    (NotEqual) => {
+        gl::NOTEQUAL
+    };
+    (GreaterEqual) => {
+        gl::GEQUAL
+    };
+    (Always) => {
+        gl::ALWAYS
+    };
+    (Keep) => {
+        gl::KEEP
+    };
+    (Replace) => {
+        gl::REPLACE
+    };
+    (Incr) => {
+        gl::INCR
+    };
+    (IncrWrap) => {
+        gl::INCR_WRAP
+    };
+    (Decr) => {
+        gl::DECR
+    };
+    (DecrWrap) => {
+        gl::DECR_WRAP
+    };
+    (Invert) => {
+        gl::INVERT
+    };
     (OneMinusSrcAlpha) => {
         gl::ONE_MINUS_SRC_ALPHA
     };
@@ -92,6 +166,66 @@ macro_rules! gl_enum {
     (RED32) => {
         (gl::RED, gl::FLOAT)
     };
+    (RG8) => {
+        (gl::RG, gl::UNSIGNED_BYTE)
+    };
+    (RG16) => {
+        (gl::RG, gl::UNSIGNED_SHORT)
+    };
+    (RG32) => {
+        (gl::RG, gl::FLOAT)
+    };
+    (RGBA16F) => {
+        (gl::RGBA, gl::HALF_FLOAT)
+    };
+    (RGB16F) => {
+        (gl::RGB, gl::HALF_FLOAT)
+    };
+    (RG16F) => {
+        (gl::RG, gl::HALF_FLOAT)
+    };
+    (RED16F) => {
+        (gl::RED, gl::HALF_FLOAT)
+    };
+    (SRGB8) => {
+        (gl::RGB, gl::UNSIGNED_BYTE)
+    };
+    (SRGBA8) => {
+        (gl::RGBA, gl::UNSIGNED_BYTE)
+    };
+    (RED32UI) => {
+        (gl::RED_INTEGER, gl::UNSIGNED_INT)
+    };
+    (RG32UI) => {
+        (gl::RG_INTEGER, gl::UNSIGNED_INT)
+    };
+    (RGBA32UI) => {
+        (gl::RGBA_INTEGER, gl::UNSIGNED_INT)
+    };
+    (RED32I) => {
+        (gl::RED_INTEGER, gl::INT)
+    };
+    (RG32I) => {
+        (gl::RG_INTEGER, gl::INT)
+    };
+    (RGBA32I) => {
+        (gl::RGBA_INTEGER, gl::INT)
+    };
+    (DEPTH16) => {
+        (gl::DEPTH_COMPONENT, gl::UNSIGNED_SHORT)
+    };
+    (DEPTH24) => {
+        (gl::DEPTH_COMPONENT, gl::UNSIGNED_INT)
+    };
+    (DEPTH32F) => {
+        (gl::DEPTH_COMPONENT, gl::FLOAT)
+    };
+    (DEPTH24STENCIL8) => {
+        (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8)
+    };
+    (DEPTH32FSTENCIL8) => {
+        (gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV)
+    };
     (Vertex) => {
         gl::ARRAY_BUFFER
     };
@@ -99,6 +233,27 @@ macro_rules! gl_enum {
     (Element) => {
         gl::ELEMENT_ARRAY_BUFFER
     };
+    (Uniform) => {
+        gl::UNIFORM_BUFFER
+    };
+    (ShaderStorage) => {
+        gl::SHADER_STORAGE_BUFFER
+    };
+    (DrawIndirect) => {
+        gl::DRAW_INDIRECT_BUFFER
+    };
+    (PixelPack) => {
+        gl::PIXEL_PACK_BUFFER
+    };
+    (PixelUnpack) => {
+        gl::PIXEL_UNPACK_BUFFER
+    };
+    (CopyRead) => {
+        gl::COPY_READ_BUFFER
+    };
+    (CopyWrite) => {
+        gl::COPY_WRITE_BUFFER
+    };
     (Static) => {
         gl::STATIC_DRAW
     };
@@ -117,6 +272,30 @@ macro_rules! gl_enum {
     (Repeat) => {
         gl::REPEAT
     };
+    (Red) => {
+        gl::RED
+    };
+    (Green) => {
+        gl::GREEN
+    };
+    (Blue) => {
+        gl::BLUE
+    };
+    (Alpha) => {
+        gl::ALPHA
+    };
+    (TimeElapsed) => {
+        gl::TIME_ELAPSED
+    };
+    (Timestamp) => {
+        gl::TIMESTAMP
+    };
+    (SamplesPassed) => {
+        gl::SAMPLES_PASSED
+    };
+    (AnySamplesPassed) => {
+        gl::ANY_SAMPLES_PASSED
+    };
     (MirroredRepeat) => {
         gl::MIRRORED_REPEAT
     };
@@ -147,9 +326,19 @@ macro_rules! gl_enum {
     (TriangleFan)=>{
         gl::TRIANGLE_FAN
     };
+    //只有compat profile有
     (Quads)=>{
         gl::QUADS
-    }
+    };
+    (ReadOnly) => {
+        gl::READ_ONLY
+    };
+    (WriteOnly) => {
+        gl::WRITE_ONLY
+    };
+    (ReadWrite) => {
+        gl::READ_WRITE
+    };
 }
 
 macro_rules! enums_creater {
@@ -171,6 +360,13 @@ macro_rules! enums_creater {
                         _=>{panic!("gl_enum not support.")}
                     }
                 }
+                //从驱动读回来的值可能不在枚举里
+                pub const fn try_from_gl(gl_enum:GLenum)->Option<Self>{
+                    match gl_enum{
+                        $(gl_enum!($var) => Some(Self::$var),)*
+                        _=>None
+                    }
+                }
             }
         )*
     };
@@ -178,7 +374,7 @@ macro_rules! enums_creater {
 macro_rules! two_enums_creater {
         ($($name:ident {$($var:ident),* $(,)?})*)=>{
         $(
-            #[derive(PartialEq,Clone,Copy)]
+            #[derive(PartialEq,Clone,Copy,Debug)]
             pub enum $name{
                 $($var,)*
             }
@@ -214,7 +410,8 @@ macro_rules! enums_index_creater {
 }
 #[macro_export]
 macro_rules! setter_gen {
-    ($name:ident{$($var:ident:$var_type:ty),* }) => {
+    ($(#[$meta:meta])* $name:ident{$($var:ident:$var_type:ty),* }) => {
+        $(#[$meta])*
         pub struct $name {
             $(pub $var:$var_type,)*
         }
@@ -253,6 +450,36 @@ enums_creater! {
         DstAlpha,
         ConstColor,
         ConstAlpha,
+        OneMinusConstColor,
+        OneMinusConstAlpha,
+        SrcAlphaSaturate,
+    }
+    BlendEquation{
+        Add,
+        Subtract,
+        ReverseSubtract,
+        Min,
+        Max
+    }
+    CompareFunc{
+        Never,
+        Less,
+        Equal,
+        LessEqual,
+        Greater,
+        NotEqual,
+        GreaterEqual,
+        Always
+    }
+    StencilOp{
+        Keep,
+        Zero,
+        Replace,
+        Incr,
+        IncrWrap,
+        Decr,
+        DecrWrap,
+        Invert
     }
     
     Face{
@@ -262,7 +489,14 @@ enums_creater! {
     }
     BufferTarget{
         Vertex,
-        Element
+        Element,
+        Uniform,
+        ShaderStorage,
+        DrawIndirect,
+        PixelPack,
+        PixelUnpack,
+        CopyRead,
+        CopyWrite
     }
     BufferUsage{
         Dynamic,
@@ -273,12 +507,25 @@ enums_creater! {
         Linear,
         Nearest
     }
+    Swizzle{
+        Red,
+        Green,
+        Blue,
+        Alpha,
+        Zero,
+        One
+    }
     TextureWarpMode{
         Repeat,
         MirroredRepeat,
         ClampBorder,
         ClampEdge
     }
+    ImageAccess{
+        ReadOnly,
+        WriteOnly,
+        ReadWrite
+    }
     DrawMode{
         Points,
         Lines,
@@ -289,6 +536,12 @@ enums_creater! {
         TriangleFan,
         Quads
     }
+    QueryTarget{
+        TimeElapsed,
+        Timestamp,
+        SamplesPassed,
+        AnySamplesPassed
+    }
 }
 pub enum PolygonMode {
         Fill,
@@ -304,6 +557,39 @@ impl PolygonMode{
         }
     }
 }
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MemoryBarrier {
+    VertexAttribArray,
+    ElementArray,
+    Uniform,
+    TextureFetch,
+    ShaderImageAccess,
+    Command,
+    PixelBuffer,
+    TextureUpdate,
+    BufferUpdate,
+    FrameBuffer,
+    ShaderStorage,
+    All,
+}
+impl MemoryBarrier {
+    pub const fn as_gl(self) -> GLenum {
+        match self {
+            MemoryBarrier::VertexAttribArray => gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT,
+            MemoryBarrier::ElementArray => gl::ELEMENT_ARRAY_BARRIER_BIT,
+            MemoryBarrier::Uniform => gl::UNIFORM_BARRIER_BIT,
+            MemoryBarrier::TextureFetch => gl::TEXTURE_FETCH_BARRIER_BIT,
+            MemoryBarrier::ShaderImageAccess => gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+            MemoryBarrier::Command => gl::COMMAND_BARRIER_BIT,
+            MemoryBarrier::PixelBuffer => gl::PIXEL_BUFFER_BARRIER_BIT,
+            MemoryBarrier::TextureUpdate => gl::TEXTURE_UPDATE_BARRIER_BIT,
+            MemoryBarrier::BufferUpdate => gl::BUFFER_UPDATE_BARRIER_BIT,
+            MemoryBarrier::FrameBuffer => gl::FRAMEBUFFER_BARRIER_BIT,
+            MemoryBarrier::ShaderStorage => gl::SHADER_STORAGE_BARRIER_BIT,
+            MemoryBarrier::All => gl::ALL_BARRIER_BITS,
+        }
+    }
+}
 two_enums_creater! {
     TextureType {
         RGBA8,
@@ -315,6 +601,90 @@ two_enums_creater! {
         RGBA32,
         RGB32,
         RED32,
+        RG8,
+        RG16,
+        RG32,
+        //半精度,上传的数据是f16
+        RGBA16F,
+        RGB16F,
+        RG16F,
+        RED16F,
+        //采样时自动转到线性空间
+        SRGB8,
+        SRGBA8,
+        //整数纹理,picking之类写id用;只能NEAREST采样,shader里是usampler/isampler
+        RED32UI,
+        RG32UI,
+        RGBA32UI,
+        RED32I,
+        RG32I,
+        RGBA32I,
+        DEPTH16,
+        DEPTH24,
+        DEPTH32F,
+        DEPTH24STENCIL8,
+        DEPTH32FSTENCIL8,
+    }
+}
+impl TextureType {
+    //sized internal fmt,renderbuffer和多重采样纹理只能用这个;es核心没有16位unorm格式
+    pub fn sized(self) -> GlResult<GLenum> {
+        if caps().es && self.is_norm16() {
+            return Err(GlError::Unsupported(format!("{:?} texture", self)));
+        }
+        Ok(self.sized_unchecked())
+    }
+    //不查caps,深度格式这种哪里都有的用
+    pub(crate) const fn sized_unchecked(self) -> GLenum {
+        match self {
+            TextureType::RGBA8 => gl::RGBA8,
+            TextureType::RGB8 => gl::RGB8,
+            TextureType::RED8 => gl::R8,
+            TextureType::RGBA16 => gl::RGBA16,
+            TextureType::RGB16 => gl::RGB16,
+            TextureType::RED16 => gl::R16,
+            TextureType::RGBA32 => gl::RGBA32F,
+            TextureType::RGB32 => gl::RGB32F,
+            TextureType::RED32 => gl::R32F,
+            TextureType::RG8 => gl::RG8,
+            TextureType::RG16 => gl::RG16,
+            TextureType::RG32 => gl::RG32F,
+            TextureType::RGBA16F => gl::RGBA16F,
+            TextureType::RGB16F => gl::RGB16F,
+            TextureType::RG16F => gl::RG16F,
+            TextureType::RED16F => gl::R16F,
+            TextureType::SRGB8 => gl::SRGB8,
+            TextureType::SRGBA8 => gl::SRGB8_ALPHA8,
+            TextureType::RED32UI => gl::R32UI,
+            TextureType::RG32UI => gl::RG32UI,
+            TextureType::RGBA32UI => gl::RGBA32UI,
+            TextureType::RED32I => gl::R32I,
+            TextureType::RG32I => gl::RG32I,
+            TextureType::RGBA32I => gl::RGBA32I,
+            TextureType::DEPTH16 => gl::DEPTH_COMPONENT16,
+            TextureType::DEPTH24 => gl::DEPTH_COMPONENT24,
+            TextureType::DEPTH32F => gl::DEPTH_COMPONENT32F,
+            TextureType::DEPTH24STENCIL8 => gl::DEPTH24_STENCIL8,
+            TextureType::DEPTH32FSTENCIL8 => gl::DEPTH32F_STENCIL8,
+        }
+    }
+    pub const fn is_norm16(self) -> bool {
+        matches!(
+            self,
+            TextureType::RGBA16 | TextureType::RGB16 | TextureType::RG16 | TextureType::RED16
+        )
+    }
+    pub const fn is_unsigned_integer(self) -> bool {
+        matches!(
+            self,
+            TextureType::RED32UI | TextureType::RG32UI | TextureType::RGBA32UI
+        )
+    }
+    pub const fn is_signed_integer(self) -> bool {
+        matches!(
+            self,
+            TextureType::RED32I | TextureType::RG32I | TextureType::RGBA32I
+        )
     }
 }
 enums_index_creater! {
@@ -332,7 +702,9 @@ setter_gen! {
         len: i32,
         is_normalized: bool,
         stride_size: i32,
-        pointer: usize
+        pointer: usize,
+        //0每个顶点取一次,n每n个实例取一次
+        divisor: u32
     }
 }
 impl VertexArrayAttribPointerGen {
@@ -343,8 +715,15 @@ impl VertexArrayAttribPointerGen {
             is_normalized: false,
             stride_size: len * size_of::<T>() as i32,
             pointer: 0,
+            divisor: 0,
         }
     }
+    //每个实例一份的属性,比如实例的位置/颜色
+    pub const fn instanced<T: TypeGL>(index: u32, len: i32) -> Self {
+        let mut pointer = Self::new::<T>(index, len);
+        pointer.divisor = 1;
+        pointer
+    }
     pub const fn new_size(index: u32,len:i32, type_size: i32) -> Self {
         Self {
             index,
@@ -352,17 +731,34 @@ impl VertexArrayAttribPointerGen {
             is_normalized: false,
             stride_size: len * type_size,
             pointer: 0,
+            divisor: 0,
         }
     }
 
 }
 
 setter_gen! {
+#[derive(Clone, Copy)]
 TextureParm {
     min_filter: Filter,
     mag_filter: Filter,
+    //None不用mipmap;Some是层之间怎么过滤
+    mip_filter: Option<Filter>,
+    //上传数据后自动glGenerateMipmap
+    generate_mipmaps: bool,
     wrap_s: TextureWarpMode,
     wrap_t: TextureWarpMode,
+    //3d纹理和cube map用
+    wrap_r: TextureWarpMode,
+    //es没有
+    lod_bias: f32,
+    min_lod: f32,
+    max_lod: f32,
+    //1是关掉,超过驱动上限会被截断
+    anisotropy: f32,
+    border_color: [f32; 4],
+    //采样结果的rgba分别从哪个通道来,单通道纹理当灰度图用时很方便
+    swizzle: [Swizzle; 4],
     once_load_size: i32
 }
 }
@@ -372,11 +768,46 @@ impl TextureParm {
         Self {
             min_filter: Filter::Nearest,
             mag_filter: Filter::Nearest,
+            mip_filter: None,
+            generate_mipmaps: false,
             wrap_s: TextureWarpMode::ClampBorder,
             wrap_t: TextureWarpMode::ClampBorder,
+            wrap_r: TextureWarpMode::ClampBorder,
+            lod_bias: 0f32,
+            min_lod: -1000f32,
+            max_lod: 1000f32,
+            anisotropy: 1f32,
+            border_color: [0f32; 4],
+            swizzle: [Swizzle::Red, Swizzle::Green, Swizzle::Blue, Swizzle::Alpha],
             once_load_size: 4,
         }
     }
+    //三线性过滤+自动mipmap,模型贴图用
+    pub const fn mipmap() -> Self {
+        let mut parm = Self::new();
+        parm.min_filter = Filter::Linear;
+        parm.mag_filter = Filter::Linear;
+        parm.mip_filter = Some(Filter::Linear);
+        parm.generate_mipmaps = true;
+        parm.wrap_s = TextureWarpMode::Repeat;
+        parm.wrap_t = TextureWarpMode::Repeat;
+        parm.wrap_r = TextureWarpMode::Repeat;
+        parm
+    }
+    pub fn wrap(self, mode: TextureWarpMode) -> Self {
+        self.wrap_s(mode).wrap_t(mode).wrap_r(mode)
+    }
+    //min filter和mip filter合起来的gl值
+    pub const fn min_filter_gl(&self) -> GLenum {
+        match (self.min_filter, self.mip_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
 }
 
 pub trait TypeGL {
@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+
+use gl::types::GLenum;
+
+use super::program::diagnostic::ShaderLog;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlError {
+    ShaderCompile(Vec<ShaderLog>),
+    //链接日志按stage拆开,stage是0的是认不出属于哪个stage的
+    ProgramLink(Vec<ShaderLog>),
+    FrameBufferIncomplete(GLenum),
+    TextureFormat(String),
+    BufferOverflow { offset: usize, size: usize, capacity: usize },
+    Asset(String),
+    Unsupported(String),
+    Uniform(String),
+    UniformType { name: String, expected: GLenum, found: &'static str },
+    UniformSize { name: String, size: usize, count: usize },
+}
+
+pub type GlResult<T> = Result<T, GlError>;
+
+pub const fn stage_name(stage: GLenum) -> &'static str {
+    match stage {
+        gl::VERTEX_SHADER => "vertex",
+        gl::FRAGMENT_SHADER => "fragment",
+        gl::GEOMETRY_SHADER => "geometry",
+        gl::TESS_CONTROL_SHADER => "tess control",
+        gl::TESS_EVALUATION_SHADER => "tess evaluation",
+        gl::COMPUTE_SHADER => "compute",
+        _ => "unknown",
+    }
+}
+
+impl Display for GlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlError::ShaderCompile(logs) => {
+                writeln!(f, "shader compile err:")?;
+                for log in logs.iter() {
+                    write!(f, "{}", log)?;
+                }
+                Ok(())
+            }
+            GlError::ProgramLink(logs) => {
+                writeln!(f, "program link err:")?;
+                for log in logs.iter() {
+                    write!(f, "{}", log)?;
+                }
+                Ok(())
+            }
+            GlError::FrameBufferIncomplete(status) => {
+                write!(f, "framebuffer incomplete: 0x{:X}", status)
+            }
+            GlError::TextureFormat(format) => write!(f, "texture format not support: {}", format),
+            GlError::BufferOverflow {
+                offset,
+                size,
+                capacity,
+            } => write!(
+                f,
+                "buffer overflow: write {} bytes at {} into {} bytes",
+                size, offset, capacity
+            ),
+            GlError::Asset(err) => write!(f, "asset err: {}", err),
+            GlError::Unsupported(func) => write!(f, "{} not supported by this context", func),
+            GlError::Uniform(name) => write!(f, "can't find uniform {}", name),
+            GlError::UniformType {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "uniform {} type mismatch: program wants 0x{:X}, got {}",
+                name, expected, found
+            ),
+            GlError::UniformSize { name, size, count } => write!(
+                f,
+                "uniform {} array too small: {} elements, got {}",
+                name, size, count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GlError {}
@@ -0,0 +1,896 @@
+use gl::types::{GLenum, GLsizei, GLuint};
+use glam::IVec2;
+
+use super::{
+    caps::caps,
+    debug,
+    define::{TextureParm, TextureType},
+    error::{GlError, GlResult},
+    state,
+    texture::{Texture2D, Texture2DMultisample, TextureWrapper},
+};
+
+//深度(模板)附件的格式
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DepthFormat {
+    Depth16,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8,
+}
+
+impl DepthFormat {
+    pub const fn texture_type(self) -> TextureType {
+        match self {
+            DepthFormat::Depth16 => TextureType::DEPTH16,
+            DepthFormat::Depth24 => TextureType::DEPTH24,
+            DepthFormat::Depth32F => TextureType::DEPTH32F,
+            DepthFormat::Depth24Stencil8 => TextureType::DEPTH24STENCIL8,
+            DepthFormat::Depth32FStencil8 => TextureType::DEPTH32FSTENCIL8,
+        }
+    }
+    //(internal fmt,fmt,type)
+    pub const fn as_gl(self) -> (GLenum, GLenum, GLenum) {
+        let type_ = self.texture_type();
+        let (format, real) = type_.as_gl();
+        (type_.sized_unchecked(), format, real)
+    }
+    pub const fn has_stencil(self) -> bool {
+        matches!(
+            self,
+            DepthFormat::Depth24Stencil8 | DepthFormat::Depth32FStencil8
+        )
+    }
+    pub const fn attachment(self) -> GLenum {
+        if self.has_stencil() {
+            gl::DEPTH_STENCIL_ATTACHMENT
+        } else {
+            gl::DEPTH_ATTACHMENT
+        }
+    }
+    const fn from_render(format: GLenum) -> Option<Self> {
+        match format {
+            gl::DEPTH_COMPONENT16 => Some(DepthFormat::Depth16),
+            gl::DEPTH_COMPONENT24 => Some(DepthFormat::Depth24),
+            gl::DEPTH_COMPONENT32F => Some(DepthFormat::Depth32F),
+            gl::DEPTH24_STENCIL8 => Some(DepthFormat::Depth24Stencil8),
+            gl::DEPTH32F_STENCIL8 => Some(DepthFormat::Depth32FStencil8),
+            _ => None,
+        }
+    }
+}
+
+pub struct RenderBuffer {
+    pub w: u32,
+    pub h: u32,
+    pub render_buffer: GLuint,
+    //internal fmt,resize时用
+    pub format: GLenum,
+    //0是普通renderbuffer
+    pub samples: u32,
+}
+
+impl RenderBuffer {
+    pub fn new(type_: GLenum, w: u32, h: u32) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, type_, w as GLsizei, h as GLsizei);
+        }
+        RenderBuffer {
+            w,
+            h,
+            render_buffer: id,
+            format: type_,
+            samples: 0,
+        }
+    }
+    pub fn multisample(type_: GLenum, w: u32, h: u32, samples: u32) -> Self {
+        if samples == 0 {
+            return Self::new(type_, w, h);
+        }
+        let mut id = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples as GLsizei,
+                type_,
+                w as GLsizei,
+                h as GLsizei,
+            );
+        }
+        RenderBuffer {
+            w,
+            h,
+            render_buffer: id,
+            format: type_,
+            samples,
+        }
+    }
+}
+
+impl Drop for RenderBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.render_buffer);
+        }
+    }
+}
+
+pub enum ColorTarget {
+    Texture(TextureWrapper<Texture2D>),
+    //shader里可以texelFetch每个采样点
+    Multisample(TextureWrapper<Texture2DMultisample>),
+    //只能blit出去
+    Render(RenderBuffer),
+}
+
+impl ColorTarget {
+    fn new(
+        type_: TextureType,
+        parm: TextureParm,
+        samples: u32,
+        sampled: bool,
+        w: u32,
+        h: u32,
+    ) -> GlResult<Self> {
+        let format = type_.sized()?;
+        Ok(match (samples, sampled) {
+            (0, _) => ColorTarget::Texture(TextureWrapper(Texture2D::with_size(w, h, type_, parm))),
+            (samples, true) => ColorTarget::Multisample(TextureWrapper(Texture2DMultisample::new(
+                format, w, h, samples,
+            ))),
+            (samples, false) => {
+                ColorTarget::Render(RenderBuffer::multisample(format, w, h, samples))
+            }
+        })
+    }
+    pub fn size(&self) -> IVec2 {
+        let (w, h) = match self {
+            ColorTarget::Texture(texture) => (texture.w, texture.h),
+            ColorTarget::Multisample(texture) => (texture.w, texture.h),
+            ColorTarget::Render(render) => (render.w, render.h),
+        };
+        IVec2::new(w as i32, h as i32)
+    }
+    pub fn samples(&self) -> u32 {
+        match self {
+            ColorTarget::Texture(_) => 0,
+            ColorTarget::Multisample(texture) => texture.samples,
+            ColorTarget::Render(render) => render.samples,
+        }
+    }
+    fn attach(&self, attachment: GLenum) {
+        unsafe {
+            match self {
+                ColorTarget::Texture(texture) => gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::TEXTURE_2D,
+                    texture.texture,
+                    0,
+                ),
+                ColorTarget::Multisample(texture) => gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::TEXTURE_2D_MULTISAMPLE,
+                    texture.texture,
+                    0,
+                ),
+                ColorTarget::Render(render) => gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::RENDERBUFFER,
+                    render.render_buffer,
+                ),
+            }
+        }
+    }
+}
+
+pub struct ColorAttachment {
+    //GL_COLOR_ATTACHMENT0 + index,也是片元着色器里的location
+    pub index: u32,
+    pub target: ColorTarget,
+    pub type_: TextureType,
+    pub parm: TextureParm,
+    pub clear: [f32; 4],
+}
+
+pub struct DepthAttachment {
+    pub texture: TextureWrapper<Texture2D>,
+    pub format: DepthFormat,
+    pub parm: TextureParm,
+}
+
+pub struct FrameBuffer {
+    pub frame_buffer: GLuint,
+    //不需要采样的深度模板用renderbuffer
+    pub render: Option<RenderBuffer>,
+    render_attachment: GLenum,
+    //按index排好
+    pub colors: Vec<ColorAttachment>,
+    //可以采样的深度纹理,shadow map用
+    pub depth: Option<DepthAttachment>,
+    pub clear_depth: f32,
+    pub clear_stencil: i32,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        Self {
+            frame_buffer: id,
+            render: None,
+            render_attachment: gl::DEPTH_STENCIL_ATTACHMENT,
+            colors: Vec::new(),
+            depth: None,
+            clear_depth: 1f32,
+            clear_stencil: 0,
+        }
+    }
+    pub fn builder(w: u32, h: u32) -> FrameBufferBuilder {
+        FrameBufferBuilder::new(w, h)
+    }
+    pub fn set_label(&self, label: &str) {
+        debug::set_label(gl::FRAMEBUFFER, self.frame_buffer, label);
+    }
+    //第0个颜色附件
+    pub fn texture(&self) -> Option<&TextureWrapper<Texture2D>> {
+        self.color(0)
+    }
+    //多重采样的附件返回None
+    pub fn color(&self, index: u32) -> Option<&TextureWrapper<Texture2D>> {
+        match &self
+            .colors
+            .iter()
+            .find(|color| color.index == index)?
+            .target
+        {
+            ColorTarget::Texture(texture) => Some(texture),
+            _ => None,
+        }
+    }
+    pub fn multisample_color(&self, index: u32) -> Option<&TextureWrapper<Texture2DMultisample>> {
+        match &self
+            .colors
+            .iter()
+            .find(|color| color.index == index)?
+            .target
+        {
+            ColorTarget::Multisample(texture) => Some(texture),
+            _ => None,
+        }
+    }
+    pub fn samples(&self) -> u32 {
+        match (self.colors.first(), self.render.as_ref()) {
+            (Some(color), _) => color.target.samples(),
+            (None, Some(render)) => render.samples,
+            (None, None) => 0,
+        }
+    }
+    pub fn depth_texture(&self) -> Option<&TextureWrapper<Texture2D>> {
+        self.depth.as_ref().map(|depth| &depth.texture)
+    }
+    pub fn view_port(&self) {
+        if let Some(size) = self.get_size() {
+            state::viewport(0, 0, size.x, size.y);
+        }
+    }
+    //原来的纹理当作RGBA8,resize时按这个重建
+    pub fn link_texture(&mut self, texture: TextureWrapper<Texture2D>, attachment: GLenum) {
+        self.try_link_texture(texture, attachment)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_link_texture(
+        &mut self,
+        texture: TextureWrapper<Texture2D>,
+        attachment: GLenum,
+    ) -> GlResult<()> {
+        match attachment {
+            gl::DEPTH_ATTACHMENT | gl::DEPTH_STENCIL_ATTACHMENT => {
+                let format = if attachment == gl::DEPTH_ATTACHMENT {
+                    DepthFormat::Depth24
+                } else {
+                    DepthFormat::Depth24Stencil8
+                };
+                self.attach_depth(DepthAttachment {
+                    texture,
+                    format,
+                    parm: TextureParm::new(),
+                });
+            }
+            _ => {
+                //STENCIL_ATTACHMENT之类比COLOR_ATTACHMENT0大,减完也要在上限里
+                let Some(index) = attachment
+                    .checked_sub(gl::COLOR_ATTACHMENT0)
+                    .filter(|index| *index < caps().max_color_attachments)
+                else {
+                    return Err(GlError::Unsupported(format!(
+                        "framebuffer attachment 0x{:X}",
+                        attachment
+                    )));
+                };
+                self.attach_color(ColorAttachment {
+                    index,
+                    target: ColorTarget::Texture(texture),
+                    type_: TextureType::RGBA8,
+                    parm: TextureParm::new(),
+                    clear: [0f32; 4],
+                });
+                self.draw_buffers();
+            }
+        }
+        self.check_status()
+    }
+    fn attach_color(&mut self, color: ColorAttachment) {
+        self.bind(gl::FRAMEBUFFER);
+        color.target.attach(gl::COLOR_ATTACHMENT0 + color.index);
+        //同一个附件点再挂会替换掉旧的
+        match self
+            .colors
+            .binary_search_by_key(&color.index, |old| old.index)
+        {
+            Ok(at) => self.colors[at] = color,
+            Err(at) => self.colors.insert(at, color),
+        }
+    }
+    fn attach_depth(&mut self, depth: DepthAttachment) {
+        self.bind(gl::FRAMEBUFFER);
+        unsafe {
+            //深度纹理和renderbuffer只能有一个
+            if self.render.take().is_some() {
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    self.render_attachment,
+                    gl::RENDERBUFFER,
+                    0,
+                );
+            }
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                depth.format.attachment(),
+                gl::TEXTURE_2D,
+                depth.texture.texture,
+                0,
+            );
+        }
+        self.depth = Some(depth);
+    }
+    //location i 写到 COLOR_ATTACHMENTi,中间空着的填NONE
+    pub fn draw_buffers(&self) {
+        let len = self
+            .colors
+            .last()
+            .map_or(0, |color| color.index as usize + 1);
+        let mut buffers = vec![gl::NONE; len];
+        for color in self.colors.iter() {
+            buffers[color.index as usize] = gl::COLOR_ATTACHMENT0 + color.index;
+        }
+        self.bind(gl::FRAMEBUFFER);
+        unsafe {
+            if buffers.is_empty() {
+                //只有深度的fbo(shadow map)
+                gl::DrawBuffers(1, [gl::NONE].as_ptr());
+                gl::ReadBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + self.colors[0].index);
+            }
+        }
+    }
+    pub fn check_status(&self) -> GlResult<()> {
+        self.bind(gl::FRAMEBUFFER);
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(GlError::FrameBufferIncomplete(status));
+        }
+        Ok(())
+    }
+    pub fn link_buffer(&mut self, w: u32, h: u32, attachment: GLenum, _type: GLenum) {
+        self.attach_render(attachment, RenderBuffer::new(_type, w, h));
+    }
+    fn attach_render(&mut self, attachment: GLenum, buffer: RenderBuffer) {
+        self.bind(gl::FRAMEBUFFER);
+        let id = buffer.render_buffer;
+        self.render = Some(buffer);
+        self.render_attachment = attachment;
+        unsafe {
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, id);
+        }
+    }
+    pub fn bind(&self, type_: GLenum) {
+        state::bind_framebuffer(type_, self.frame_buffer);
+    }
+    pub fn set_clear_color(&mut self, index: u32, color: [f32; 4]) {
+        if let Some(attachment) = self.colors.iter_mut().find(|old| old.index == index) {
+            attachment.clear = color;
+        }
+    }
+    pub fn set_clear_depth(&mut self, depth: f32, stencil: i32) {
+        self.clear_depth = depth;
+        self.clear_stencil = stencil;
+    }
+    //每个附件清成自己的值;受scissor和color mask影响
+    pub fn clear(&self) {
+        self.bind(gl::FRAMEBUFFER);
+        unsafe {
+            //整数附件用ClearBufferfv是未定义的,按格式换成uiv/iv
+            for color in self.colors.iter() {
+                let index = color.index as i32;
+                if color.type_.is_unsigned_integer() {
+                    let clear = color.clear.map(|value| value as u32);
+                    gl::ClearBufferuiv(gl::COLOR, index, clear.as_ptr());
+                } else if color.type_.is_signed_integer() {
+                    let clear = color.clear.map(|value| value as i32);
+                    gl::ClearBufferiv(gl::COLOR, index, clear.as_ptr());
+                } else {
+                    gl::ClearBufferfv(gl::COLOR, index, color.clear.as_ptr());
+                }
+            }
+            let depth = match (self.depth.as_ref(), self.render.as_ref()) {
+                (Some(depth), _) => Some(depth.format),
+                (None, Some(render)) => DepthFormat::from_render(render.format),
+                (None, None) => None,
+            };
+            match depth {
+                Some(format) if format.has_stencil() => {
+                    gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, self.clear_depth, self.clear_stencil);
+                }
+                Some(_) => gl::ClearBufferfv(gl::DEPTH, 0, &self.clear_depth),
+                None => {}
+            }
+        }
+    }
+    pub fn get_size(&self) -> Option<IVec2> {
+        if let Some(color) = self.colors.first() {
+            return Some(color.target.size());
+        }
+        if let Some(depth) = self.depth.as_ref() {
+            return Some(IVec2::new(depth.texture.w as i32, depth.texture.h as i32));
+        }
+        if let Some(render) = self.render.as_ref() {
+            return Some(IVec2::new(render.w as i32, render.h as i32));
+        }
+        None
+    }
+    //所有附件按原来的格式重新分配,内容不保留
+    pub fn resize(&mut self, w: u32, h: u32) -> GlResult<()> {
+        if self.get_size() == Some(IVec2::new(w as i32, h as i32)) {
+            return Ok(());
+        }
+        //先把新的颜色附件都建好,中途失败时旧的附件还在
+        let targets = self
+            .colors
+            .iter()
+            .map(|color| {
+                let sampled = matches!(color.target, ColorTarget::Multisample(_));
+                ColorTarget::new(
+                    color.type_,
+                    color.parm,
+                    color.target.samples(),
+                    sampled,
+                    w,
+                    h,
+                )
+            })
+            .collect::<GlResult<Vec<_>>>()?;
+        for (color, target) in std::mem::take(&mut self.colors).into_iter().zip(targets) {
+            self.attach_color(ColorAttachment { target, ..color });
+        }
+        if let Some(depth) = self.depth.take() {
+            let texture = TextureWrapper(Texture2D::depth(w, h, depth.format, depth.parm));
+            self.attach_depth(DepthAttachment { texture, ..depth });
+        }
+        if let Some(render) = self.render.take() {
+            let buffer = RenderBuffer::multisample(render.format, w, h, render.samples);
+            self.attach_render(self.render_attachment, buffer);
+        }
+        self.check_status()
+    }
+
+    pub fn unbind() {
+        state::bind_framebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    pub fn blit(
+        &self,
+        other: &Self,
+        src_down: IVec2,
+        src_up: IVec2,
+        dst_down: IVec2,
+        dst_up: IVec2,
+        attachment: GLenum,
+        mode: GLenum,
+    ) {
+        self.bind(gl::READ_FRAMEBUFFER);
+        other.bind(gl::DRAW_FRAMEBUFFER);
+        unsafe {
+            gl::BlitFramebuffer(
+                src_down.x, src_down.y, src_up.x, src_up.y, dst_down.x, dst_down.y, dst_up.x,
+                dst_up.y, attachment, mode,
+            );
+        }
+    }
+    //多重采样的结果解析到target里,两边同index的颜色附件各blit一次,深度也会带过去
+    //多重采样时两边必须一样大;不是多重采样时大小不同就线性缩放
+    pub fn resolve_into(&self, target: &FrameBuffer) -> GlResult<()> {
+        let (Some(size), Some(target_size)) = (self.get_size(), target.get_size()) else {
+            return Ok(());
+        };
+        if self.samples() > 0 && size != target_size {
+            return Err(GlError::Unsupported(format!(
+                "multisample resolve from {} to {}",
+                size, target_size
+            )));
+        }
+        let filter = if size == target_size {
+            gl::NEAREST
+        } else {
+            gl::LINEAR
+        };
+        for color in self.colors.iter() {
+            if !target.colors.iter().any(|other| other.index == color.index) {
+                continue;
+            }
+            let attachment = gl::COLOR_ATTACHMENT0 + color.index;
+            self.bind(gl::READ_FRAMEBUFFER);
+            target.bind(gl::DRAW_FRAMEBUFFER);
+            unsafe {
+                gl::ReadBuffer(attachment);
+                let mut buffers = vec![gl::NONE; color.index as usize + 1];
+                buffers[color.index as usize] = attachment;
+                gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+            }
+            self.blit(
+                target,
+                IVec2::ZERO,
+                size,
+                IVec2::ZERO,
+                target_size,
+                gl::COLOR_BUFFER_BIT,
+                filter,
+            );
+        }
+        let has_depth = |frame: &FrameBuffer| frame.depth.is_some() || frame.render.is_some();
+        if has_depth(self) && has_depth(target) && size == target_size {
+            self.blit(
+                target,
+                IVec2::ZERO,
+                size,
+                IVec2::ZERO,
+                target_size,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+        self.draw_buffers();
+        target.draw_buffers();
+        Ok(())
+    }
+    pub fn blit_all(
+        &self,
+        other: &Self,
+        src_size: IVec2,
+        dst_size: IVec2,
+        attachment: GLenum,
+        mode: GLenum,
+    ) {
+        self.blit(
+            other,
+            IVec2::new(0, src_size.y),
+            IVec2::new(src_size.x, 0),
+            IVec2::new(0, dst_size.y),
+            IVec2::new(dst_size.x, 0),
+            attachment,
+            mode,
+        )
+    }
+
+    // pub fn get_now_bind_id() -> GLint {
+    //     unsafe {
+    //         let mut id: GLint = 0;
+    //         gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut id as *mut GLint);
+    //         id
+    //     }
+    // }
+    // pub fn get_now_attachment_id(attachment: GLenum) -> GLint {
+    //     // self.bind(gl::FRAMEBUFFER);
+    //     unsafe {
+    //         let mut id: GLint = 0;
+    //         gl::GetFramebufferAttachmentParameteriv(
+    //             gl::FRAMEBUFFER,
+    //             attachment,
+    //             gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME,
+    //             &mut id as *mut GLint,
+    //         );
+    //         id
+    //     }
+    // }
+}
+
+impl Drop for FrameBuffer {
+    fn drop(&mut self) {
+        Self::unbind();
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.frame_buffer);
+        }
+        state::forget_framebuffer(self.frame_buffer);
+    }
+}
+
+enum DepthTarget {
+    Texture(DepthFormat, TextureParm),
+    Buffer(DepthFormat),
+}
+
+//FrameBuffer::builder(w,h).color(..).depth_texture(..).build()
+pub struct FrameBufferBuilder {
+    w: u32,
+    h: u32,
+    colors: Vec<(TextureType, TextureParm, [f32; 4])>,
+    depth: Option<DepthTarget>,
+    samples: u32,
+    sampled: bool,
+    clear_depth: f32,
+    clear_stencil: i32,
+}
+
+impl FrameBufferBuilder {
+    pub fn new(w: u32, h: u32) -> Self {
+        Self {
+            w,
+            h,
+            colors: Vec::new(),
+            depth: None,
+            samples: 0,
+            sampled: false,
+            clear_depth: 1f32,
+            clear_stencil: 0,
+        }
+    }
+    //大于0时颜色和深度都用多重采样,画完resolve_into到普通fbo
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+    //多重采样的颜色用TEXTURE_2D_MULTISAMPLE而不是renderbuffer
+    pub fn multisample_texture(mut self, sampled: bool) -> Self {
+        self.sampled = sampled;
+        self
+    }
+    //按调用顺序依次是COLOR_ATTACHMENT0,1,2...
+    pub fn color(self, type_: TextureType) -> Self {
+        self.color_with(type_, TextureParm::new(), [0f32; 4])
+    }
+    pub fn color_with(mut self, type_: TextureType, parm: TextureParm, clear: [f32; 4]) -> Self {
+        self.colors.push((type_, parm, clear));
+        self
+    }
+    //可以采样的深度纹理
+    pub fn depth_texture(mut self, format: DepthFormat, parm: TextureParm) -> Self {
+        self.depth = Some(DepthTarget::Texture(format, parm));
+        self
+    }
+    pub fn depth_buffer(mut self, format: DepthFormat) -> Self {
+        self.depth = Some(DepthTarget::Buffer(format));
+        self
+    }
+    pub fn clear_depth(mut self, depth: f32, stencil: i32) -> Self {
+        self.clear_depth = depth;
+        self.clear_stencil = stencil;
+        self
+    }
+    pub fn build(self) -> GlResult<FrameBuffer> {
+        let (w, h) = (self.w, self.h);
+        let max = caps().max_color_attachments;
+        if self.colors.len() > max as usize {
+            return Err(GlError::Unsupported(format!(
+                "{} color attachments, max {}",
+                self.colors.len(),
+                max
+            )));
+        }
+        let mut frame = FrameBuffer::new();
+        frame.set_clear_depth(self.clear_depth, self.clear_stencil);
+        for (index, (type_, parm, clear)) in self.colors.into_iter().enumerate() {
+            frame.attach_color(ColorAttachment {
+                index: index as u32,
+                target: ColorTarget::new(type_, parm, self.samples, self.sampled, w, h)?,
+                type_,
+                parm,
+                clear,
+            });
+        }
+        match self.depth {
+            Some(DepthTarget::Texture(..)) if self.samples > 0 => {
+                return Err(GlError::Unsupported(
+                    "multisample depth texture".to_string(),
+                ));
+            }
+            Some(DepthTarget::Texture(format, parm)) => frame.attach_depth(DepthAttachment {
+                texture: TextureWrapper(Texture2D::depth(w, h, format, parm)),
+                format,
+                parm,
+            }),
+            Some(DepthTarget::Buffer(format)) => frame.attach_render(
+                format.attachment(),
+                RenderBuffer::multisample(format.as_gl().0, w, h, self.samples),
+            ),
+            None => {}
+        }
+        frame.draw_buffers();
+        frame.check_status()?;
+        Ok(frame)
+    }
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use crate::gl_unit::{
+        define::{TextureParm, TextureType},
+        golden::with_shared_context,
+        texture::{Texture2D, TextureWrapper},
+    };
+
+    use super::{DepthFormat, FrameBuffer};
+
+    #[test]
+    fn multiple_targets() {
+        with_shared_context(|_| {
+            let mut frame = FrameBuffer::builder(16, 16)
+                .color_with(
+                    TextureType::RGBA8,
+                    TextureParm::new(),
+                    [1f32, 0f32, 0f32, 1f32],
+                )
+                .color_with(
+                    TextureType::RGBA8,
+                    TextureParm::new(),
+                    [0f32, 0f32, 1f32, 1f32],
+                )
+                .depth_texture(DepthFormat::Depth24, TextureParm::new())
+                .build()
+                .unwrap();
+            frame.clear();
+            let pixel = |index| {
+                frame
+                    .color(index)
+                    .unwrap()
+                    .get_image()
+                    .to_rgba8()
+                    .get_pixel(3, 3)
+                    .0
+            };
+            assert_eq!(pixel(0), [255, 0, 0, 255]);
+            assert_eq!(pixel(1), [0, 0, 255, 255]);
+
+            frame.resize(32, 8).unwrap();
+            assert_eq!(frame.depth_texture().unwrap().w, 32);
+            assert_eq!(frame.color(1).unwrap().h, 8);
+        });
+    }
+
+    #[test]
+    fn resolve_multisample() {
+        with_shared_context(|_| {
+            let msaa = FrameBuffer::builder(16, 16)
+                .samples(4)
+                .color_with(
+                    TextureType::RGBA8,
+                    TextureParm::new(),
+                    [0f32, 1f32, 0f32, 1f32],
+                )
+                .depth_buffer(DepthFormat::Depth24Stencil8)
+                .build()
+                .unwrap();
+            assert_eq!(msaa.samples(), 4);
+            assert!(msaa.color(0).is_none());
+            let resolve = FrameBuffer::builder(16, 16)
+                .color(TextureType::RGBA8)
+                .build()
+                .unwrap();
+            msaa.clear();
+            msaa.resolve_into(&resolve).unwrap();
+            let small = FrameBuffer::builder(8, 8)
+                .color(TextureType::RGBA8)
+                .build()
+                .unwrap();
+            assert!(msaa.resolve_into(&small).is_err());
+            let image = resolve.texture().unwrap().get_image().to_rgba8();
+            assert_eq!(image.get_pixel(8, 8).0, [0, 255, 0, 255]);
+        });
+    }
+
+    #[test]
+    fn integer_target() {
+        with_shared_context(|_| {
+            //picking buffer:颜色+物体id
+            let mut frame = FrameBuffer::builder(16, 16)
+                .color(TextureType::RGBA8)
+                .color_with(
+                    TextureType::RED32UI,
+                    TextureParm::new(),
+                    [42f32, 0f32, 0f32, 0f32],
+                )
+                .build()
+                .unwrap();
+            frame.clear();
+            let mut id = 0u32;
+            frame.bind(gl::READ_FRAMEBUFFER);
+            unsafe {
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT1);
+                gl::ReadPixels(
+                    3,
+                    3,
+                    1,
+                    1,
+                    gl::RED_INTEGER,
+                    gl::UNSIGNED_INT,
+                    &mut id as *mut u32 as *mut _,
+                );
+            }
+            assert_eq!(id, 42);
+
+            let texture = TextureWrapper(Texture2D::with_size(
+                16,
+                16,
+                TextureType::RGBA8,
+                TextureParm::new(),
+            ));
+            assert!(
+                frame
+                    .try_link_texture(texture, gl::STENCIL_ATTACHMENT)
+                    .is_err()
+            );
+        });
+    }
+}
+
+#[cfg(all(test, feature = "mock-gl"))]
+mod mock_test {
+    use glam::IVec2;
+
+    use crate::gl_unit::{
+        caps::{Caps, parse_version, set_caps},
+        define::TextureType,
+        error::GlError,
+        mock,
+    };
+
+    use super::FrameBufferBuilder;
+
+    #[test]
+    fn failed_resize_keeps_targets() {
+        mock::load();
+        set_caps(Caps::desktop(3, 3));
+        let mut frame = FrameBufferBuilder::new(4, 4)
+            .color(TextureType::RGBA8)
+            .color(TextureType::RED16)
+            .build()
+            .unwrap();
+        //第二个附件在es上建不出来,第一个不能已经换掉
+        set_caps(parse_version("OpenGL ES 3.0"));
+        assert!(matches!(frame.resize(8, 8), Err(GlError::Unsupported(_))));
+        assert_eq!(frame.colors.len(), 2);
+        assert!(
+            frame
+                .colors
+                .iter()
+                .all(|color| color.target.size() == IVec2::new(4, 4))
+        );
+    }
+}
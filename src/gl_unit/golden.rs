@@ -0,0 +1,255 @@
+use std::{
+    fmt::{Display, Formatter},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use image::{ImageFormat, Rgba, RgbaImage, imageops};
+
+use super::{FrameBuffer, GLcontext, finish, state};
+
+//golden图片默认目录,GOLDEN_UPDATE=1 时重写
+pub const GOLDEN_DIR: &str = "./test_res/golden";
+
+pub struct GoldenDiff {
+    pub mismatched: usize,
+    pub max_diff: u8,
+    pub size_mismatch: bool,
+    pub diff: RgbaImage,
+}
+impl GoldenDiff {
+    pub const fn is_pass(&self) -> bool {
+        self.mismatched == 0 && !self.size_mismatch
+    }
+}
+impl Display for GoldenDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.size_mismatch {
+            return write!(f, "image size mismatch");
+        }
+        write!(
+            f,
+            "{} pixels mismatched (max channel diff {})",
+            self.mismatched, self.max_diff
+        )
+    }
+}
+
+//读回frame buffer,翻成图片坐标(左上角原点)
+pub fn read_frame(frame_buffer: &FrameBuffer) -> RgbaImage {
+    finish();
+    let image = frame_buffer
+        .texture()
+        .expect("golden frame buffer no texture")
+        .get_image()
+        .to_rgba8();
+    imageops::flip_vertical(&image)
+}
+
+pub fn render_frame(frame_buffer: &FrameBuffer, func: impl FnOnce()) -> RgbaImage {
+    frame_buffer.bind(gl::FRAMEBUFFER);
+    frame_buffer.view_port();
+    unsafe {
+        gl::ClearColor(0f32, 0f32, 0f32, 0f32);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
+    func();
+    let image = read_frame(frame_buffer);
+    FrameBuffer::unbind();
+    image
+}
+
+//STREAM_VERTEX/QUAD_INDEX/UI_VAO这些全局gl对象只在创建它们的context里有效,
+//glfw也不让在别的线程上建context,所以要context的测试都丢到同一个线程的同一个context里跑
+type Job = Box<dyn FnOnce(&mut GLcontext) + Send>;
+static SHARED: LazyLock<Mutex<Sender<Job>>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    thread::spawn(move || {
+        let mut context = GLcontext::headless(1, 1);
+        for job in receiver {
+            job(&mut context);
+        }
+    });
+    Mutex::new(sender)
+});
+
+//测试里改的渲染状态跑完都还原,panic了也一样;panic原样抛回调用的测试线程
+pub fn with_shared_context<R: Send + 'static>(
+    func: impl FnOnce(&mut GLcontext) -> R + Send + 'static,
+) -> R {
+    let (sender, receiver) = mpsc::channel();
+    SHARED
+        .lock()
+        .unwrap()
+        .send(Box::new(move |context| {
+            state::push();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| func(context)));
+            state::pop();
+            let _ = sender.send(result);
+        }))
+        .expect("shared gl context thread exited");
+    match receiver.recv().expect("shared gl context thread exited") {
+        Ok(value) => value,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+//共享context的frame buffer先调成w*h,清屏色设回透明黑
+pub fn with_headless<R: Send + 'static>(
+    w: u32,
+    h: u32,
+    func: impl FnOnce(&mut GLcontext) -> R + Send + 'static,
+) -> R {
+    with_shared_context(move |context| {
+        let frame_buffer = context.frame_buffer.as_mut().unwrap();
+        frame_buffer.resize(w, h).unwrap();
+        frame_buffer.view_port();
+        unsafe {
+            gl::ClearColor(0f32, 0f32, 0f32, 0f32);
+        }
+        func(context)
+    })
+}
+
+pub fn render_headless(
+    w: u32,
+    h: u32,
+    func: impl FnOnce(&mut GLcontext) + Send + 'static,
+) -> RgbaImage {
+    with_headless(w, h, move |context| {
+        context.draw_headless(func);
+        read_frame(context.frame_buffer.as_ref().unwrap())
+    })
+}
+
+//逐通道比较,超过tolerance的像素在diff里标红
+pub fn compare_image(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> GoldenDiff {
+    if actual.dimensions() != expected.dimensions() {
+        return GoldenDiff {
+            mismatched: 0,
+            max_diff: 0,
+            size_mismatch: true,
+            diff: actual.clone(),
+        };
+    }
+    let mut mismatched = 0;
+    let mut max_diff = 0;
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    for ((a, e), d) in actual.pixels().zip(expected.pixels()).zip(diff.pixels_mut()) {
+        let channel = a
+            .0
+            .iter()
+            .zip(e.0.iter())
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap();
+        max_diff = max_diff.max(channel);
+        *d = if channel > tolerance {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let gray = ((e.0[0] as u32 + e.0[1] as u32 + e.0[2] as u32) / 3 / 4) as u8;
+            Rgba([gray, gray, gray, 255])
+        };
+    }
+    GoldenDiff {
+        mismatched,
+        max_diff,
+        size_mismatch: false,
+        diff,
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+//GOLDEN_UPDATE设了才写入golden,不存在时算失败;失败时在旁边写 .actual.png 和 .diff.png
+pub fn check_golden(path: impl AsRef<Path>, actual: &RgbaImage, tolerance: u8) -> Result<(), String> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        return actual
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(|err| err.to_string());
+    }
+    if !path.exists() {
+        let actual_path = sibling(path, "actual");
+        actual
+            .save_with_format(&actual_path, ImageFormat::Png)
+            .map_err(|err| err.to_string())?;
+        return Err(format!(
+            "golden {:?} missing, run with GOLDEN_UPDATE=1 to accept {:?}",
+            path, actual_path
+        ));
+    }
+    let expected = image::open(path).map_err(|err| err.to_string())?.to_rgba8();
+    let result = compare_image(actual, &expected, tolerance);
+    if result.is_pass() {
+        return Ok(());
+    }
+    let actual_path = sibling(path, "actual");
+    let diff_path = sibling(path, "diff");
+    actual
+        .save_with_format(&actual_path, ImageFormat::Png)
+        .map_err(|err| err.to_string())?;
+    result
+        .diff
+        .save_with_format(&diff_path, ImageFormat::Png)
+        .map_err(|err| err.to_string())?;
+    Err(format!(
+        "golden {:?}: {}, see {:?}",
+        path, result, diff_path
+    ))
+}
+
+pub fn assert_golden(name: &str, actual: &RgbaImage, tolerance: u8) {
+    let path = Path::new(GOLDEN_DIR).join(format!("{}.png", name));
+    if let Err(err) = check_golden(path, actual, tolerance) {
+        panic!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::{Rgba, RgbaImage};
+
+    use super::{check_golden, compare_image};
+
+    #[test]
+    fn compare_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 1, Rgba([103, 100, 100, 255]));
+        assert!(compare_image(&actual, &expected, 3).is_pass());
+
+        actual.put_pixel(2, 2, Rgba([120, 100, 100, 255]));
+        let result = compare_image(&actual, &expected, 3);
+        assert_eq!(result.mismatched, 1);
+        assert_eq!(result.max_diff, 20);
+        assert_eq!(result.diff.get_pixel(2, 2).0, [255, 0, 0, 255]);
+
+        let small = RgbaImage::new(2, 2);
+        assert!(compare_image(&small, &expected, 255).size_mismatch);
+    }
+
+    #[test]
+    fn missing_golden() {
+        let path = std::env::temp_dir().join("gl_tools_golden/missing.png");
+        let _ = std::fs::remove_file(&path);
+        let image = RgbaImage::new(2, 2);
+        //没有GOLDEN_UPDATE时不能悄悄写入当成通过
+        if std::env::var_os("GOLDEN_UPDATE").is_none() {
+            assert!(check_golden(&path, &image, 0).is_err());
+            assert!(!path.exists());
+        }
+    }
+}
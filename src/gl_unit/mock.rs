@@ -0,0 +1,646 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::c_void,
+    fmt::{Display, Formatter},
+    ptr::null,
+};
+
+use gl::types::{
+    GLbitfield, GLboolean, GLchar, GLenum, GLfloat, GLint, GLint64, GLintptr, GLsizei, GLsizeiptr,
+    GLubyte, GLuint, GLuint64,
+};
+
+use super::{caps, state};
+
+//假的gl后端:函数指针换成只记录调用的实现,没有context也能测调用顺序/次数
+//函数指针是整个进程共用的,开了mock-gl以后要真context的测试都不编译(cfg(not(feature = "mock-gl"))),
+//不然并行的测试会互相换掉函数指针;记录是每个线程一份,mock的测试之间互不干扰
+//所以 cargo test --features mock-gl 只跑mock的测试,golden/headless这些要真context的一个都不会跑,
+//两种都要覆盖就 cargo test 和 cargo test --features mock-gl 各跑一遍
+
+//一次gl调用,参数用Debug格式存
+#[derive(Clone, PartialEq, Debug)]
+pub struct GlCall {
+    pub name: &'static str,
+    pub args: Vec<String>,
+}
+impl Display for GlCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gl{}({})", self.name, self.args.join(", "))
+    }
+}
+
+thread_local! {
+    static LOG: RefCell<Vec<GlCall>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: Cell<GLuint> = const { Cell::new(1) };
+    static MAPPED: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(name: &'static str, args: Vec<String>) {
+    LOG.with_borrow_mut(|log| log.push(GlCall { name, args }));
+}
+//Gen*都从这里拿id,不会重复
+fn gen_ids(n: GLsizei, ids: *mut GLuint) {
+    for index in 0..n.max(0) as usize {
+        let id = NEXT_ID.get();
+        NEXT_ID.set(id + 1);
+        unsafe {
+            *ids.add(index) = id;
+        }
+    }
+}
+fn next_id() -> GLuint {
+    let mut id = 0;
+    gen_ids(1, &mut id);
+    id
+}
+fn write<T>(ptr: *mut T, value: T) {
+    unsafe {
+        *ptr = value;
+    }
+}
+//新context的初值,绑定之类的都是0;viewport保持调用方给的初值
+fn get_integer(name: GLenum, data: *mut GLint) {
+    match name {
+        gl::MAJOR_VERSION => write(data, 4),
+        gl::MINOR_VERSION => write(data, 6),
+        gl::ACTIVE_TEXTURE => write(data, gl::TEXTURE0 as GLint),
+        gl::DEPTH_FUNC => write(data, gl::LESS as GLint),
+        gl::VIEWPORT => {}
+        _ => write(data, 0),
+    }
+}
+fn get_boolean(name: GLenum, data: *mut GLboolean) {
+    let count = match name {
+        gl::DEPTH_WRITEMASK => 1,
+        gl::COLOR_WRITEMASK => 4,
+        _ => 0,
+    };
+    for index in 0..count {
+        write(unsafe { data.add(index) }, gl::TRUE);
+    }
+}
+//MapBufferRange给一块线程自己的内存,写进去的数据直接丢掉
+fn map(length: GLsizeiptr) -> *mut c_void {
+    MAPPED.with_borrow_mut(|mapped| {
+        mapped.resize(mapped.len().max(length.max(0) as usize), 0);
+        mapped.as_mut_ptr() as *mut c_void
+    })
+}
+
+const VERSION: &[u8] = b"4.6 (Core Profile) mock\0";
+
+//每个假函数先记一笔,再执行body
+macro_rules! mock_fns {
+    ($($name:ident($($arg:ident: $type:ty),*) $(-> $ret:ty)? $body:block)*) => {
+        $(
+            #[allow(non_snake_case, clippy::too_many_arguments)]
+            extern "system" fn $name($($arg: $type),*) $(-> $ret)? {
+                record(stringify!($name), vec![$(format!("{:?}", $arg)),*]);
+                $body
+            }
+        )*
+        fn proc_address(name: &str) -> *const c_void {
+            match name.strip_prefix("gl") {
+                $(Some(stringify!($name)) => $name as *const c_void,)*
+                _ => null(),
+            }
+        }
+    };
+}
+
+//代码里用到的gl函数都有假实现,Gen*/Create*发递增的id,状态查询返回gl的初始值,编译/链接/fbo检查都算成功
+mock_fns! {
+    ActiveTexture(texture: GLenum) {}
+    AttachShader(program: GLuint, shader: GLuint) {}
+    BeginQuery(target: GLenum, id: GLuint) {}
+    BindBuffer(target: GLenum, buffer: GLuint) {}
+    BindBufferBase(target: GLenum, index: GLuint, buffer: GLuint) {}
+    BindBufferRange(
+        target: GLenum,
+        index: GLuint,
+        buffer: GLuint,
+        offset: GLintptr,
+        size: GLsizeiptr
+    ) {}
+    BindFramebuffer(target: GLenum, framebuffer: GLuint) {}
+    BindImageTexture(
+        unit: GLuint,
+        texture: GLuint,
+        level: GLint,
+        layered: GLboolean,
+        layer: GLint,
+        access: GLenum,
+        format: GLenum
+    ) {}
+    BindRenderbuffer(target: GLenum, renderbuffer: GLuint) {}
+    BindSampler(unit: GLuint, sampler: GLuint) {}
+    BindTexture(target: GLenum, texture: GLuint) {}
+    BindVertexArray(array: GLuint) {}
+    BlendColor(red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat) {}
+    BlendEquation(mode: GLenum) {}
+    BlendEquationSeparate(mode_rgb: GLenum, mode_alpha: GLenum) {}
+    BlendFunc(sfactor: GLenum, dfactor: GLenum) {}
+    BlendFuncSeparate(
+        sfactor_rgb: GLenum,
+        dfactor_rgb: GLenum,
+        sfactor_alpha: GLenum,
+        dfactor_alpha: GLenum
+    ) {}
+    BlitFramebuffer(
+        src_x0: GLint,
+        src_y0: GLint,
+        src_x1: GLint,
+        src_y1: GLint,
+        dst_x0: GLint,
+        dst_y0: GLint,
+        dst_x1: GLint,
+        dst_y1: GLint,
+        mask: GLbitfield,
+        filter: GLenum
+    ) {}
+    BufferData(target: GLenum, size: GLsizeiptr, data: *const c_void, usage: GLenum) {}
+    BufferSubData(target: GLenum, offset: GLintptr, size: GLsizeiptr, data: *const c_void) {}
+    CheckFramebufferStatus(target: GLenum) -> GLenum { gl::FRAMEBUFFER_COMPLETE }
+    Clear(mask: GLbitfield) {}
+    ClearBufferfi(buffer: GLenum, drawbuffer: GLint, depth: GLfloat, stencil: GLint) {}
+    ClearBufferfv(buffer: GLenum, drawbuffer: GLint, value: *const GLfloat) {}
+    ClearColor(red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat) {}
+    ColorMask(red: GLboolean, green: GLboolean, blue: GLboolean, alpha: GLboolean) {}
+    CompileShader(shader: GLuint) {}
+    CompressedTexImage2D(
+        target: GLenum,
+        level: GLint,
+        internalformat: GLenum,
+        width: GLsizei,
+        height: GLsizei,
+        border: GLint,
+        image_size: GLsizei,
+        data: *const c_void
+    ) {}
+    CreateProgram() -> GLuint { next_id() }
+    CreateShader(type_: GLenum) -> GLuint { next_id() }
+    CullFace(mode: GLenum) {}
+    DeleteBuffers(n: GLsizei, buffers: *const GLuint) {}
+    DeleteFramebuffers(n: GLsizei, framebuffers: *const GLuint) {}
+    DeleteProgram(program: GLuint) {}
+    DeleteQueries(n: GLsizei, ids: *const GLuint) {}
+    DeleteRenderbuffers(n: GLsizei, renderbuffers: *const GLuint) {}
+    DeleteSamplers(count: GLsizei, samplers: *const GLuint) {}
+    DeleteShader(shader: GLuint) {}
+    DeleteTextures(n: GLsizei, textures: *const GLuint) {}
+    DeleteVertexArrays(n: GLsizei, arrays: *const GLuint) {}
+    DepthFunc(func: GLenum) {}
+    DepthMask(flag: GLboolean) {}
+    DetachShader(program: GLuint, shader: GLuint) {}
+    Disable(cap: GLenum) {}
+    DisableVertexAttribArray(index: GLuint) {}
+    DispatchCompute(num_groups_x: GLuint, num_groups_y: GLuint, num_groups_z: GLuint) {}
+    DrawArrays(mode: GLenum, first: GLint, count: GLsizei) {}
+    DrawArraysIndirect(mode: GLenum, indirect: *const c_void) {}
+    DrawArraysInstanced(mode: GLenum, first: GLint, count: GLsizei, instancecount: GLsizei) {}
+    DrawArraysInstancedBaseInstance(
+        mode: GLenum,
+        first: GLint,
+        count: GLsizei,
+        instancecount: GLsizei,
+        baseinstance: GLuint
+    ) {}
+    DrawBuffers(n: GLsizei, bufs: *const GLenum) {}
+    DrawElements(mode: GLenum, count: GLsizei, type_: GLenum, indices: *const c_void) {}
+    DrawElementsBaseVertex(
+        mode: GLenum,
+        count: GLsizei,
+        type_: GLenum,
+        indices: *const c_void,
+        basevertex: GLint
+    ) {}
+    DrawElementsIndirect(mode: GLenum, type_: GLenum, indirect: *const c_void) {}
+    DrawElementsInstanced(
+        mode: GLenum,
+        count: GLsizei,
+        type_: GLenum,
+        indices: *const c_void,
+        instancecount: GLsizei
+    ) {}
+    DrawElementsInstancedBaseVertex(
+        mode: GLenum,
+        count: GLsizei,
+        type_: GLenum,
+        indices: *const c_void,
+        instancecount: GLsizei,
+        basevertex: GLint
+    ) {}
+    DrawElementsInstancedBaseVertexBaseInstance(
+        mode: GLenum,
+        count: GLsizei,
+        type_: GLenum,
+        indices: *const c_void,
+        instancecount: GLsizei,
+        basevertex: GLint,
+        baseinstance: GLuint
+    ) {}
+    Enable(cap: GLenum) {}
+    EnableVertexAttribArray(index: GLuint) {}
+    EndQuery(target: GLenum) {}
+    Finish() {}
+    Flush() {}
+    FramebufferRenderbuffer(
+        target: GLenum,
+        attachment: GLenum,
+        renderbuffertarget: GLenum,
+        renderbuffer: GLuint
+    ) {}
+    FramebufferTexture2D(
+        target: GLenum,
+        attachment: GLenum,
+        textarget: GLenum,
+        texture: GLuint,
+        level: GLint
+    ) {}
+    GenBuffers(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenFramebuffers(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenQueries(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenRenderbuffers(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenSamplers(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenTextures(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenVertexArrays(n: GLsizei, ids: *mut GLuint) { gen_ids(n, ids) }
+    GenerateMipmap(target: GLenum) {}
+    GetActiveAttrib(
+        program: GLuint,
+        index: GLuint,
+        buf_size: GLsizei,
+        length: *mut GLsizei,
+        size: *mut GLint,
+        type_: *mut GLenum,
+        name: *mut GLchar
+    ) {}
+    GetActiveUniform(
+        program: GLuint,
+        index: GLuint,
+        buf_size: GLsizei,
+        length: *mut GLsizei,
+        size: *mut GLint,
+        type_: *mut GLenum,
+        name: *mut GLchar
+    ) {}
+    GetAttribLocation(program: GLuint, name: *const GLchar) -> GLint { 0 }
+    GetBooleanv(pname: GLenum, data: *mut GLboolean) { get_boolean(pname, data) }
+    GetError() -> GLenum { gl::NO_ERROR }
+    GetFloatv(pname: GLenum, data: *mut GLfloat) {}
+    GetFramebufferAttachmentParameteriv(
+        target: GLenum,
+        attachment: GLenum,
+        pname: GLenum,
+        params: *mut GLint
+    ) {}
+    GetInteger64v(pname: GLenum, data: *mut GLint64) {}
+    GetIntegerv(pname: GLenum, data: *mut GLint) { get_integer(pname, data) }
+    GetProgramInfoLog(
+        program: GLuint,
+        buf_size: GLsizei,
+        length: *mut GLsizei,
+        info_log: *mut GLchar
+    ) {}
+    GetProgramResourceIndex(
+        program: GLuint,
+        program_interface: GLenum,
+        name: *const GLchar
+    ) -> GLuint { 0 }
+    GetProgramiv(
+        program: GLuint,
+        pname: GLenum,
+        params: *mut GLint
+    ) { write(params, (pname == gl::LINK_STATUS) as GLint) }
+    GetQueryObjectui64v(id: GLuint, pname: GLenum, params: *mut GLuint64) {}
+    GetQueryObjectuiv(id: GLuint, pname: GLenum, params: *mut GLuint) { write(params, 1) }
+    GetShaderInfoLog(
+        shader: GLuint,
+        buf_size: GLsizei,
+        length: *mut GLsizei,
+        info_log: *mut GLchar
+    ) {}
+    GetShaderiv(
+        shader: GLuint,
+        pname: GLenum,
+        params: *mut GLint
+    ) { write(params, (pname == gl::COMPILE_STATUS) as GLint) }
+    GetString(name: GLenum) -> *const GLubyte { VERSION.as_ptr() }
+    GetTexImage(target: GLenum, level: GLint, format: GLenum, type_: GLenum, pixels: *mut c_void) {}
+    GetUniformBlockIndex(program: GLuint, uniform_block_name: *const GLchar) -> GLuint { 0 }
+    GetUniformLocation(program: GLuint, name: *const GLchar) -> GLint { 0 }
+    GetVertexAttribiv(index: GLuint, pname: GLenum, params: *mut GLint) {}
+    IsEnabled(cap: GLenum) -> GLboolean { gl::FALSE }
+    LineWidth(width: GLfloat) {}
+    LinkProgram(program: GLuint) {}
+    MapBufferRange(
+        target: GLenum,
+        offset: GLintptr,
+        length: GLsizeiptr,
+        access: GLbitfield
+    ) -> *mut c_void { map(length) }
+    MemoryBarrier(barriers: GLbitfield) {}
+    MultiDrawArraysIndirect(
+        mode: GLenum,
+        indirect: *const c_void,
+        drawcount: GLsizei,
+        stride: GLsizei
+    ) {}
+    MultiDrawElementsIndirect(
+        mode: GLenum,
+        type_: GLenum,
+        indirect: *const c_void,
+        drawcount: GLsizei,
+        stride: GLsizei
+    ) {}
+    ObjectLabel(identifier: GLenum, name: GLuint, length: GLsizei, label: *const GLchar) {}
+    PixelStorei(pname: GLenum, param: GLint) {}
+    PointSize(size: GLfloat) {}
+    PolygonMode(face: GLenum, mode: GLenum) {}
+    PopDebugGroup() {}
+    PushDebugGroup(source: GLenum, id: GLuint, length: GLsizei, message: *const GLchar) {}
+    QueryCounter(id: GLuint, target: GLenum) {}
+    ReadBuffer(src: GLenum) {}
+    ReadPixels(
+        x: GLint,
+        y: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *mut c_void
+    ) {}
+    RenderbufferStorage(target: GLenum, internalformat: GLenum, width: GLsizei, height: GLsizei) {}
+    RenderbufferStorageMultisample(
+        target: GLenum,
+        samples: GLsizei,
+        internalformat: GLenum,
+        width: GLsizei,
+        height: GLsizei
+    ) {}
+    SamplerParameterf(sampler: GLuint, pname: GLenum, param: GLfloat) {}
+    SamplerParameterfv(sampler: GLuint, pname: GLenum, param: *const GLfloat) {}
+    SamplerParameteri(sampler: GLuint, pname: GLenum, param: GLint) {}
+    Scissor(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {}
+    ShaderSource(
+        shader: GLuint,
+        count: GLsizei,
+        string: *const *const GLchar,
+        length: *const GLint
+    ) {}
+    ShaderStorageBlockBinding(
+        program: GLuint,
+        storage_block_index: GLuint,
+        storage_block_binding: GLuint
+    ) {}
+    StencilFuncSeparate(face: GLenum, func: GLenum, ref_: GLint, mask: GLuint) {}
+    StencilMask(mask: GLuint) {}
+    StencilMaskSeparate(face: GLenum, mask: GLuint) {}
+    StencilOpSeparate(face: GLenum, sfail: GLenum, dpfail: GLenum, dppass: GLenum) {}
+    TexImage1D(
+        target: GLenum,
+        level: GLint,
+        internalformat: GLint,
+        width: GLsizei,
+        border: GLint,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *const c_void
+    ) {}
+    TexImage2D(
+        target: GLenum,
+        level: GLint,
+        internalformat: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        border: GLint,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *const c_void
+    ) {}
+    TexImage2DMultisample(
+        target: GLenum,
+        samples: GLsizei,
+        internalformat: GLenum,
+        width: GLsizei,
+        height: GLsizei,
+        fixedsamplelocations: GLboolean
+    ) {}
+    TexImage3D(
+        target: GLenum,
+        level: GLint,
+        internalformat: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        depth: GLsizei,
+        border: GLint,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *const c_void
+    ) {}
+    TexParameterf(target: GLenum, pname: GLenum, param: GLfloat) {}
+    TexParameterfv(target: GLenum, pname: GLenum, params: *const GLfloat) {}
+    TexParameteri(target: GLenum, pname: GLenum, param: GLint) {}
+    TexStorage2DMultisample(
+        target: GLenum,
+        samples: GLsizei,
+        internalformat: GLenum,
+        width: GLsizei,
+        height: GLsizei,
+        fixedsamplelocations: GLboolean
+    ) {}
+    TexSubImage2D(
+        target: GLenum,
+        level: GLint,
+        xoffset: GLint,
+        yoffset: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *const c_void
+    ) {}
+    TexSubImage3D(
+        target: GLenum,
+        level: GLint,
+        xoffset: GLint,
+        yoffset: GLint,
+        zoffset: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        depth: GLsizei,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *const c_void
+    ) {}
+    Uniform1f(location: GLint, v0: GLfloat) {}
+    Uniform1fv(location: GLint, count: GLsizei, value: *const GLfloat) {}
+    Uniform1i(location: GLint, v0: GLint) {}
+    Uniform1iv(location: GLint, count: GLsizei, value: *const GLint) {}
+    Uniform1uiv(location: GLint, count: GLsizei, value: *const GLuint) {}
+    Uniform2fv(location: GLint, count: GLsizei, value: *const GLfloat) {}
+    Uniform2iv(location: GLint, count: GLsizei, value: *const GLint) {}
+    Uniform2uiv(location: GLint, count: GLsizei, value: *const GLuint) {}
+    Uniform3fv(location: GLint, count: GLsizei, value: *const GLfloat) {}
+    Uniform3iv(location: GLint, count: GLsizei, value: *const GLint) {}
+    Uniform3uiv(location: GLint, count: GLsizei, value: *const GLuint) {}
+    Uniform4f(location: GLint, v0: GLfloat, v1: GLfloat, v2: GLfloat, v3: GLfloat) {}
+    Uniform4fv(location: GLint, count: GLsizei, value: *const GLfloat) {}
+    Uniform4iv(location: GLint, count: GLsizei, value: *const GLint) {}
+    Uniform4uiv(location: GLint, count: GLsizei, value: *const GLuint) {}
+    UniformBlockBinding(
+        program: GLuint,
+        uniform_block_index: GLuint,
+        uniform_block_binding: GLuint
+    ) {}
+    UniformMatrix2fv(
+        location: GLint,
+        count: GLsizei,
+        transpose: GLboolean,
+        value: *const GLfloat
+    ) {}
+    UniformMatrix3fv(
+        location: GLint,
+        count: GLsizei,
+        transpose: GLboolean,
+        value: *const GLfloat
+    ) {}
+    UniformMatrix4fv(
+        location: GLint,
+        count: GLsizei,
+        transpose: GLboolean,
+        value: *const GLfloat
+    ) {}
+    UnmapBuffer(target: GLenum) -> GLboolean { gl::TRUE }
+    UseProgram(program: GLuint) {}
+    VertexAttribDivisor(index: GLuint, divisor: GLuint) {}
+    VertexAttribIPointer(
+        index: GLuint,
+        size: GLint,
+        type_: GLenum,
+        stride: GLsizei,
+        pointer: *const c_void
+    ) {}
+    VertexAttribPointer(
+        index: GLuint,
+        size: GLint,
+        type_: GLenum,
+        normalized: GLboolean,
+        stride: GLsizei,
+        pointer: *const c_void
+    ) {}
+    Viewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {}
+}
+
+//把gl函数指针换成假的,caps当成桌面4.6,状态缓存按gl初始值;没有假实现的函数调用时会panic
+pub fn load() {
+    gl::load_with(proc_address);
+    caps::detect();
+    state::query();
+    clear();
+}
+//清空当前线程的记录
+pub fn clear() {
+    LOG.with_borrow_mut(Vec::clear);
+}
+pub fn calls() -> Vec<GlCall> {
+    LOG.with_borrow(Vec::clone)
+}
+//清空并返回之前的记录
+pub fn take() -> Vec<GlCall> {
+    LOG.with_borrow_mut(std::mem::take)
+}
+pub fn count(name: &str) -> usize {
+    LOG.with_borrow(|log| log.iter().filter(|call| call.name == name).count())
+}
+//draw call的个数,DrawBuffers不算
+pub fn draw_calls() -> usize {
+    LOG.with_borrow(|log| {
+        log.iter()
+            .filter(|call| call.name.starts_with("Draw") || call.name.starts_with("MultiDraw"))
+            .filter(|call| !call.name.starts_with("DrawBuffer"))
+            .count()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        BufferConst,
+        gl_unit::{
+            VertexArray,
+            define::{
+                BufferTarget, BufferUsage, DrawMode, TextureParm, TextureType,
+                VertexArrayAttribPointerGen,
+            },
+            state,
+            texture::{Texture2D, TextureMap, TextureWrapper},
+        },
+    };
+
+    use super::{GlCall, calls, count, draw_calls, load, take};
+
+    #[test]
+    fn records_draws_and_binds() {
+        load();
+        let buffer = BufferConst::new(BufferTarget::Vertex, &[0f32; 8], BufferUsage::Static);
+        let vao = VertexArray::new();
+        take();
+        vao.bind(|vao| {
+            vao.bind_pointer(&buffer, VertexArrayAttribPointerGen::new::<f32>(0, 2));
+            for _ in 0..3 {
+                state::use_program(7);
+                vao.draw_arrays(DrawMode::Triangles, 0, 4);
+            }
+        });
+        //状态缓存挡掉了重复的UseProgram
+        assert_eq!(count("UseProgram"), 1);
+        assert_eq!(draw_calls(), 3);
+        assert_eq!(
+            calls().iter().find(|call| call.name == "DrawArrays"),
+            Some(&GlCall {
+                name: "DrawArrays",
+                args: vec![gl::TRIANGLES.to_string(), "0".to_string(), "4".to_string()],
+            })
+        );
+        let log = take();
+        assert_eq!(log.first().map(|call| call.name), Some("BindVertexArray"));
+        assert_eq!(
+            log.last().map(|call| call.to_string()).unwrap(),
+            "glBindVertexArray(0)"
+        );
+    }
+
+    #[test]
+    fn texture_map_draws_once_per_texture() {
+        load();
+        let mut map = TextureMap::new(256, 256);
+        let textures = [(1, 40, 30), (2, 64, 64), (3, 10, 90)]
+            .into_iter()
+            .map(|(name, w, h)| {
+                let raw = vec![255u8; (w * h * 4) as usize];
+                let texture = Texture2D::load(
+                    Some(raw.as_slice()),
+                    TextureType::RGBA8,
+                    w,
+                    h,
+                    TextureParm::new(),
+                );
+                (name, TextureWrapper(texture))
+            })
+            .collect();
+        take();
+        map.add(textures, true).unwrap();
+        assert_eq!(draw_calls(), 3);
+        //程序只绑一次,scoped结束时还原成0
+        let programs: Vec<String> = calls()
+            .into_iter()
+            .filter(|call| call.name == "UseProgram")
+            .map(|call| call.args[0].clone())
+            .collect();
+        assert_eq!(programs.len(), 2);
+        assert_ne!(programs[0], "0");
+        assert_eq!(programs[1], "0");
+        assert!(map.get_uv(&2).is_some());
+    }
+}
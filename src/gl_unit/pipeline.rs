@@ -0,0 +1,289 @@
+use super::{
+    ConstBlend,
+    define::{Blend, BlendEquation, CompareFunc, Face, StencilOp},
+    state,
+};
+use crate::setter_gen;
+
+//rgb和alpha可以分开设置的混合函数和方程
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BlendState {
+    pub src_rgb: Blend,
+    pub dst_rgb: Blend,
+    pub src_alpha: Blend,
+    pub dst_alpha: Blend,
+    pub equation_rgb: BlendEquation,
+    pub equation_alpha: BlendEquation,
+}
+setter_gen! {
+    impl BlendState{
+        equation_rgb: BlendEquation,
+        equation_alpha: BlendEquation
+    }
+}
+impl BlendState {
+    pub const fn new(src: Blend, dst: Blend) -> Self {
+        Self::separate(src, dst, src, dst)
+    }
+    pub const fn separate(
+        src_rgb: Blend,
+        dst_rgb: Blend,
+        src_alpha: Blend,
+        dst_alpha: Blend,
+    ) -> Self {
+        Self {
+            src_rgb,
+            dst_rgb,
+            src_alpha,
+            dst_alpha,
+            equation_rgb: BlendEquation::Add,
+            equation_alpha: BlendEquation::Add,
+        }
+    }
+    pub const fn equation(mut self, equation: BlendEquation) -> Self {
+        self.equation_rgb = equation;
+        self.equation_alpha = equation;
+        self
+    }
+    //非预乘的颜色混进去,alpha按预乘累加,画到fbo里再合成也是对的
+    pub const fn alpha() -> Self {
+        Self::separate(
+            Blend::SrcAlpha,
+            Blend::OneMinusSrcAlpha,
+            Blend::One,
+            Blend::OneMinusSrcAlpha,
+        )
+    }
+    pub const fn premultiplied() -> Self {
+        Self::new(Blend::One, Blend::OneMinusSrcAlpha)
+    }
+    pub const fn additive() -> Self {
+        Self::new(Blend::SrcAlpha, Blend::One)
+    }
+    // 1 - (1 - src) * (1 - dst)
+    pub const fn screen() -> Self {
+        Self::new(Blend::One, Blend::OneMinusSrcColor)
+    }
+    pub const fn multiply() -> Self {
+        Self::new(Blend::DstColor, Blend::OneMinusSrcAlpha)
+    }
+    pub const fn lighten() -> Self {
+        Self::new(Blend::One, Blend::One).equation(BlendEquation::Max)
+    }
+    pub const fn darken() -> Self {
+        Self::new(Blend::One, Blend::One).equation(BlendEquation::Min)
+    }
+    //dst - src
+    pub const fn subtract() -> Self {
+        Self::new(Blend::One, Blend::One).equation(BlendEquation::ReverseSubtract)
+    }
+}
+
+impl From<ConstBlend> for BlendState {
+    fn from(value: ConstBlend) -> Self {
+        let (src, dst) = value.blend();
+        Self::new(src, dst)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DepthState {
+    pub func: CompareFunc,
+    //false时只测试不写深度
+    pub write: bool,
+}
+impl DepthState {
+    pub const fn new(func: CompareFunc, write: bool) -> Self {
+        Self { func, write }
+    }
+    pub const fn less() -> Self {
+        Self::new(CompareFunc::Less, true)
+    }
+    pub const fn read_only() -> Self {
+        Self::new(CompareFunc::LessEqual, false)
+    }
+}
+
+//模板测试的一面
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StencilFace {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    //模板测试失败
+    pub fail: StencilOp,
+    //模板过了深度没过
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+setter_gen! {
+    impl StencilFace{
+        func: CompareFunc,
+        reference: i32,
+        read_mask: u32,
+        write_mask: u32,
+        fail: StencilOp,
+        depth_fail: StencilOp,
+        pass: StencilOp
+    }
+}
+impl StencilFace {
+    pub const fn new() -> Self {
+        Self {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+    //画的地方写入reference,做遮罩
+    pub const fn write(reference: i32) -> Self {
+        let mut face = Self::new();
+        face.reference = reference;
+        face.pass = StencilOp::Replace;
+        face
+    }
+    //只画模板值等于reference的地方
+    pub const fn test_equal(reference: i32) -> Self {
+        let mut face = Self::new();
+        face.func = CompareFunc::Equal;
+        face.reference = reference;
+        face.write_mask = 0;
+        face
+    }
+    //只画模板值不等于reference的地方,描边用
+    pub const fn test_not_equal(reference: i32) -> Self {
+        let mut face = Self::test_equal(reference);
+        face.func = CompareFunc::NotEqual;
+        face
+    }
+}
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StencilState {
+    pub front: StencilFace,
+    pub back: StencilFace,
+}
+impl StencilState {
+    pub const fn both(face: StencilFace) -> Self {
+        Self {
+            front: face,
+            back: face,
+        }
+    }
+}
+
+//一次draw用到的固定管线状态,apply时整体设置,没写的项都回到默认值
+setter_gen! {
+    PipelineState{
+        //None关掉混合
+        blend: Option<BlendState>,
+        blend_color: [f32; 4],
+        //None关掉深度测试
+        depth: Option<DepthState>,
+        stencil: Option<StencilState>,
+        cull: Option<Face>,
+        //x,y,w,h
+        scissor: Option<[i32; 4]>,
+        color_mask: [bool; 4]
+    }
+}
+
+impl PipelineState {
+    //和gl初始状态一样
+    pub const fn new() -> Self {
+        Self {
+            blend: None,
+            blend_color: [0f32; 4],
+            depth: None,
+            stencil: None,
+            cull: None,
+            scissor: None,
+            color_mask: [true; 4],
+        }
+    }
+    pub fn const_blend(self, blend: ConstBlend) -> Self {
+        self.blend(Some(blend.into()))
+    }
+    //经过状态缓存设置,和当前一样的项不会调用gl
+    pub fn apply(&self) {
+        state::blend(self.blend);
+        state::blend_color(self.blend_color);
+        match self.depth {
+            Some(depth) => {
+                state::depth_test(true);
+                state::depth_func(depth.func);
+                state::depth_write(depth.write);
+            }
+            None => {
+                state::depth_test(false);
+                //关掉深度写入会让glClear也清不掉深度
+                state::depth_write(true);
+            }
+        }
+        state::stencil(self.stencil);
+        state::cull(self.cull);
+        state::scissor(self.scissor);
+        state::color_mask(self.color_mask);
+    }
+    //func返回后还原成apply之前的状态
+    pub fn scoped<T>(&self, func: impl FnOnce() -> T) -> T {
+        state::scoped(|| {
+            self.apply();
+            func()
+        })
+    }
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use crate::gl_unit::{
+        ConstBlend,
+        define::{Blend, BlendEquation, CompareFunc, Face},
+        golden::with_headless,
+        state::current,
+    };
+
+    use super::{BlendState, DepthState, PipelineState, StencilFace, StencilState};
+
+    #[test]
+    fn apply_and_restore() {
+        with_headless(64, 64, |context| {
+            PipelineState::new().apply();
+            let before = context.state();
+            let outline = PipelineState::new()
+                .blend(Some(BlendState::alpha().equation_alpha(BlendEquation::Max)))
+                .depth(Some(DepthState::read_only()))
+                .stencil(Some(StencilState::both(StencilFace::test_not_equal(1))))
+                .cull(Some(Face::Back))
+                .scissor(Some([0, 0, 8, 8]));
+            outline.scoped(|| {
+                let state = current();
+                assert_eq!(state.blend, Some(outline.blend));
+                assert_eq!(state.depth_func, Some(CompareFunc::LessEqual));
+                assert_eq!(state.depth_write, Some(false));
+                assert_eq!(state.scissor, Some(Some([0, 0, 8, 8])));
+            });
+            assert_eq!(context.state(), before);
+            assert_eq!(
+                BlendState::from(ConstBlend::SrcOnly),
+                BlendState::new(Blend::One, Blend::Zero)
+            );
+        });
+    }
+}
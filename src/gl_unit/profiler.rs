@@ -0,0 +1,320 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use json::{JsonValue, object};
+
+use super::{
+    caps::caps,
+    define::QueryTarget,
+    query::{Query, gpu_timestamp},
+};
+
+//gpu结果最多攒这么多帧,再多就等最老的一帧
+const MAX_PENDING: usize = 4;
+
+//一个profile_scope!的记录,时间都相对profiler创建的时候
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScopeRecord {
+    pub name: &'static str,
+    //嵌套层数,最外层是0
+    pub depth: u32,
+    pub cpu_start: Duration,
+    pub cpu_time: Duration,
+    //不支持timer query时是None
+    pub gpu_start: Option<Duration>,
+    pub gpu_time: Option<Duration>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct FrameRecord {
+    pub index: u64,
+    pub cpu_start: Duration,
+    pub cpu_time: Duration,
+    pub scopes: Vec<ScopeRecord>,
+}
+impl FrameRecord {
+    //最外层scope的gpu时间加起来
+    pub fn gpu_time(&self) -> Option<Duration> {
+        self.scopes
+            .iter()
+            .filter(|scope| scope.depth == 0)
+            .map(|scope| scope.gpu_time)
+            .sum()
+    }
+}
+
+struct PendingFrame {
+    record: FrameRecord,
+    //和scopes一一对应的(开始,结束)时间戳
+    queries: Vec<Option<(Query, Query)>>,
+    //gpu时钟 - cpu时钟,纳秒
+    gpu_offset: i128,
+}
+
+pub struct Profiler {
+    epoch: Instant,
+    frame: u64,
+    gpu: bool,
+    current: Option<PendingFrame>,
+    stack: Vec<usize>,
+    pending: VecDeque<PendingFrame>,
+    //读完结果的query留着下次用
+    free: Vec<Query>,
+    history: VecDeque<FrameRecord>,
+    history_len: usize,
+}
+
+impl Profiler {
+    //history_len:保留最近多少帧
+    pub fn new(history_len: usize) -> Self {
+        let mut profiler = Self::cpu_only(history_len);
+        profiler.gpu = caps().has_timer_query();
+        profiler
+    }
+    //不碰gl,没有context也能用
+    pub fn cpu_only(history_len: usize) -> Self {
+        Self {
+            epoch: Instant::now(),
+            frame: 0,
+            gpu: false,
+            current: None,
+            stack: Vec::new(),
+            pending: VecDeque::new(),
+            free: Vec::new(),
+            history: VecDeque::new(),
+            history_len: history_len.max(1),
+        }
+    }
+    pub fn begin_frame(&mut self) {
+        if self.current.is_some() {
+            self.end_frame();
+        }
+        let cpu_start = self.epoch.elapsed();
+        let gpu_offset = if self.gpu {
+            gpu_timestamp() as i128 - cpu_start.as_nanos() as i128
+        } else {
+            0
+        };
+        self.current = Some(PendingFrame {
+            record: FrameRecord {
+                index: self.frame,
+                cpu_start,
+                ..Default::default()
+            },
+            queries: Vec::new(),
+            gpu_offset,
+        });
+        self.frame += 1;
+    }
+    pub fn end_frame(&mut self) {
+        while !self.stack.is_empty() {
+            self.end_scope();
+        }
+        let Some(mut frame) = self.current.take() else {
+            return;
+        };
+        frame.record.cpu_time = self.epoch.elapsed() - frame.record.cpu_start;
+        self.pending.push_back(frame);
+        self.collect(false);
+        while self.pending.len() > MAX_PENDING {
+            self.collect(true);
+        }
+    }
+    //不在begin_frame/end_frame之间时什么都不做
+    pub fn begin_scope(&mut self, name: &'static str) {
+        if self.current.is_none() {
+            return;
+        }
+        let query = self.gpu.then(|| {
+            let mut start = self.query();
+            start.record();
+            (start, self.query())
+        });
+        let depth = self.stack.len() as u32;
+        let cpu_start = self.epoch.elapsed();
+        let frame = self.current.as_mut().unwrap();
+        frame.record.scopes.push(ScopeRecord {
+            name,
+            depth,
+            cpu_start,
+            cpu_time: Duration::ZERO,
+            gpu_start: None,
+            gpu_time: None,
+        });
+        frame.queries.push(query);
+        self.stack.push(frame.record.scopes.len() - 1);
+    }
+    pub fn end_scope(&mut self) {
+        let (Some(index), Some(frame)) = (self.stack.pop(), self.current.as_mut()) else {
+            return;
+        };
+        let scope = &mut frame.record.scopes[index];
+        scope.cpu_time = self.epoch.elapsed() - scope.cpu_start;
+        if let Some((_, end)) = frame.queries[index].as_mut() {
+            end.record();
+        }
+    }
+    //gpu结果已经回来的帧,旧的在前
+    pub fn frames(&self) -> impl Iterator<Item = &FrameRecord> {
+        self.history.iter()
+    }
+    pub fn last_frame(&self) -> Option<&FrameRecord> {
+        self.history.back()
+    }
+    //chrome://tracing 或 perfetto 能打开的json,cpu和gpu各一条线
+    pub fn chrome_trace(&self) -> String {
+        chrome_trace(self.frames())
+    }
+
+    fn query(&mut self) -> Query {
+        self.free
+            .pop()
+            .unwrap_or_else(|| Query::new(QueryTarget::Timestamp))
+    }
+    //block为false时只收gpu已经跑完的帧
+    fn collect(&mut self, block: bool) {
+        while let Some(frame) = self.pending.front() {
+            let ready = frame.queries.iter().flatten().all(|(_, end)| end.ready());
+            if !ready && !block {
+                break;
+            }
+            let mut frame = self.pending.pop_front().unwrap();
+            for (scope, query) in frame.record.scopes.iter_mut().zip(frame.queries.drain(..)) {
+                let Some((start, end)) = query else {
+                    continue;
+                };
+                let (begin, finish) = (start.result() as i128, end.result() as i128);
+                let begin_ns = (begin - frame.gpu_offset).max(0) as u64;
+                scope.gpu_start = Some(Duration::from_nanos(begin_ns));
+                scope.gpu_time = Some(Duration::from_nanos((finish - begin).max(0) as u64));
+                self.free.extend([start, end]);
+            }
+            self.history.push_back(frame.record);
+            while self.history.len() > self.history_len {
+                self.history.pop_front();
+            }
+            if block {
+                break;
+            }
+        }
+    }
+}
+
+fn trace_event(name: &str, tid: u32, start: Duration, time: Duration) -> JsonValue {
+    object! {
+        name: name,
+        ph: "X",
+        pid: 0,
+        tid: tid,
+        ts: start.as_secs_f64() * 1e6,
+        dur: time.as_secs_f64() * 1e6,
+    }
+}
+pub fn chrome_trace<'a>(frames: impl Iterator<Item = &'a FrameRecord>) -> String {
+    let mut events = JsonValue::new_array();
+    for (tid, name) in [(0, "cpu"), (1, "gpu")] {
+        let _ = events.push(object! {
+            name: "thread_name",
+            ph: "M",
+            pid: 0,
+            tid: tid,
+            args: object! { name: name },
+        });
+    }
+    for frame in frames {
+        let name = format!("frame {}", frame.index);
+        let _ = events.push(trace_event(&name, 0, frame.cpu_start, frame.cpu_time));
+        for scope in frame.scopes.iter() {
+            let _ = events.push(trace_event(scope.name, 0, scope.cpu_start, scope.cpu_time));
+            if let (Some(start), Some(time)) = (scope.gpu_start, scope.gpu_time) {
+                let _ = events.push(trace_event(scope.name, 1, start, time));
+            }
+        }
+    }
+    object! { traceEvents: events }.dump()
+}
+
+thread_local! {
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+//当前线程的profiler,profile_scope!都记到这里
+pub fn enable(profiler: Profiler) {
+    PROFILER.with_borrow_mut(|current| *current = Some(profiler));
+}
+pub fn disable() -> Option<Profiler> {
+    PROFILER.with_borrow_mut(Option::take)
+}
+//没enable时返回None
+pub fn with<T>(func: impl FnOnce(&mut Profiler) -> T) -> Option<T> {
+    PROFILER.with_borrow_mut(|profiler| profiler.as_mut().map(func))
+}
+pub fn begin_frame() {
+    with(Profiler::begin_frame);
+}
+pub fn end_frame() {
+    with(Profiler::end_frame);
+}
+
+//profile_scope!生成的,drop时结束这个scope
+pub struct ScopeGuard {
+    active: bool,
+}
+impl ScopeGuard {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            active: with(|profiler| profiler.begin_scope(name)).is_some(),
+        }
+    }
+}
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        if self.active {
+            with(Profiler::end_scope);
+        }
+    }
+}
+
+//记录到当前块结束为止的cpu/gpu时间
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::gl_unit::profiler::ScopeGuard::new($name);
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Profiler, disable, enable};
+
+    #[test]
+    fn chrome_trace_export() {
+        enable(Profiler::cpu_only(8));
+        for _ in 0..2 {
+            super::begin_frame();
+            {
+                crate::profile_scope!("shadow pass");
+                crate::profile_scope!("cull");
+            }
+            crate::profile_scope!("post");
+            super::end_frame();
+        }
+        let profiler = disable().unwrap();
+        let frame = profiler.last_frame().unwrap();
+        assert_eq!(frame.index, 1);
+        let names: Vec<_> = frame.scopes.iter().map(|s| (s.name, s.depth)).collect();
+        assert_eq!(names, vec![("shadow pass", 0), ("cull", 1), ("post", 0)]);
+        assert_eq!(frame.gpu_time(), None);
+
+        let trace = json::parse(&profiler.chrome_trace()).unwrap();
+        let events = &trace["traceEvents"];
+        //2条线程名 + 2帧*(1帧+3scope)
+        assert_eq!(events.len(), 2 + 2 * 4);
+        assert_eq!(events[3]["name"], "shadow pass");
+        assert_eq!(events[3]["ph"], "X");
+        assert!(events[3]["dur"].as_f64().unwrap() >= 0f64);
+    }
+}
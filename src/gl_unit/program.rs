@@ -1,14 +1,31 @@
 use std::{
-    ffi::{c_char, CStr, CString},
+    collections::HashMap,
+    ffi::{c_char, CString},
     fs,
-    path::Path,
-    sync::LazyLock,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock},
 };
 
 use gl::types::{GLenum, GLint, GLsizei, GLuint};
-use glam::{Mat3, Mat4};
+use glam::{IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
 use json::JsonValue;
 
+use super::{
+    caps::{caps, rewrite_version},
+    debug,
+    error::{GlError, GlResult},
+    state,
+};
+
+pub mod builder;
+pub mod diagnostic;
+pub mod reload;
+pub mod uniform;
+pub use builder::ProgramBuilder;
+pub use reload::ReloadProgram;
+pub use uniform::{AttributeInfo, Sampler, UniformInfo, UniformValue};
+use diagnostic::ShaderLog;
+
 const PROGRAM2D_VERT_TWO: &str = include_str!("../../shaders/program2d_two/vert.glsl");
 const PROGRAM2D_FRAG_TWO: &str = include_str!("../../shaders/program2d_two/frag.glsl");
 
@@ -36,19 +53,80 @@ impl Shader {
         Self::load(&fs::read_to_string(path).unwrap(), type_)
     }
     pub fn load(code: &str, type_: GLenum) -> Self {
+        Self::try_load(code, type_).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load(code: &str, type_: GLenum) -> GlResult<Self> {
+        Self::compile(code, type_, 0).map_err(|log| GlError::ShaderCompile(vec![log]))
+    }
+    //line_offset:code前面额外插入的行数,用来把驱动报的行号映射回原文件
+    fn compile(code: &str, type_: GLenum, line_offset: usize) -> Result<Self, ShaderLog> {
         let id;
-        let code = std::ffi::CString::new(code).unwrap();
+        //es上自动换#version头,报错行号再减回去
+        let (source, added) = rewrite_version(code, caps());
+        let line_offset = line_offset + added;
+        let c_code = match std::ffi::CString::new(source) {
+            Ok(c_code) => c_code,
+            Err(err) => return Err(ShaderLog::new(type_, err.to_string(), code, line_offset)),
+        };
         unsafe {
             id = gl::CreateShader(type_);
             gl::ShaderSource(
                 id,
                 1,
-                &code.as_ptr() as *const *const c_char,
+                &c_code.as_ptr() as *const *const c_char,
                 std::ptr::null(),
             );
             gl::CompileShader(id);
         }
-        Shader { shader_id: id }
+        let shader = Shader { shader_id: id };
+        let mut status = 0;
+        unsafe {
+            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut status);
+        }
+        if status == gl::FALSE as GLint {
+            return Err(ShaderLog::new(type_, shader_log(id), code, line_offset));
+        }
+        Ok(shader)
+    }
+}
+
+fn shader_log(id: GLuint) -> String {
+    unsafe {
+        let mut len: GLint = 0;
+        gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
+        let mut log: Vec<u8> = vec![0; len.max(1) as usize];
+        gl::GetShaderInfoLog(id, len, &mut len, log.as_mut_ptr() as *mut c_char);
+        log.truncate(len.max(0) as usize);
+        String::from_utf8_lossy(&log).trim_end().to_string()
+    }
+}
+
+fn read_source(path: &Path) -> GlResult<String> {
+    fs::read_to_string(path).map_err(|err| GlError::Asset(format!("{:?}:{}", path, err)))
+}
+
+//program json: {"vert":"name","frag":"name","uniforms":[...]},shader文件和json同目录
+fn descriptor(path: &Path) -> GlResult<(JsonValue, PathBuf, PathBuf)> {
+    let json = json::parse(&read_source(path)?).map_err(|err| GlError::Asset(err.to_string()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let (vert, frag) = match (json["vert"].as_str(), json["frag"].as_str()) {
+        (Some(vert), Some(frag)) => (
+            dir.join(vert.to_string() + ".vert"),
+            dir.join(frag.to_string() + ".frag"),
+        ),
+        _ => return Err(GlError::Asset(format!("{:?} no vert/frag", path))),
+    };
+    Ok((json, vert, frag))
+}
+
+fn program_log(id: GLuint) -> String {
+    unsafe {
+        let mut len: GLint = 0;
+        gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+        let mut log: Vec<u8> = vec![0; len.max(1) as usize];
+        gl::GetProgramInfoLog(id, len, &mut len, log.as_mut_ptr() as *mut c_char);
+        log.truncate(len.max(0) as usize);
+        String::from_utf8_lossy(&log).trim_end().to_string()
     }
 }
 
@@ -61,65 +139,111 @@ impl Drop for Shader {
 pub struct Program {
     program_id: GLuint,
     uniform: JsonValue,
+    //链接后反射出来的active uniform/attribute
+    uniforms: Arc<HashMap<String, UniformInfo>>,
+    attributes: Arc<HashMap<String, AttributeInfo>>,
 }
 
 impl Program {
     pub fn get_uniform(&self, name: &str) -> GLint {
+        if let Some(info) = self.uniforms.get(name) {
+            return info.location;
+        }
+        //"name[3]"这类非首元素不在表里,交给驱动查
+        match CString::new(name) {
+            Ok(c_name) => unsafe { gl::GetUniformLocation(self.program_id, c_name.as_ptr()) },
+            Err(_) => -1,
+        }
+    }
+    pub fn uniform_info(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+    pub fn uniforms(&self) -> &HashMap<String, UniformInfo> {
+        &self.uniforms
+    }
+    pub fn attribute_info(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.get(name)
+    }
+    pub fn attributes(&self) -> &HashMap<String, AttributeInfo> {
+        &self.attributes
+    }
+    //把shader里的 uniform block 接到 bind_base 用的绑定点上
+    pub fn uniform_block_binding(&self, name: &str, binding: u32) -> GlResult<()> {
+        let c_name = CString::new(name).map_err(|_| GlError::Uniform(name.to_string()))?;
         unsafe {
-            let id = gl::GetUniformLocation(self.program_id, CString::new(name).unwrap().as_ptr());
-            if id == -1 {
-                println!("can't find {} in program", name)
+            let index = gl::GetUniformBlockIndex(self.program_id, c_name.as_ptr());
+            if index == gl::INVALID_INDEX {
+                return Err(GlError::Uniform(name.to_string()));
             }
-            id
+            gl::UniformBlockBinding(self.program_id, index, binding);
         }
+        Ok(())
+    }
+    pub fn storage_block_binding(&self, name: &str, binding: u32) -> GlResult<()> {
+        let c_name = CString::new(name).map_err(|_| GlError::Uniform(name.to_string()))?;
+        unsafe {
+            let index = gl::GetProgramResourceIndex(
+                self.program_id,
+                gl::SHADER_STORAGE_BLOCK,
+                c_name.as_ptr(),
+            );
+            if index == gl::INVALID_INDEX {
+                return Err(GlError::Uniform(name.to_string()));
+            }
+            gl::ShaderStorageBlockBinding(self.program_id, index, binding);
+        }
+        Ok(())
+    }
+    //需要先bind
+    pub fn set_uniform<T: UniformValue>(&self, name: &str, value: T) -> GlResult<()> {
+        let info = self
+            .uniforms
+            .get(name)
+            .ok_or_else(|| GlError::Uniform(name.to_string()))?;
+        if !T::GL_TYPES.contains(&info.type_) {
+            return Err(GlError::UniformType {
+                name: name.to_string(),
+                expected: info.type_,
+                found: T::NAME,
+            });
+        }
+        if value.count() > info.size as usize {
+            return Err(GlError::UniformSize {
+                name: name.to_string(),
+                size: info.size as usize,
+                count: value.count(),
+            });
+        }
+        value.put(info.location);
+        Ok(())
     }
     pub fn load(path: &Path) -> Self {
-        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
-        let path_str = path.to_str().unwrap();
-        let path = &path_str[..path_str.rfind("\\").unwrap() + 1];
-        Self::basic_new(
-            fs::read_to_string(Path::new(
-                &(path.to_string() + (json["vert"].as_str().unwrap()) + ".vert"),
-            ))
-            .ok()
-            .as_ref()
-            .unwrap(),
-            fs::read_to_string(Path::new(
-                &(path.to_string() + json["frag"].as_str().unwrap() + ".frag"),
-            ))
-            .ok()
-            .as_ref()
-            .unwrap(),
-            Some(json["uniforms"].clone()),
-        )
+        Self::try_load(path).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load(path: &Path) -> GlResult<Self> {
+        let (json, vert, frag) = descriptor(path)?;
+        Self::try_new(&read_source(&vert)?, &read_source(&frag)?, Some(json["uniforms"].clone()))
     }
     pub fn basic_new(vert: &str, frag: &str, uniform: Option<JsonValue>) -> Self {
+        Self::try_new(vert, frag, uniform).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_new(vert: &str, frag: &str, uniform: Option<JsonValue>) -> GlResult<Self> {
+        let builder = ProgramBuilder::new().vertex(vert).fragment(frag);
+        match uniform {
+            Some(uniform) => builder.uniform(uniform).build(),
+            None => builder.build(),
+        }
+    }
+    pub fn compute(code: &str) -> Self {
+        ProgramBuilder::new()
+            .compute(code)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn dispatch_compute(&self, x: u32, y: u32, z: u32) {
+        self.bind();
         unsafe {
-            let program_id = gl::CreateProgram();
-
-            //vert
-            gl::AttachShader(program_id, Shader::load(vert, gl::VERTEX_SHADER).shader_id);
-
-            //frag
-            gl::AttachShader(
-                program_id,
-                Shader::load(frag, gl::FRAGMENT_SHADER).shader_id,
-            );
-
-            let mut len: GLsizei = 1;
-            let mut code: Vec<c_char> = Vec::with_capacity(1000);
-            gl::LinkProgram(program_id);
-            gl::GetProgramInfoLog(program_id, 1000, &mut len, code.as_mut_ptr());
-            let cstr = CStr::from_ptr(code.as_ptr());
-            let str_slice = cstr.to_str().expect("Failed to convert CStr to &str");
-            if !str_slice.is_empty() {
-                panic!("Program err: {}", str_slice);
-            }
-
-            Program {
-                program_id,
-                uniform: uniform.unwrap_or(JsonValue::Null),
-            }
+            gl::DispatchCompute(x, y, z);
         }
     }
     pub fn put_bool(&self, id: GLint, bool: bool) {
@@ -164,13 +288,29 @@ impl Program {
         }
     }
     pub fn put_f32(&self, name: &str, date: &Vec<f32>) {
+        if let Err(err) = self.try_put_f32(name, date) {
+            eprintln!("[put f32]{}", err);
+        }
+    }
+    //有反射信息就按shader里声明的类型上传,个数/类型不对时返回错误
+    pub fn try_put_f32(&self, name: &str, date: &Vec<f32>) -> GlResult<()> {
         let id = self.get_uniform(name);
-        match date.len() {
-            1 => self.put_one(date[0], id),
-
-            4 => self.put_vec4([date[0], date[1], date[2], date[3]], id),
+        match self.uniforms.get(name).map(|info| info.type_) {
+            Some(gl::FLOAT_VEC2) => self.set_uniform(name, chunks(date, Vec2::from_slice)),
+            Some(gl::FLOAT_VEC3) => self.set_uniform(name, chunks(date, Vec3::from_slice)),
+            Some(gl::FLOAT_MAT2) => self.set_uniform(name, chunks(date, Mat2::from_cols_slice)),
+            Some(gl::FLOAT_MAT3) => self.set_uniform(name, chunks(date, Mat3::from_cols_slice)),
+            Some(gl::FLOAT_MAT4) => self.set_uniform(name, chunks(date, Mat4::from_cols_slice)),
             _ => {
-                self.put_vec(date, id);
+                match date.len() {
+                    1 => self.put_one(date[0], id),
+
+                    4 => self.put_vec4([date[0], date[1], date[2], date[3]], id),
+                    _ => {
+                        self.put_vec(date, id);
+                    }
+                }
+                Ok(())
             }
         }
     }
@@ -179,67 +319,71 @@ impl Program {
             gl::Uniform1i(id, tex_unit);
         }
     }
+    pub fn set_label(&self, label: &str) {
+        debug::set_label(gl::PROGRAM, self.program_id, label);
+    }
     pub fn bind(&self) {
-        unsafe {
-            gl::UseProgram(self.program_id);
-            // gl::Enable(gl::BLEND);
-            // gl::BlendEquation(Self::get_enum(self.blend_func["func"].as_str().unwrap()));
-            // let src = Self::get_enum(self.blend_func["srcrgb"].as_str().unwrap());
-            // let dst = Self::get_enum(self.blend_func["dstrgb"].as_str().unwrap());
-            // gl::BlendFunc(src, dst);
-        }
+        state::use_program(self.program_id);
+        // gl::Enable(gl::BLEND);
+        // gl::BlendEquation(Self::get_enum(self.blend_func["func"].as_str().unwrap()));
+        // let src = Self::get_enum(self.blend_func["srcrgb"].as_str().unwrap());
+        // let dst = Self::get_enum(self.blend_func["dstrgb"].as_str().unwrap());
+        // gl::BlendFunc(src, dst);
     }
     pub fn load_uniform(&self) {
+        if let Err(err) = self.try_load_uniform() {
+            eprintln!("[load uniform]{}", err);
+        }
+    }
+    //json里的初值全部设一遍,出错的跳过,返回第一个错误
+    pub fn try_load_uniform(&self) -> GlResult<()> {
         self.bind();
+        let mut first_err = None;
         for i in 0..self.uniform.len() {
-            let mut temp = self.uniform[i].clone();
-            let name = temp["name"].as_str().unwrap().to_string();
-            let uniform_type = temp["type"].as_str().unwrap().to_string();
-            let value_count = temp["count"].as_usize().unwrap();
-            let mut value = Vec::with_capacity(value_count);
-            let vec = temp["values"].take();
-            for j in 0..value_count {
-                if let Some(num) = vec[j].as_f32() {
-                    value.push(num);
-                }
-            }
-            unsafe {
-                let name_id = self.get_uniform(&name);
-                match uniform_type.as_str() {
-                    "vec4" => {
-                        let vec = [
-                            vec[0].as_f32().unwrap(),
-                            vec[1].as_f32().unwrap(),
-                            vec[2].as_f32().unwrap(),
-                            vec[3].as_f32().unwrap(),
-                        ];
-                        self.put_vec4(vec, name_id)
-                    }
-
-                    "float" => self.put_one(value[0], name_id),
-                    "matrix4x4" => {
-                        self.put_matrix(
-                            Mat4::from_cols_array(&(value.as_slice().try_into().unwrap())),
-                            name_id,
-                        );
-                        // gl::UniformMatrix4fv(name_id, 1, gl::FALSE, value.as_ptr());
-                    }
-                    "matrix3x3" => {
-                        gl::UniformMatrix3fv(name_id, 1, gl::FALSE, value.as_ptr());
-                    }
-                    "bool" => {
-                        let some;
-                        if vec[0].as_str().unwrap().eq("true") {
-                            some = 1;
-                        } else {
-                            some = 0;
-                        }
-                        gl::Uniform1i(name_id, some);
-                    }
-                    &_ => {}
-                }
+            if let Err(err) = self.load_one_uniform(&self.uniform[i]) {
+                first_err.get_or_insert(err);
             }
         }
+        first_err.map_or(Ok(()), Err)
+    }
+    fn load_one_uniform(&self, uniform: &JsonValue) -> GlResult<()> {
+        let field =
+            |key: &str| GlError::Uniform(format!("{}: missing or invalid {}", uniform, key));
+        let name = uniform["name"].as_str().ok_or_else(|| field("name"))?;
+        let uniform_type = uniform["type"].as_str().ok_or_else(|| field("type"))?;
+        let value_count = uniform["count"].as_usize().ok_or_else(|| field("count"))?;
+        let vec = &uniform["values"];
+        let floats: Vec<f32> = (0..value_count).filter_map(|j| vec[j].as_f32()).collect();
+        let ints: Vec<i32> = (0..value_count).filter_map(|j| vec[j].as_i32()).collect();
+        let uints: Vec<u32> = (0..value_count).filter_map(|j| vec[j].as_u32()).collect();
+        match uniform_type {
+            "float" => self.set_uniform(name, floats),
+            "vec2" => self.set_uniform(name, chunks(&floats, Vec2::from_slice)),
+            "vec3" => self.set_uniform(name, chunks(&floats, Vec3::from_slice)),
+            "vec4" => self.set_uniform(name, chunks(&floats, Vec4::from_slice)),
+            "int" => self.set_uniform(name, ints),
+            "ivec2" => self.set_uniform(name, chunks(&ints, IVec2::from_slice)),
+            "ivec3" => self.set_uniform(name, chunks(&ints, IVec3::from_slice)),
+            "ivec4" => self.set_uniform(name, chunks(&ints, IVec4::from_slice)),
+            "uint" => self.set_uniform(name, uints),
+            "uvec2" => self.set_uniform(name, chunks(&uints, UVec2::from_slice)),
+            "uvec3" => self.set_uniform(name, chunks(&uints, UVec3::from_slice)),
+            "uvec4" => self.set_uniform(name, chunks(&uints, UVec4::from_slice)),
+            "matrix2x2" => self.set_uniform(name, chunks(&floats, Mat2::from_cols_slice)),
+            "matrix3x3" => self.set_uniform(name, chunks(&floats, Mat3::from_cols_slice)),
+            "matrix4x4" => self.set_uniform(name, chunks(&floats, Mat4::from_cols_slice)),
+            "sampler" => self.set_uniform(name, uints.into_iter().map(Sampler).collect::<Vec<_>>()),
+            "bool" => self.set_uniform(
+                name,
+                (0..value_count)
+                    .map(|j| vec[j].as_str() == Some("true") || vec[j].as_bool() == Some(true))
+                    .collect::<Vec<_>>(),
+            ),
+            &_ => Err(GlError::Uniform(format!(
+                "{}: unknown type {}",
+                name, uniform_type
+            ))),
+        }
     }
     // fn get_enum(str: &str) -> GLenum {
     //     match str {
@@ -252,3 +396,69 @@ impl Program {
     //     }
     // }
 }
+
+//按分量数切开,多余的尾巴丢掉
+fn chunks<T: Copy, V>(values: &[T], from: fn(&[T]) -> V) -> Vec<V> {
+    let size = std::mem::size_of::<V>() / std::mem::size_of::<T>();
+    values.chunks_exact(size).map(from).collect()
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use crate::gl_unit::{error::GlError, golden::with_shared_context};
+
+    use super::Program;
+
+    const VERT: &str =
+        "#version 330\nlayout (location = 0) in vec4 vert;\nvoid main(){ gl_Position = vert; }\n";
+    const FRAG: &str = "#version 330\nuniform vec2 offset;\nuniform vec4 tint;\nout vec4 color;\nvoid main(){ color = tint + offset.xyxy; }\n";
+
+    #[test]
+    fn uniform_errors() {
+        with_shared_context(|_| {
+            //tint类型写错,后面的offset照样设上
+            let uniform = json::parse(
+                r#"[{"name":"tint","type":"float","count":1,"values":[1]},
+                    {"name":"offset","type":"vec2","count":2,"values":[1,2]}]"#,
+            )
+            .unwrap();
+            let program = Program::try_new(VERT, FRAG, Some(uniform)).unwrap();
+            assert!(matches!(
+                program.try_load_uniform(),
+                Err(GlError::UniformType { name, .. }) if name == "tint"
+            ));
+            let mut offset = [0f32; 2];
+            unsafe {
+                gl::GetUniformfv(
+                    program.program_id,
+                    program.get_uniform("offset"),
+                    offset.as_mut_ptr(),
+                );
+            }
+            assert_eq!(offset, [1f32, 2f32]);
+
+            assert_eq!(program.try_put_f32("tint", &vec![1f32; 4]), Ok(()));
+            //vec2不是数组,给3个
+            assert!(matches!(
+                program.try_put_f32("offset", &vec![0f32; 6]),
+                Err(GlError::UniformSize { count: 3, .. })
+            ));
+
+            //缺字段、类型不对不会panic
+            let uniform = json::parse(
+                r#"[{"type":"float","count":1,"values":[1]},
+                    {"name":"tint","type":"vec4","count":"4","values":[1,1,1,1]}]"#,
+            )
+            .unwrap();
+            let broken = Program::try_new(VERT, FRAG, Some(uniform)).unwrap();
+            assert!(matches!(
+                broken.try_load_uniform(),
+                Err(GlError::Uniform(_))
+            ));
+            unsafe {
+                gl::DeleteProgram(program.program_id);
+                gl::DeleteProgram(broken.program_id);
+            }
+        });
+    }
+}
@@ -0,0 +1,108 @@
+use gl::types::{GLenum, GLint};
+use json::JsonValue;
+
+use crate::gl_unit::{
+    caps::{caps, rewrite_version},
+    error::{GlError, GlResult},
+};
+
+use std::sync::Arc;
+
+use super::{
+    Program, Shader,
+    diagnostic::link_logs,
+    program_log,
+    uniform::{reflect_attributes, reflect_uniforms},
+};
+
+#[derive(Default)]
+pub struct ProgramBuilder {
+    stages: Vec<(GLenum, String)>,
+    uniform: Option<JsonValue>,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn stage(mut self, type_: GLenum, code: &str) -> Self {
+        self.stages.push((type_, code.to_string()));
+        self
+    }
+    pub fn vertex(self, code: &str) -> Self {
+        self.stage(gl::VERTEX_SHADER, code)
+    }
+    pub fn fragment(self, code: &str) -> Self {
+        self.stage(gl::FRAGMENT_SHADER, code)
+    }
+    pub fn geometry(self, code: &str) -> Self {
+        self.stage(gl::GEOMETRY_SHADER, code)
+    }
+    pub fn tess_control(self, code: &str) -> Self {
+        self.stage(gl::TESS_CONTROL_SHADER, code)
+    }
+    pub fn tess_evaluation(self, code: &str) -> Self {
+        self.stage(gl::TESS_EVALUATION_SHADER, code)
+    }
+    pub fn compute(self, code: &str) -> Self {
+        self.stage(gl::COMPUTE_SHADER, code)
+    }
+    pub fn uniform(mut self, uniform: JsonValue) -> Self {
+        self.uniform = Some(uniform);
+        self
+    }
+
+    //行号按编译时一样减掉es头插入的行数
+    fn link_error(&self, log: &str) -> GlError {
+        let sources: Vec<_> = self
+            .stages
+            .iter()
+            .map(|(type_, code)| (*type_, code.as_str(), rewrite_version(code, caps()).1))
+            .collect();
+        GlError::ProgramLink(link_logs(log, &sources))
+    }
+
+    pub fn build(self) -> GlResult<Program> {
+        let is_compute = self.stages.iter().any(|(type_, _)| *type_ == gl::COMPUTE_SHADER);
+        if is_compute && self.stages.len() > 1 {
+            return Err(self.link_error("compute stage can't link with other stages"));
+        }
+        //每个stage都编译一遍,一次报全
+        let mut shaders = Vec::with_capacity(self.stages.len());
+        let mut logs = Vec::new();
+        for (type_, code) in self.stages.iter() {
+            match Shader::compile(code, *type_, 0) {
+                Ok(shader) => shaders.push(shader),
+                Err(log) => logs.push(log),
+            }
+        }
+        if !logs.is_empty() {
+            return Err(GlError::ShaderCompile(logs));
+        }
+        unsafe {
+            let program_id = gl::CreateProgram();
+            for shader in shaders.iter() {
+                gl::AttachShader(program_id, shader.shader_id);
+            }
+            gl::LinkProgram(program_id);
+
+            let mut status = 0;
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut status);
+            if status == gl::FALSE as GLint {
+                let log = program_log(program_id);
+                gl::DeleteProgram(program_id);
+                return Err(self.link_error(&log));
+            }
+            for shader in shaders.iter() {
+                gl::DetachShader(program_id, shader.shader_id);
+            }
+
+            Ok(Program {
+                program_id,
+                uniform: self.uniform.unwrap_or(JsonValue::Null),
+                uniforms: Arc::new(reflect_uniforms(program_id)),
+                attributes: Arc::new(reflect_attributes(program_id)),
+            })
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt::{Display, Formatter};
+
+use gl::types::GLenum;
+
+use crate::gl_unit::error::stage_name;
+
+//出错行前后各带几行源码
+const SNIPPET_AROUND: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderDiagnostic {
+    //原始源码里的行号(从1开始)
+    pub line: Option<usize>,
+    pub level: String,
+    pub message: String,
+    pub snippet: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderLog {
+    pub stage: GLenum,
+    pub log: String,
+    pub diagnostics: Vec<ShaderDiagnostic>,
+}
+
+impl ShaderLog {
+    //line_offset:编译前在源码前面插入的行数
+    pub fn new(stage: GLenum, log: String, source: &str, line_offset: usize) -> Self {
+        let diagnostics = log
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| ShaderDiagnostic::parse(line, source, line_offset))
+            .collect();
+        Self {
+            stage,
+            log,
+            diagnostics,
+        }
+    }
+}
+
+//链接日志按stage拆开:nvidia分"Vertex info"段,mesa/amd在信息里写"vertex shader";
+//哪个stage都不像的行stage记0,算整个program的
+//stages:(stage,源码,编译前插入的行数)
+pub fn link_logs(log: &str, stages: &[(GLenum, &str, usize)]) -> Vec<ShaderLog> {
+    let mut logs: Vec<ShaderLog> = Vec::new();
+    let mut section = None;
+    for line in log.lines().map(str::trim) {
+        if line.is_empty() || line.chars().all(|c| c == '-') {
+            continue;
+        }
+        if let Some(stage) = line.strip_suffix(" info").and_then(stage_of) {
+            section = Some(stage);
+            continue;
+        }
+        let stage = section.or_else(|| mentioned_stage(line)).unwrap_or(0);
+        let (source, line_offset) = stages
+            .iter()
+            .find(|(type_, _, _)| *type_ == stage)
+            .map_or(("", 0), |(_, source, offset)| (*source, *offset));
+        let diagnostic = ShaderDiagnostic::parse(line, source, line_offset);
+        match logs.iter_mut().find(|log| log.stage == stage) {
+            Some(log) => {
+                log.log.push('\n');
+                log.log.push_str(line);
+                log.diagnostics.push(diagnostic);
+            }
+            None => logs.push(ShaderLog {
+                stage,
+                log: line.to_string(),
+                diagnostics: vec![diagnostic],
+            }),
+        }
+    }
+    logs
+}
+
+const STAGE_NAMES: [(&str, GLenum); 8] = [
+    ("vertex", gl::VERTEX_SHADER),
+    ("fragment", gl::FRAGMENT_SHADER),
+    ("geometry", gl::GEOMETRY_SHADER),
+    ("tessellation control", gl::TESS_CONTROL_SHADER),
+    ("tess control", gl::TESS_CONTROL_SHADER),
+    ("tessellation evaluation", gl::TESS_EVALUATION_SHADER),
+    ("tess evaluation", gl::TESS_EVALUATION_SHADER),
+    ("compute", gl::COMPUTE_SHADER),
+];
+
+fn stage_of(name: &str) -> Option<GLenum> {
+    let name = name.trim().to_lowercase();
+    STAGE_NAMES
+        .iter()
+        .find(|(stage_name, _)| name == *stage_name)
+        .map(|(_, stage)| *stage)
+}
+
+//一行里最先提到的"xxx shader"/"xxx stage"
+fn mentioned_stage(line: &str) -> Option<GLenum> {
+    let line = line.to_lowercase();
+    STAGE_NAMES
+        .iter()
+        .filter_map(|(name, stage)| {
+            [" shader", " stage"]
+                .iter()
+                .filter_map(|suffix| line.find(&format!("{}{}", name, suffix)))
+                .min()
+                .map(|at| (at, *stage))
+        })
+        .min_by_key(|(at, _)| *at)
+        .map(|(_, stage)| stage)
+}
+
+impl ShaderDiagnostic {
+    pub fn parse(log_line: &str, source: &str, line_offset: usize) -> Self {
+        let (line, level, message) = match parse_line(log_line.trim()) {
+            Some((line, level, message)) => (
+                line.and_then(|line| line.checked_sub(line_offset))
+                    .filter(|line| *line > 0),
+                level,
+                message,
+            ),
+            None => (None, String::new(), log_line.trim().to_string()),
+        };
+        let snippet = match line {
+            Some(line) => source
+                .lines()
+                .enumerate()
+                .map(|(index, code)| (index + 1, code.to_string()))
+                .skip(line.saturating_sub(SNIPPET_AROUND + 1))
+                .take(SNIPPET_AROUND * 2 + 1)
+                .collect(),
+            None => Vec::new(),
+        };
+        Self {
+            line,
+            level,
+            message,
+            snippet,
+        }
+    }
+}
+
+fn level_of(str: &str) -> Option<String> {
+    let level = str.split_whitespace().next()?.to_lowercase();
+    match level.as_str() {
+        "error" | "warning" | "info" | "fatal" => Some(level),
+        _ => None,
+    }
+}
+
+//(行号,级别,信息)
+fn parse_line(log: &str) -> Option<(Option<usize>, String, String)> {
+    let (head, rest) = log.split_once(':')?;
+    if let Some(level) = level_of(head) {
+        // amd/intel/apple: "ERROR: 0:12: message"
+        let mut parts = rest.splitn(3, ':');
+        let file = parts.next()?.trim().parse::<usize>();
+        let line = parts.next().map(|line| line.trim().parse::<usize>());
+        if let (Ok(_), Some(Ok(line)), Some(message)) = (file, line, parts.next()) {
+            return Some((Some(line), level, message.trim().to_string()));
+        }
+        // mesa的链接日志没有行号: "error: message"
+        if head.trim().eq_ignore_ascii_case(&level) {
+            return Some((None, level, rest.trim().to_string()));
+        }
+        return None;
+    }
+    let (line, rest) = match head.split_once('(') {
+        // nvidia: "0(12) : error C1008: message"
+        Some((_, line)) => (line.trim().trim_end_matches(')'), rest),
+        // mesa: "0:12(5): error: message"
+        None => {
+            let (line, rest) = rest.split_once(':')?;
+            (line.split('(').next()?, rest)
+        }
+    };
+    let line = line.trim().parse::<usize>().ok()?;
+    let (level, message) = rest.split_once(':')?;
+    Some((Some(line), level_of(level)?, message.trim().to_string()))
+}
+
+impl Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => writeln!(f, "line {}: {}: {}", line, self.level, self.message)?,
+            None if !self.level.is_empty() => writeln!(f, "{}: {}", self.level, self.message)?,
+            None => writeln!(f, "{}", self.message)?,
+        }
+        for (index, code) in self.snippet.iter() {
+            let mark = if Some(*index) == self.line { ">" } else { " " };
+            writeln!(f, "  {} {:>4} | {}", mark, index, code)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ShaderLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.stage {
+            0 => writeln!(f, "program:")?,
+            stage => writeln!(f, "{} shader:", stage_name(stage))?,
+        }
+        if self.diagnostics.is_empty() {
+            return writeln!(f, "{}", self.log);
+        }
+        for diagnostic in self.diagnostics.iter() {
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ShaderDiagnostic, link_logs};
+
+    const SOURCE: &str = "#version 330\nin vec2 uv;\nout vec4 color;\nvoid main(){\n    color = vec4(uv, 0);\n}";
+
+    #[test]
+    fn parse_driver_logs() {
+        for log in [
+            "0:5(13): error: too few components to construct `vec4'",
+            "0(5) : error C7011: implicit cast from \"vec3\" to \"vec4\"",
+            "ERROR: 0:5: 'constructor' : not enough data provided for construction",
+        ] {
+            let diagnostic = ShaderDiagnostic::parse(log, SOURCE, 0);
+            assert_eq!(diagnostic.line, Some(5), "{}", log);
+            assert_eq!(diagnostic.level, "error");
+            assert!(diagnostic
+                .snippet
+                .iter()
+                .any(|(line, code)| *line == 5 && code.contains("vec4(uv, 0)")));
+        }
+    }
+
+    #[test]
+    fn parse_with_offset() {
+        let diagnostic = ShaderDiagnostic::parse("0:6(1): warning: unused", SOURCE, 1);
+        assert_eq!(diagnostic.line, Some(5));
+        assert_eq!(diagnostic.level, "warning");
+
+        let diagnostic = ShaderDiagnostic::parse("link failed", SOURCE, 0);
+        assert_eq!(diagnostic.line, None);
+        assert!(diagnostic.snippet.is_empty());
+    }
+
+    #[test]
+    fn parse_link_logs() {
+        let stages = [
+            (
+                gl::VERTEX_SHADER,
+                "#version 330\nout vec3 uv;\nvoid main(){}",
+                0,
+            ),
+            (gl::FRAGMENT_SHADER, SOURCE, 0),
+        ];
+        //nvidia:按stage分段,带行号
+        let logs = link_logs(
+            "Vertex info\n-----------\n0(2) : error C5145: output uv type mismatch\n\nFragment info\n-------------\n0(2) : error C5145: input uv type mismatch",
+            &stages,
+        );
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].stage, gl::VERTEX_SHADER);
+        assert_eq!(logs[0].diagnostics[0].line, Some(2));
+        assert!(
+            logs[0].diagnostics[0]
+                .snippet
+                .iter()
+                .any(|(_, code)| code.contains("out vec3 uv"))
+        );
+        assert_eq!(logs[1].stage, gl::FRAGMENT_SHADER);
+        assert!(
+            logs[1].diagnostics[0]
+                .snippet
+                .iter()
+                .any(|(_, code)| code.contains("in vec2 uv"))
+        );
+
+        //mesa/amd:信息里提到的第一个stage
+        let logs = link_logs(
+            "error: fragment shader input `uv' has no matching output in the previous stage\nerror: linking failed",
+            &stages,
+        );
+        assert_eq!((logs[0].stage, logs[1].stage), (gl::FRAGMENT_SHADER, 0));
+        assert_eq!(logs[0].diagnostics[0].level, "error");
+        assert_eq!(logs[0].diagnostics[0].line, None);
+        let logs = link_logs(
+            "Vertex shader(s) failed to link, fragment shader(s) linked.",
+            &stages,
+        );
+        assert_eq!(logs[0].stage, gl::VERTEX_SHADER);
+        assert!(logs[0].to_string().starts_with("vertex shader:"));
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use gl::types::{GLint, GLuint};
+
+use crate::gl_unit::{
+    error::{GlError, GlResult},
+    state,
+};
+
+use super::{Program, UniformInfo, UniformValue, descriptor, read_source};
+
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+type WatchFiles = Vec<(PathBuf, Option<SystemTime>)>;
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+//Program::load 的可热重载版本,轮询json和shader文件的修改时间
+//重载会删掉旧program,所以不借出&Program(Program能clone,留着旧id就悬空了),只转发要用的方法
+pub struct ReloadProgram {
+    path: PathBuf,
+    files: WatchFiles,
+    program: Program,
+    last_check: Instant,
+    pub interval: Duration,
+    pub last_error: Option<GlError>,
+}
+
+impl ReloadProgram {
+    pub fn load(path: impl AsRef<Path>) -> GlResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut files = Vec::new();
+        let program = Self::compile(&path, &mut files)?;
+        let last_error = program.try_load_uniform().err();
+        Ok(Self {
+            path,
+            files,
+            program,
+            last_check: Instant::now(),
+            interval: RELOAD_INTERVAL,
+            last_error,
+        })
+    }
+
+    //json读成功就先换监视列表,shader编译失败时也能盯着新文件
+    fn compile(path: &Path, files: &mut WatchFiles) -> GlResult<Program> {
+        let (json, vert, frag) = descriptor(path)?;
+        *files = [path.to_path_buf(), vert.clone(), frag.clone()]
+            .into_iter()
+            .map(|file| {
+                let time = modified(&file);
+                (file, time)
+            })
+            .collect();
+        let program = Program::try_new(
+            &read_source(&vert)?,
+            &read_source(&frag)?,
+            Some(json["uniforms"].clone()),
+        )?;
+        Ok(program)
+    }
+
+    fn is_changed(&self) -> bool {
+        self.files
+            .iter()
+            .any(|(file, time)| modified(file) != *time)
+    }
+
+    //每帧调用,有改动且链接成功才替换,返回是否替换了;失败的错误同时存在last_error
+    pub fn poll(&mut self) -> GlResult<bool> {
+        if self.last_check.elapsed() < self.interval {
+            return Ok(false);
+        }
+        self.last_check = Instant::now();
+        if !self.is_changed() {
+            return Ok(false);
+        }
+        self.reload().map(|_| true)
+    }
+
+    //链接失败时保留旧的program;链接成功就换上,uniform初值设不上只记到last_error
+    pub fn reload(&mut self) -> GlResult<()> {
+        match Self::compile(&self.path, &mut self.files) {
+            Ok(program) => {
+                self.last_error = program.try_load_uniform().err();
+                delete(&std::mem::replace(&mut self.program, program));
+                Ok(())
+            }
+            Err(err) => {
+                //失败也记下时间,改好之前不再重复编译
+                for (file, time) in self.files.iter_mut() {
+                    *time = modified(file);
+                }
+                self.last_error = Some(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    //每次用都现取,别存起来,重载后会变
+    pub fn id(&self) -> GLuint {
+        self.program.program_id
+    }
+    pub fn bind(&self) {
+        self.program.bind();
+    }
+    pub fn get_uniform(&self, name: &str) -> GLint {
+        self.program.get_uniform(name)
+    }
+    pub fn uniform_info(&self, name: &str) -> Option<&UniformInfo> {
+        self.program.uniform_info(name)
+    }
+    //需要先bind
+    pub fn set_uniform<T: UniformValue>(&self, name: &str, value: T) -> GlResult<()> {
+        self.program.set_uniform(name, value)
+    }
+    pub fn uniform_block_binding(&self, name: &str, binding: u32) -> GlResult<()> {
+        self.program.uniform_block_binding(name, binding)
+    }
+    pub fn storage_block_binding(&self, name: &str, binding: u32) -> GlResult<()> {
+        self.program.storage_block_binding(name, binding)
+    }
+    pub fn set_label(&self, label: &str) {
+        self.program.set_label(label);
+    }
+}
+
+fn delete(program: &Program) {
+    unsafe {
+        gl::DeleteProgram(program.program_id);
+    }
+    state::forget_program(program.program_id);
+}
+
+impl Drop for ReloadProgram {
+    fn drop(&mut self) {
+        delete(&self.program);
+    }
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use std::{
+        fs::{self, File},
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::gl_unit::golden::with_shared_context;
+
+    use super::ReloadProgram;
+
+    const VERT: &str = "#version 330\nlayout (location = 0) in vec4 vert;\nvoid main(){ gl_Position = vec4(vert.xy, 0, 1); }\n";
+    const FRAG: &str =
+        "#version 330\nuniform vec4 tint;\nout vec4 color;\nvoid main(){ color = tint; }\n";
+
+    //写内容并把修改时间钉死,不依赖文件系统的时间精度
+    fn write(path: &Path, code: &str, secs: u64) {
+        fs::write(path, code).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    }
+
+    fn is_program(id: u32) -> bool {
+        unsafe { gl::IsProgram(id) == gl::TRUE }
+    }
+
+    #[test]
+    fn reload_on_change() {
+        with_shared_context(|_| {
+            let dir = std::env::temp_dir().join(format!("gl_tools_reload_{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let json = dir.join("test.json");
+            fs::write(&json, r#"{"vert":"test","frag":"test","uniforms":[]}"#).unwrap();
+            write(&dir.join("test.vert"), VERT, 1);
+            write(&dir.join("test.frag"), FRAG, 1);
+
+            let mut program = ReloadProgram::load(&json).unwrap();
+            program.interval = Duration::ZERO;
+            assert!(!program.poll().unwrap());
+            let first = program.id();
+            assert!(program.uniform_info("tint").is_some());
+
+            //改了shader就重编,旧program删掉
+            write(
+                &dir.join("test.frag"),
+                &FRAG.replace("color = tint", "color = tint * 0.5"),
+                2,
+            );
+            assert!(program.poll().unwrap());
+            let second = program.id();
+            assert_ne!(first, second);
+            assert!(!is_program(first));
+            assert!(is_program(second));
+
+            //改坏了保留旧的,也不会每帧重试
+            write(
+                &dir.join("test.frag"),
+                "#version 330\nvoid main(){ broken }\n",
+                3,
+            );
+            assert!(program.poll().is_err());
+            assert!(program.last_error.is_some());
+            assert_eq!(program.id(), second);
+            assert!(is_program(second));
+            assert!(!program.poll().unwrap());
+
+            //uniform初值设不上照样换上新program,错误记在last_error
+            fs::write(
+                &json,
+                r#"{"vert":"test","frag":"test","uniforms":[{"name":"missing","type":"float","count":1,"values":[1]}]}"#,
+            )
+            .unwrap();
+            write(&dir.join("test.frag"), FRAG, 4);
+            assert!(program.poll().unwrap());
+            let third = program.id();
+            assert_ne!(third, second);
+            assert!(is_program(third));
+            assert!(program.last_error.is_some());
+
+            //还在use的program驱动会推迟删除,先解绑再查
+            drop(program);
+            unsafe { gl::UseProgram(0) };
+            assert!(!is_program(third));
+            fs::remove_dir_all(&dir).unwrap();
+        });
+    }
+}
@@ -0,0 +1,268 @@
+use std::{collections::HashMap, ffi::c_char};
+
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use glam::{IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UniformInfo {
+    pub location: GLint,
+    pub type_: GLenum,
+    //数组长度,非数组为1
+    pub size: GLint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttributeInfo {
+    pub location: GLint,
+    pub type_: GLenum,
+    pub size: GLint,
+}
+
+//纹理单元号,对应 sampler*/image* 类型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampler(pub u32);
+
+pub trait UniformValue: Sized {
+    const GL_TYPES: &'static [GLenum];
+    const NAME: &'static str;
+    fn put_slice(values: &[Self], location: GLint);
+    fn put(&self, location: GLint) {
+        Self::put_slice(std::slice::from_ref(self), location);
+    }
+    fn count(&self) -> usize {
+        1
+    }
+}
+
+macro_rules! uniform_value {
+    ($($type:ty: [$($gl:path),*] => |$values:ident, $location:ident| $body:expr;)*) => {
+        $(
+            impl UniformValue for $type {
+                const GL_TYPES: &'static [GLenum] = &[$($gl),*];
+                const NAME: &'static str = stringify!($type);
+                fn put_slice($values: &[Self], $location: GLint) {
+                    unsafe {
+                        $body;
+                    }
+                }
+            }
+        )*
+    };
+}
+
+//glsl里的sampler*/image*,值都是纹理/图像单元号
+const SAMPLER_TYPES: &[GLenum] = &[
+    gl::SAMPLER_1D,
+    gl::SAMPLER_1D_ARRAY,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_2D_RECT,
+    gl::SAMPLER_2D_MULTISAMPLE,
+    gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_CUBE_MAP_ARRAY,
+    gl::SAMPLER_BUFFER,
+    gl::INT_SAMPLER_1D,
+    gl::INT_SAMPLER_1D_ARRAY,
+    gl::INT_SAMPLER_2D,
+    gl::INT_SAMPLER_2D_ARRAY,
+    gl::INT_SAMPLER_2D_RECT,
+    gl::INT_SAMPLER_2D_MULTISAMPLE,
+    gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::INT_SAMPLER_3D,
+    gl::INT_SAMPLER_CUBE,
+    gl::INT_SAMPLER_CUBE_MAP_ARRAY,
+    gl::INT_SAMPLER_BUFFER,
+    gl::UNSIGNED_INT_SAMPLER_1D,
+    gl::UNSIGNED_INT_SAMPLER_1D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D,
+    gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_RECT,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_3D,
+    gl::UNSIGNED_INT_SAMPLER_CUBE,
+    gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_BUFFER,
+    gl::SAMPLER_1D_SHADOW,
+    gl::SAMPLER_1D_ARRAY_SHADOW,
+    gl::SAMPLER_2D_SHADOW,
+    gl::SAMPLER_2D_ARRAY_SHADOW,
+    gl::SAMPLER_2D_RECT_SHADOW,
+    gl::SAMPLER_CUBE_SHADOW,
+    gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW,
+    gl::IMAGE_1D,
+    gl::IMAGE_1D_ARRAY,
+    gl::IMAGE_2D,
+    gl::IMAGE_2D_ARRAY,
+    gl::IMAGE_2D_RECT,
+    gl::IMAGE_2D_MULTISAMPLE,
+    gl::IMAGE_2D_MULTISAMPLE_ARRAY,
+    gl::IMAGE_3D,
+    gl::IMAGE_CUBE,
+    gl::IMAGE_CUBE_MAP_ARRAY,
+    gl::IMAGE_BUFFER,
+    gl::INT_IMAGE_1D,
+    gl::INT_IMAGE_1D_ARRAY,
+    gl::INT_IMAGE_2D,
+    gl::INT_IMAGE_2D_ARRAY,
+    gl::INT_IMAGE_2D_RECT,
+    gl::INT_IMAGE_2D_MULTISAMPLE,
+    gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+    gl::INT_IMAGE_3D,
+    gl::INT_IMAGE_CUBE,
+    gl::INT_IMAGE_CUBE_MAP_ARRAY,
+    gl::INT_IMAGE_BUFFER,
+    gl::UNSIGNED_INT_IMAGE_1D,
+    gl::UNSIGNED_INT_IMAGE_1D_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_2D,
+    gl::UNSIGNED_INT_IMAGE_2D_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_2D_RECT,
+    gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE,
+    gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_3D,
+    gl::UNSIGNED_INT_IMAGE_CUBE,
+    gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_BUFFER,
+];
+
+fn len(values: &[impl Sized]) -> GLsizei {
+    values.len() as GLsizei
+}
+
+uniform_value! {
+    f32: [gl::FLOAT] => |values, location| gl::Uniform1fv(location, len(values), values.as_ptr());
+    Vec2: [gl::FLOAT_VEC2] => |values, location| gl::Uniform2fv(location, len(values), values.as_ptr() as *const f32);
+    Vec3: [gl::FLOAT_VEC3] => |values, location| gl::Uniform3fv(location, len(values), values.as_ptr() as *const f32);
+    Vec4: [gl::FLOAT_VEC4] => |values, location| gl::Uniform4fv(location, len(values), values.as_ptr() as *const f32);
+    i32: [gl::INT] => |values, location| gl::Uniform1iv(location, len(values), values.as_ptr());
+    IVec2: [gl::INT_VEC2] => |values, location| gl::Uniform2iv(location, len(values), values.as_ptr() as *const i32);
+    IVec3: [gl::INT_VEC3] => |values, location| gl::Uniform3iv(location, len(values), values.as_ptr() as *const i32);
+    IVec4: [gl::INT_VEC4] => |values, location| gl::Uniform4iv(location, len(values), values.as_ptr() as *const i32);
+    u32: [gl::UNSIGNED_INT] => |values, location| gl::Uniform1uiv(location, len(values), values.as_ptr());
+    UVec2: [gl::UNSIGNED_INT_VEC2] => |values, location| gl::Uniform2uiv(location, len(values), values.as_ptr() as *const u32);
+    UVec3: [gl::UNSIGNED_INT_VEC3] => |values, location| gl::Uniform3uiv(location, len(values), values.as_ptr() as *const u32);
+    UVec4: [gl::UNSIGNED_INT_VEC4] => |values, location| gl::Uniform4uiv(location, len(values), values.as_ptr() as *const u32);
+    Mat2: [gl::FLOAT_MAT2] => |values, location| gl::UniformMatrix2fv(location, len(values), gl::FALSE, values.as_ptr() as *const f32);
+    Mat3: [gl::FLOAT_MAT3] => |values, location| gl::UniformMatrix3fv(location, len(values), gl::FALSE, values.as_ptr() as *const f32);
+    Mat4: [gl::FLOAT_MAT4] => |values, location| gl::UniformMatrix4fv(location, len(values), gl::FALSE, values.as_ptr() as *const f32);
+    bool: [gl::BOOL] => |values, location| {
+        let values: Vec<i32> = values.iter().map(|value| *value as i32).collect();
+        gl::Uniform1iv(location, len(&values), values.as_ptr())
+    };
+}
+
+impl UniformValue for Sampler {
+    const GL_TYPES: &'static [GLenum] = SAMPLER_TYPES;
+    const NAME: &'static str = "Sampler";
+    fn put_slice(values: &[Self], location: GLint) {
+        let values: Vec<i32> = values.iter().map(|value| value.0 as i32).collect();
+        unsafe {
+            gl::Uniform1iv(location, len(&values), values.as_ptr());
+        }
+    }
+}
+
+impl<T: UniformValue, const N: usize> UniformValue for [T; N] {
+    const GL_TYPES: &'static [GLenum] = T::GL_TYPES;
+    const NAME: &'static str = T::NAME;
+    fn put_slice(values: &[Self], location: GLint) {
+        T::put_slice(values.as_flattened(), location);
+    }
+    fn count(&self) -> usize {
+        N
+    }
+}
+
+impl<T: UniformValue + Clone> UniformValue for Vec<T> {
+    const GL_TYPES: &'static [GLenum] = T::GL_TYPES;
+    const NAME: &'static str = T::NAME;
+    fn put_slice(values: &[Self], location: GLint) {
+        let values: Vec<T> = values.iter().flatten().cloned().collect();
+        T::put_slice(&values, location);
+    }
+    fn put(&self, location: GLint) {
+        T::put_slice(self, location);
+    }
+    fn count(&self) -> usize {
+        self.len()
+    }
+}
+
+fn active_name(
+    program_id: GLuint,
+    index: GLuint,
+    max_len: GLint,
+    get: unsafe fn(GLuint, GLuint, GLsizei, *mut GLsizei, *mut GLint, *mut GLenum, *mut c_char),
+) -> (String, GLint, GLenum) {
+    let mut name: Vec<u8> = vec![0; max_len.max(1) as usize];
+    let mut name_len = 0;
+    let mut size = 0;
+    let mut type_ = 0;
+    unsafe {
+        get(
+            program_id,
+            index,
+            max_len,
+            &mut name_len,
+            &mut size,
+            &mut type_,
+            name.as_mut_ptr() as *mut c_char,
+        );
+    }
+    name.truncate(name_len.max(0) as usize);
+    (String::from_utf8_lossy(&name).to_string(), size, type_)
+}
+
+//数组uniform会报成 "name[0]",两种写法都能查到
+pub fn reflect_uniforms(program_id: GLuint) -> HashMap<String, UniformInfo> {
+    let mut map = HashMap::new();
+    let (mut count, mut max_len) = (0, 0);
+    unsafe {
+        gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORMS, &mut count);
+        gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+    }
+    for index in 0..count.max(0) as GLuint {
+        let (name, size, type_) = active_name(program_id, index, max_len, gl::GetActiveUniform);
+        let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+        let location = unsafe { gl::GetUniformLocation(program_id, c_name.as_ptr()) };
+        //uniform block 里的成员没有location
+        if location < 0 {
+            continue;
+        }
+        let info = UniformInfo {
+            location,
+            type_,
+            size,
+        };
+        if let Some(base) = name.strip_suffix("[0]") {
+            map.insert(base.to_string(), info);
+        }
+        map.insert(name, info);
+    }
+    map
+}
+
+pub fn reflect_attributes(program_id: GLuint) -> HashMap<String, AttributeInfo> {
+    let mut map = HashMap::new();
+    let (mut count, mut max_len) = (0, 0);
+    unsafe {
+        gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTES, &mut count);
+        gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_len);
+    }
+    for index in 0..count.max(0) as GLuint {
+        let (name, size, type_) = active_name(program_id, index, max_len, gl::GetActiveAttrib);
+        let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+        let location = unsafe { gl::GetAttribLocation(program_id, c_name.as_ptr()) };
+        map.insert(
+            name,
+            AttributeInfo {
+                location,
+                type_,
+                size,
+            },
+        );
+    }
+    map
+}
@@ -0,0 +1,134 @@
+use gl::types::GLuint;
+
+use super::{caps::caps, define::QueryTarget};
+
+//gl查询对象,结果是异步的:几帧之后ready了再取,直接result会等gpu跑完
+pub struct Query {
+    query: GLuint,
+    target: QueryTarget,
+    active: bool,
+}
+
+impl Query {
+    pub fn new(target: QueryTarget) -> Self {
+        let mut query = 0;
+        unsafe {
+            gl::GenQueries(1, &mut query);
+        }
+        Self {
+            query,
+            target,
+            active: false,
+        }
+    }
+    pub fn target(&self) -> QueryTarget {
+        self.target
+    }
+    //同一种target同时只能有一个在begin/end之间,TIME_ELAPSED不能嵌套
+    pub fn begin(&mut self) {
+        debug_assert!(
+            self.target != QueryTarget::Timestamp,
+            "timestamp use record()"
+        );
+        unsafe {
+            gl::BeginQuery(self.target.as_gl(), self.query);
+        }
+        self.active = true;
+    }
+    pub fn end(&mut self) {
+        if self.active {
+            unsafe {
+                gl::EndQuery(self.target.as_gl());
+            }
+            self.active = false;
+        }
+    }
+    pub fn scoped<T>(&mut self, func: impl FnOnce() -> T) -> T {
+        self.begin();
+        let value = func();
+        self.end();
+        value
+    }
+    //TIMESTAMP:gpu执行到这里时的时间
+    pub fn record(&mut self) {
+        unsafe {
+            gl::QueryCounter(self.query, gl::TIMESTAMP);
+        }
+    }
+    pub fn ready(&self) -> bool {
+        let mut available = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+    //时间类是纳秒,SAMPLES_PASSED是片元数,ANY_SAMPLES_PASSED是0/1
+    pub fn try_result(&self) -> Option<u64> {
+        self.ready().then(|| self.result())
+    }
+    pub fn result(&self) -> u64 {
+        if caps().es {
+            let mut value = 0;
+            unsafe {
+                gl::GetQueryObjectuiv(self.query, gl::QUERY_RESULT, &mut value);
+            }
+            value as u64
+        } else {
+            let mut value = 0;
+            unsafe {
+                gl::GetQueryObjectui64v(self.query, gl::QUERY_RESULT, &mut value);
+            }
+            value
+        }
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.end();
+        unsafe {
+            gl::DeleteQueries(1, &self.query);
+        }
+    }
+}
+
+//gpu现在的时间戳,纳秒,和Query::record的是同一个时钟
+pub fn gpu_timestamp() -> u64 {
+    let mut value = 0;
+    unsafe {
+        gl::GetInteger64v(gl::TIMESTAMP, &mut value);
+    }
+    value as u64
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use crate::gl_unit::{define::QueryTarget, golden::with_shared_context};
+
+    use super::Query;
+
+    #[test]
+    fn timer_and_occlusion() {
+        with_shared_context(|_| {
+            let mut elapsed = Query::new(QueryTarget::TimeElapsed);
+            let mut any = Query::new(QueryTarget::AnySamplesPassed);
+            let (mut start, mut end) = (
+                Query::new(QueryTarget::Timestamp),
+                Query::new(QueryTarget::Timestamp),
+            );
+            start.record();
+            elapsed.scoped(|| {
+                any.scoped(|| unsafe {
+                    gl::Clear(gl::COLOR_BUFFER_BIT);
+                })
+            });
+            end.record();
+            //clear不产生片元
+            assert_eq!(any.result(), 0);
+            //result等完之后就ready了
+            let time = elapsed.result();
+            assert_eq!(elapsed.try_result(), Some(time));
+            assert!(end.result() >= start.result());
+        });
+    }
+}
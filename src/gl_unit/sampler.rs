@@ -0,0 +1,86 @@
+use gl::types::GLuint;
+
+use super::{define::TextureParm, texture::sampler_parm};
+
+//独立的sampler对象,绑到纹理单元上后覆盖该单元纹理自己的采样参数;swizzle和对齐不归它管
+pub struct TextureSampler {
+    pub sampler: GLuint,
+}
+
+impl TextureSampler {
+    pub fn new(parm: TextureParm) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenSamplers(1, &mut id);
+        }
+        let sampler = Self { sampler: id };
+        sampler.set_parm(parm);
+        sampler
+    }
+    pub fn set_parm(&self, parm: TextureParm) {
+        let id = self.sampler;
+        unsafe {
+            sampler_parm(
+                &parm,
+                |name, value| gl::SamplerParameteri(id, name, value),
+                |name, value| gl::SamplerParameterf(id, name, value),
+                |name, value| gl::SamplerParameterfv(id, name, value.as_ptr()),
+            );
+        }
+    }
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, self.sampler);
+        }
+    }
+    //单元回到用纹理自己的参数
+    pub fn unbind(unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, 0);
+        }
+    }
+}
+
+impl Drop for TextureSampler {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSamplers(1, &self.sampler);
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use gl::types::GLint;
+
+    use crate::gl_unit::{define::TextureParm, golden::with_shared_context};
+
+    use super::TextureSampler;
+
+    fn bound(unit: u32) -> GLint {
+        let mut id = 0;
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::GetIntegerv(gl::SAMPLER_BINDING, &mut id);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+        id
+    }
+
+    #[test]
+    fn bind_unit() {
+        with_shared_context(|_| {
+            let sampler = TextureSampler::new(TextureParm::mipmap());
+            sampler.bind(3);
+            assert_eq!(bound(3), sampler.sampler as GLint);
+            assert_eq!(bound(0), 0);
+            let mut filter = 0;
+            unsafe {
+                gl::GetSamplerParameteriv(sampler.sampler, gl::TEXTURE_MIN_FILTER, &mut filter);
+            }
+            assert_eq!(filter, gl::LINEAR_MIPMAP_LINEAR as GLint);
+            TextureSampler::unbind(3);
+            assert_eq!(bound(3), 0);
+        });
+    }
+}
@@ -0,0 +1,772 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+
+use super::{
+    caps::caps,
+    debug,
+    define::{Blend, BlendEquation, CompareFunc, Face, StencilOp},
+    pipeline::{BlendState, StencilFace, StencilState},
+};
+
+const TEXTURE_UNITS: usize = 32;
+
+//None表示不知道驱动里现在是什么,下次一定会真的调用
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderState {
+    pub program: Option<GLuint>,
+    pub vao: Option<GLuint>,
+    pub draw_fbo: Option<GLuint>,
+    pub read_fbo: Option<GLuint>,
+    pub active_unit: Option<u32>,
+    //每个纹理单元最后绑的(target,id)
+    pub textures: [Option<(GLenum, GLuint)>; TEXTURE_UNITS],
+    //Some(None)是关掉了blend
+    pub blend: Option<Option<BlendState>>,
+    pub blend_color: Option<[f32; 4]>,
+    pub depth_test: Option<bool>,
+    pub depth_func: Option<CompareFunc>,
+    pub depth_write: Option<bool>,
+    pub stencil: Option<Option<StencilState>>,
+    pub cull: Option<Option<Face>>,
+    pub scissor: Option<Option<[i32; 4]>>,
+    pub color_mask: Option<[bool; 4]>,
+    pub viewport: Option<[i32; 4]>,
+}
+
+impl RenderState {
+    pub const fn unknown() -> Self {
+        Self {
+            program: None,
+            vao: None,
+            draw_fbo: None,
+            read_fbo: None,
+            active_unit: None,
+            textures: [None; TEXTURE_UNITS],
+            blend: None,
+            blend_color: None,
+            depth_test: None,
+            depth_func: None,
+            depth_write: None,
+            stencil: None,
+            cull: None,
+            scissor: None,
+            color_mask: None,
+            viewport: None,
+        }
+    }
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self::unknown()
+    }
+}
+
+#[derive(Default)]
+struct StateCache {
+    current: RenderState,
+    stack: Vec<RenderState>,
+}
+
+//按当前context分开存,一个线程上切换多个context不会串
+thread_local! {
+    static STATES: RefCell<HashMap<usize, StateCache>> = RefCell::new(HashMap::new());
+}
+
+//glfw没初始化/没有current context(mock-gl)时是0
+fn context_key() -> usize {
+    unsafe { glfw::ffi::glfwGetCurrentContext() as usize }
+}
+
+fn with_cache<T>(func: impl FnOnce(&mut StateCache) -> T) -> T {
+    let key = context_key();
+    STATES.with_borrow_mut(|states| func(states.entry(key).or_default()))
+}
+
+fn with<T>(func: impl FnOnce(&mut RenderState) -> T) -> T {
+    with_cache(|cache| func(&mut cache.current))
+}
+
+//新建的context可能复用了已销毁context的指针,丢掉旧缓存和栈
+pub(crate) fn reset() {
+    let key = context_key();
+    STATES.with_borrow_mut(|states| states.remove(&key));
+}
+
+//值变了才调用gl
+fn update<T: PartialEq + Copy>(
+    field: impl FnOnce(&mut RenderState) -> &mut Option<T>,
+    value: T,
+    apply: impl FnOnce(T),
+) {
+    let changed = with(|state| {
+        let field = field(state);
+        let changed = *field != Some(value);
+        *field = Some(value);
+        changed
+    });
+    if changed {
+        apply(value);
+    }
+    debug::raise_pending();
+}
+
+pub fn current() -> RenderState {
+    with(|state| *state)
+}
+
+//外部代码直接动了gl状态后调用,之后的设置都会真的下发
+pub fn invalidate() {
+    with(|state| *state = RenderState::unknown());
+}
+
+pub fn use_program(id: GLuint) {
+    update(
+        |state| &mut state.program,
+        id,
+        |id| unsafe {
+            gl::UseProgram(id);
+        },
+    );
+}
+
+pub fn bind_vertex_array(id: GLuint) {
+    update(
+        |state| &mut state.vao,
+        id,
+        |id| unsafe {
+            gl::BindVertexArray(id);
+        },
+    );
+}
+
+pub fn bind_framebuffer(target: GLenum, id: GLuint) {
+    match target {
+        gl::DRAW_FRAMEBUFFER => update(
+            |state| &mut state.draw_fbo,
+            id,
+            |id| unsafe {
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, id);
+            },
+        ),
+        gl::READ_FRAMEBUFFER => update(
+            |state| &mut state.read_fbo,
+            id,
+            |id| unsafe {
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, id);
+            },
+        ),
+        _ => {
+            let same = with(|state| {
+                let same = state.draw_fbo == Some(id) && state.read_fbo == Some(id);
+                state.draw_fbo = Some(id);
+                state.read_fbo = Some(id);
+                same
+            });
+            if !same {
+                unsafe {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+                }
+            }
+        }
+    }
+}
+
+pub fn active_texture(unit: u32) {
+    update(
+        |state| &mut state.active_unit,
+        unit,
+        |unit| unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+        },
+    );
+}
+
+//绑到当前激活的纹理单元上
+pub fn bind_texture(target: GLenum, id: GLuint) {
+    let unit = with(|state| state.active_unit);
+    match unit {
+        Some(unit) if (unit as usize) < TEXTURE_UNITS => {
+            update(
+                |state| &mut state.textures[unit as usize],
+                (target, id),
+                |(target, id)| unsafe {
+                    gl::BindTexture(target, id);
+                },
+            );
+        }
+        _ => unsafe {
+            gl::BindTexture(target, id);
+        },
+    }
+}
+
+pub fn blend(value: Option<BlendState>) {
+    update(
+        |state| &mut state.blend,
+        value,
+        |value| unsafe {
+            match value {
+                Some(blend) => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFuncSeparate(
+                        blend.src_rgb.as_gl(),
+                        blend.dst_rgb.as_gl(),
+                        blend.src_alpha.as_gl(),
+                        blend.dst_alpha.as_gl(),
+                    );
+                    gl::BlendEquationSeparate(
+                        blend.equation_rgb.as_gl(),
+                        blend.equation_alpha.as_gl(),
+                    );
+                }
+                None => gl::Disable(gl::BLEND),
+            }
+        },
+    );
+}
+
+pub fn blend_color(value: [f32; 4]) {
+    update(
+        |state| &mut state.blend_color,
+        value,
+        |[r, g, b, a]| unsafe {
+            gl::BlendColor(r, g, b, a);
+        },
+    );
+}
+
+pub fn depth_test(value: bool) {
+    update(
+        |state| &mut state.depth_test,
+        value,
+        |value| unsafe {
+            if value {
+                gl::Enable(gl::DEPTH_TEST);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        },
+    );
+}
+
+pub fn depth_func(func: CompareFunc) {
+    update(
+        |state| &mut state.depth_func,
+        func,
+        |func| unsafe {
+            gl::DepthFunc(func.as_gl());
+        },
+    );
+}
+
+//也会影响glClear
+pub fn depth_write(value: bool) {
+    update(
+        |state| &mut state.depth_write,
+        value,
+        |value| unsafe {
+            gl::DepthMask(if value { gl::TRUE } else { gl::FALSE });
+        },
+    );
+}
+
+pub fn stencil(value: Option<StencilState>) {
+    update(
+        |state| &mut state.stencil,
+        value,
+        |value| unsafe {
+            match value {
+                Some(stencil) => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    for (face, side) in [(gl::FRONT, stencil.front), (gl::BACK, stencil.back)] {
+                        gl::StencilFuncSeparate(
+                            face,
+                            side.func.as_gl(),
+                            side.reference,
+                            side.read_mask,
+                        );
+                        gl::StencilMaskSeparate(face, side.write_mask);
+                        gl::StencilOpSeparate(
+                            face,
+                            side.fail.as_gl(),
+                            side.depth_fail.as_gl(),
+                            side.pass.as_gl(),
+                        );
+                    }
+                }
+                None => {
+                    gl::Disable(gl::STENCIL_TEST);
+                    //写掩码留着的话glClear清不掉模板
+                    gl::StencilMask(!0);
+                }
+            }
+        },
+    );
+}
+
+pub fn cull(face: Option<Face>) {
+    update(
+        |state| &mut state.cull,
+        face,
+        |face| unsafe {
+            match face {
+                Some(face) => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(face.as_gl());
+                }
+                None => gl::Disable(gl::CULL_FACE),
+            }
+        },
+    );
+}
+
+//也会裁掉glClear
+pub fn scissor(value: Option<[i32; 4]>) {
+    update(
+        |state| &mut state.scissor,
+        value,
+        |value| unsafe {
+            match value {
+                Some([x, y, w, h]) => {
+                    gl::Enable(gl::SCISSOR_TEST);
+                    gl::Scissor(x, y, w as GLsizei, h as GLsizei);
+                }
+                None => gl::Disable(gl::SCISSOR_TEST),
+            }
+        },
+    );
+}
+
+pub fn color_mask(value: [bool; 4]) {
+    update(
+        |state| &mut state.color_mask,
+        value,
+        |value| unsafe {
+            let [r, g, b, a] = value.map(|on| if on { gl::TRUE } else { gl::FALSE });
+            gl::ColorMask(r, g, b, a);
+        },
+    );
+}
+
+pub fn viewport(x: i32, y: i32, w: i32, h: i32) {
+    update(
+        |state| &mut state.viewport,
+        [x, y, w, h],
+        |[x, y, w, h]| unsafe {
+            gl::Viewport(x, y, w as GLsizei, h as GLsizei);
+        },
+    );
+}
+
+//对象删掉后名字会被重用,缓存里要清掉
+pub fn forget_program(id: GLuint) {
+    with(|state| {
+        if state.program == Some(id) {
+            state.program = None;
+        }
+    });
+}
+pub fn forget_vertex_array(id: GLuint) {
+    with(|state| {
+        if state.vao == Some(id) {
+            state.vao = Some(0);
+        }
+    });
+}
+pub fn forget_framebuffer(id: GLuint) {
+    with(|state| {
+        for fbo in [&mut state.draw_fbo, &mut state.read_fbo] {
+            if *fbo == Some(id) {
+                *fbo = Some(0);
+            }
+        }
+    });
+}
+pub fn forget_texture(id: GLuint) {
+    with(|state| {
+        for texture in state.textures.iter_mut() {
+            //删掉后所有单元上的绑定都回到0
+            if let Some((target, bind)) = *texture
+                && bind == id
+            {
+                *texture = Some((target, 0));
+            }
+        }
+    });
+}
+
+//把缓存里已知的状态真的设回去
+fn apply(target: RenderState) {
+    if let Some(id) = target.program {
+        use_program(id);
+    }
+    if let Some(id) = target.vao {
+        bind_vertex_array(id);
+    }
+    if let Some(id) = target.draw_fbo {
+        bind_framebuffer(gl::DRAW_FRAMEBUFFER, id);
+    }
+    if let Some(id) = target.read_fbo {
+        bind_framebuffer(gl::READ_FRAMEBUFFER, id);
+    }
+    for (unit, texture) in target.textures.iter().enumerate() {
+        if let Some((texture_target, id)) = texture {
+            active_texture(unit as u32);
+            bind_texture(*texture_target, *id);
+        }
+    }
+    if let Some(unit) = target.active_unit {
+        active_texture(unit);
+    }
+    if let Some(value) = target.blend {
+        blend(value);
+    }
+    if let Some(value) = target.blend_color {
+        blend_color(value);
+    }
+    if let Some(value) = target.depth_test {
+        depth_test(value);
+    }
+    if let Some(value) = target.depth_func {
+        depth_func(value);
+    }
+    if let Some(value) = target.depth_write {
+        depth_write(value);
+    }
+    if let Some(value) = target.stencil {
+        stencil(value);
+    }
+    if let Some(value) = target.cull {
+        cull(value);
+    }
+    if let Some(value) = target.scissor {
+        scissor(value);
+    }
+    if let Some(value) = target.color_mask {
+        color_mask(value);
+    }
+    if let Some([x, y, w, h]) = target.viewport {
+        viewport(x, y, w, h);
+    }
+}
+
+//能从驱动读回来的字段里有不知道的
+fn has_unknown(state: &RenderState) -> bool {
+    state.program.is_none()
+        || state.vao.is_none()
+        || state.draw_fbo.is_none()
+        || state.read_fbo.is_none()
+        || state.active_unit.is_none()
+        || state.depth_test.is_none()
+        || state.depth_func.is_none()
+        || state.depth_write.is_none()
+        || state.color_mask.is_none()
+        || state.blend_color.is_none()
+        || state.viewport.is_none()
+        || state.blend.is_none()
+        || state.cull.is_none()
+        || state.scissor.is_none()
+        || state.stencil.is_none()
+}
+
+//不知道的字段先从驱动读出来再压栈,不然pop时还原不了
+pub fn push() {
+    let known = current();
+    if has_unknown(&known) {
+        let read = query();
+        with(|state| {
+            *state = RenderState {
+                program: known.program.or(read.program),
+                vao: known.vao.or(read.vao),
+                draw_fbo: known.draw_fbo.or(read.draw_fbo),
+                read_fbo: known.read_fbo.or(read.read_fbo),
+                active_unit: known.active_unit.or(read.active_unit),
+                textures: std::array::from_fn(|unit| known.textures[unit].or(read.textures[unit])),
+                blend: known.blend.or(read.blend),
+                blend_color: known.blend_color.or(read.blend_color),
+                depth_test: known.depth_test.or(read.depth_test),
+                depth_func: known.depth_func.or(read.depth_func),
+                depth_write: known.depth_write.or(read.depth_write),
+                stencil: known.stencil.or(read.stencil),
+                cull: known.cull.or(read.cull),
+                scissor: known.scissor.or(read.scissor),
+                color_mask: known.color_mask.or(read.color_mask),
+                viewport: known.viewport.or(read.viewport),
+            }
+        });
+    }
+    with_cache(|cache| {
+        let current = cache.current;
+        cache.stack.push(current);
+    });
+}
+
+pub fn pop() {
+    let Some(target) = with_cache(|cache| cache.stack.pop()) else {
+        eprintln!("[render state]pop without push");
+        return;
+    };
+    apply(target);
+}
+
+//func里改的状态在返回后全部还原
+pub fn scoped<T>(func: impl FnOnce() -> T) -> T {
+    push();
+    let value = func();
+    pop();
+    value
+}
+
+//从驱动里把当前状态读进缓存
+pub fn query() -> RenderState {
+    let mut state = RenderState::unknown();
+    unsafe {
+        state.program = Some(get_integer(gl::CURRENT_PROGRAM) as GLuint);
+        state.vao = Some(get_integer(gl::VERTEX_ARRAY_BINDING) as GLuint);
+        state.draw_fbo = Some(get_integer(gl::DRAW_FRAMEBUFFER_BINDING) as GLuint);
+        state.read_fbo = Some(get_integer(gl::READ_FRAMEBUFFER_BINDING) as GLuint);
+        let active = get_integer(gl::ACTIVE_TEXTURE) as GLenum;
+        state.depth_test = Some(gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE);
+        //开着的时候连函数一起读回来;读到枚举里没有的值就当不知道
+        state.blend = enabled_or(gl::BLEND, query_blend);
+        state.cull = enabled_or(gl::CULL_FACE, || {
+            Face::try_from_gl(get_integer(gl::CULL_FACE_MODE) as GLenum)
+        });
+        state.scissor = enabled_or(gl::SCISSOR_TEST, || {
+            let mut scissor = [0; 4];
+            gl::GetIntegerv(gl::SCISSOR_BOX, scissor.as_mut_ptr());
+            Some(scissor)
+        });
+        state.stencil = enabled_or(gl::STENCIL_TEST, || {
+            Some(StencilState {
+                front: query_stencil_face(STENCIL_FRONT)?,
+                back: query_stencil_face(STENCIL_BACK)?,
+            })
+        });
+        state.active_unit = Some(active - gl::TEXTURE0);
+        state.textures = query_textures(active);
+        state.depth_func = Some(CompareFunc::from_gl(get_integer(gl::DEPTH_FUNC) as GLenum));
+        let mut mask = [gl::FALSE; 4];
+        gl::GetBooleanv(gl::DEPTH_WRITEMASK, mask.as_mut_ptr());
+        state.depth_write = Some(mask[0] == gl::TRUE);
+        gl::GetBooleanv(gl::COLOR_WRITEMASK, mask.as_mut_ptr());
+        state.color_mask = Some(mask.map(|on| on == gl::TRUE));
+        let mut color = [0f32; 4];
+        gl::GetFloatv(gl::BLEND_COLOR, color.as_mut_ptr());
+        state.blend_color = Some(color);
+        let mut viewport = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        state.viewport = Some(viewport);
+    }
+    with(|current| *current = state);
+    state
+}
+
+fn get_integer(name: GLenum) -> GLint {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(name, &mut value);
+    }
+    value
+}
+
+//关着是Some(None),开着读不出来是None
+fn enabled_or<T>(cap: GLenum, read: impl FnOnce() -> Option<T>) -> Option<Option<T>> {
+    if unsafe { gl::IsEnabled(cap) } == gl::FALSE {
+        return Some(None);
+    }
+    read().map(Some)
+}
+
+fn query_blend() -> Option<BlendState> {
+    let blend = |name| Blend::try_from_gl(get_integer(name) as GLenum);
+    let equation = |name| BlendEquation::try_from_gl(get_integer(name) as GLenum);
+    Some(BlendState {
+        src_rgb: blend(gl::BLEND_SRC_RGB)?,
+        dst_rgb: blend(gl::BLEND_DST_RGB)?,
+        src_alpha: blend(gl::BLEND_SRC_ALPHA)?,
+        dst_alpha: blend(gl::BLEND_DST_ALPHA)?,
+        equation_rgb: equation(gl::BLEND_EQUATION_RGB)?,
+        equation_alpha: equation(gl::BLEND_EQUATION_ALPHA)?,
+    })
+}
+
+//(func,ref,value mask,write mask,fail,depth fail,pass)
+const STENCIL_FRONT: [GLenum; 7] = [
+    gl::STENCIL_FUNC,
+    gl::STENCIL_REF,
+    gl::STENCIL_VALUE_MASK,
+    gl::STENCIL_WRITEMASK,
+    gl::STENCIL_FAIL,
+    gl::STENCIL_PASS_DEPTH_FAIL,
+    gl::STENCIL_PASS_DEPTH_PASS,
+];
+const STENCIL_BACK: [GLenum; 7] = [
+    gl::STENCIL_BACK_FUNC,
+    gl::STENCIL_BACK_REF,
+    gl::STENCIL_BACK_VALUE_MASK,
+    gl::STENCIL_BACK_WRITEMASK,
+    gl::STENCIL_BACK_FAIL,
+    gl::STENCIL_BACK_PASS_DEPTH_FAIL,
+    gl::STENCIL_BACK_PASS_DEPTH_PASS,
+];
+fn query_stencil_face(names: [GLenum; 7]) -> Option<StencilFace> {
+    let [
+        func,
+        reference,
+        read_mask,
+        write_mask,
+        fail,
+        depth_fail,
+        pass,
+    ] = names.map(get_integer);
+    let op = |value: GLint| StencilOp::try_from_gl(value as GLenum);
+    Some(StencilFace {
+        func: CompareFunc::try_from_gl(func as GLenum)?,
+        reference,
+        read_mask: read_mask as u32,
+        write_mask: write_mask as u32,
+        fail: op(fail)?,
+        depth_fail: op(depth_fail)?,
+        pass: op(pass)?,
+    })
+}
+
+//每个单元上非0的第一个target;都是0就记成(TEXTURE_2D,0)。查完切回active
+fn query_textures(active: GLenum) -> [Option<(GLenum, GLuint)>; TEXTURE_UNITS] {
+    const BINDINGS: [(GLenum, GLenum); 5] = [
+        (gl::TEXTURE_2D, gl::TEXTURE_BINDING_2D),
+        (gl::TEXTURE_CUBE_MAP, gl::TEXTURE_BINDING_CUBE_MAP),
+        (gl::TEXTURE_2D_ARRAY, gl::TEXTURE_BINDING_2D_ARRAY),
+        (gl::TEXTURE_3D, gl::TEXTURE_BINDING_3D),
+        (
+            gl::TEXTURE_2D_MULTISAMPLE,
+            gl::TEXTURE_BINDING_2D_MULTISAMPLE,
+        ),
+    ];
+    let units =
+        (get_integer(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS).max(0) as usize).min(TEXTURE_UNITS);
+    //es 3.0没有多重采样纹理的绑定点,查了是INVALID_ENUM
+    let caps = caps();
+    let multisample = !caps.es || caps.at_least(3, 1);
+    let mut textures = [None; TEXTURE_UNITS];
+    for (unit, texture) in textures.iter_mut().take(units).enumerate() {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+        }
+        let bound = BINDINGS
+            .into_iter()
+            .take(if multisample { 5 } else { 4 })
+            .map(|(target, binding)| (target, get_integer(binding) as GLuint))
+            .find(|(_, id)| *id != 0);
+        *texture = Some(bound.unwrap_or((gl::TEXTURE_2D, 0)));
+    }
+    unsafe {
+        gl::ActiveTexture(active);
+    }
+    textures
+}
+
+#[cfg(all(test, not(feature = "mock-gl")))]
+mod test {
+    use crate::gl_unit::{
+        ConstBlend, GLcontext, const_blend,
+        define::{Blend, CompareFunc, Face, TextureParm, TextureType},
+        golden::with_headless,
+        pipeline::BlendState,
+        texture::{Texture2D, TextureWrapper},
+    };
+
+    use super::{
+        active_texture, bind_texture, cull, current, invalidate, query, scissor, scoped, stencil,
+        viewport,
+    };
+
+    #[test]
+    fn scoped_restore() {
+        with_headless(64, 64, |context| {
+            const_blend(ConstBlend::Normal);
+            viewport(0, 0, 64, 64);
+            let before = context.state();
+            scoped(|| {
+                const_blend(ConstBlend::SrcOnly);
+                cull(Some(Face::Back));
+                viewport(0, 0, 8, 8);
+                assert_eq!(
+                    current().blend,
+                    Some(Some(BlendState::new(Blend::One, Blend::Zero)))
+                );
+            });
+            assert_eq!(context.state(), before);
+            assert_eq!(current().cull, Some(None));
+
+            //push时不知道的viewport会先读回来,pop能还原
+            invalidate();
+            scoped(|| viewport(0, 0, 8, 8));
+            assert_eq!(current().viewport, Some([0, 0, 64, 64]));
+
+            //外部代码开着blend/cull/scissor/stencil、绑着纹理,也要读回来再还原
+            let texture = TextureWrapper(Texture2D::with_size(
+                4,
+                4,
+                TextureType::RGBA8,
+                TextureParm::new(),
+            ));
+            unsafe {
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+                gl::Enable(gl::CULL_FACE);
+                gl::CullFace(gl::FRONT);
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Scissor(1, 2, 3, 4);
+                gl::Enable(gl::STENCIL_TEST);
+                gl::StencilFunc(gl::EQUAL, 3, 0xFF);
+                gl::ActiveTexture(gl::TEXTURE2);
+                gl::BindTexture(gl::TEXTURE_2D, texture.texture);
+                gl::ActiveTexture(gl::TEXTURE0);
+            }
+            invalidate();
+            scoped(|| {
+                const_blend(ConstBlend::SrcOnly);
+                cull(None);
+                scissor(None);
+                stencil(None);
+                active_texture(2);
+                bind_texture(gl::TEXTURE_2D, 0);
+            });
+            let state = current();
+            assert_eq!(state.blend, Some(Some(BlendState::additive())));
+            assert_eq!(state.cull, Some(Some(Face::Front)));
+            assert_eq!(state.scissor, Some(Some([1, 2, 3, 4])));
+            let stencil = state.stencil.unwrap().unwrap();
+            assert_eq!(
+                (stencil.front.func, stencil.front.reference),
+                (CompareFunc::Equal, 3)
+            );
+            assert_eq!(state.textures[2], Some((gl::TEXTURE_2D, texture.texture)));
+            //缓存和驱动里的一致
+            assert_eq!(query(), state);
+        });
+    }
+
+    #[test]
+    fn per_context_cache() {
+        with_headless(64, 64, |context| {
+            viewport(0, 0, 32, 32);
+            //新context变成current后用自己的缓存
+            let mut other = GLcontext::headless(4, 4);
+            assert_eq!(current().viewport, Some([0, 0, 4, 4]));
+            assert_eq!(query().viewport, Some([0, 0, 4, 4]));
+            context.headless.as_mut().unwrap().current();
+            assert_eq!(current().viewport, Some([0, 0, 32, 32]));
+            assert_eq!(query().viewport, current().viewport);
+            //fbo要在自己的context里删
+            other.headless.as_mut().unwrap().current();
+            drop(other);
+            context.headless.as_mut().unwrap().current();
+            assert_eq!(current().viewport, Some([0, 0, 32, 32]));
+        });
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    ffi::c_void,
+    marker::PhantomData,
+    ptr::null,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use gl::types::{GLenum, GLuint};
+
+use super::{
+    buffer::Buffer,
+    define::{BufferTarget, TypeGL},
+};
+
+//环形的流式缓冲:每次push写到上次结束的位置,写满了就orphan整块存储从头开始。
+//写入用 MAP_UNSYNCHRONIZED,因为同一块存储里已经交出去的区间不会再被覆盖,不用等gpu
+pub struct StreamBuffer<T: TypeGL> {
+    target: BufferTarget,
+    id: GLuint,
+    //都按字节
+    capacity: AtomicUsize,
+    cursor: AtomicUsize,
+    type_const: PhantomData<T>,
+}
+
+impl<T: TypeGL> StreamBuffer<T> {
+    //len:能放下多少个T,不够会自动扩容
+    pub fn new(target: BufferTarget, len: usize) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+        }
+        let buffer = Self {
+            target,
+            id,
+            capacity: AtomicUsize::new(len.max(1) * size_of::<T>()),
+            cursor: AtomicUsize::new(0),
+            type_const: PhantomData,
+        };
+        buffer.orphan();
+        buffer
+    }
+    //丢掉旧存储换一块新的,旧的等gpu用完由驱动回收
+    fn orphan(&self) {
+        self.bind_target();
+        unsafe {
+            gl::BufferData(
+                self.target.as_gl(),
+                self.capacity.load(Ordering::Relaxed) as isize,
+                null(),
+                gl::STREAM_DRAW,
+            );
+        }
+        self.cursor.store(0, Ordering::Relaxed);
+    }
+    //align按字节,返回写入位置的字节偏移
+    pub fn push(&self, data: &[T], align: usize) -> usize {
+        let size = std::mem::size_of_val(data);
+        let align = align.max(1);
+        let mut offset = self.cursor.load(Ordering::Relaxed).div_ceil(align) * align;
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if offset + size > capacity {
+            if size > capacity {
+                self.capacity
+                    .store(size.next_power_of_two(), Ordering::Relaxed);
+            }
+            self.orphan();
+            offset = 0;
+        }
+        if size == 0 {
+            return offset;
+        }
+        self.bind_target();
+        unsafe {
+            let ptr = gl::MapBufferRange(
+                self.target.as_gl(),
+                offset as isize,
+                size as isize,
+                gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT | gl::MAP_UNSYNCHRONIZED_BIT,
+            );
+            if ptr.is_null() {
+                //映射失败就退回同步上传
+                gl::BufferSubData(
+                    self.target.as_gl(),
+                    offset as isize,
+                    size as isize,
+                    data.as_ptr() as *const c_void,
+                );
+            } else {
+                std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, ptr as *mut u8, size);
+                gl::UnmapBuffer(self.target.as_gl());
+            }
+        }
+        self.cursor.store(offset + size, Ordering::Relaxed);
+        offset
+    }
+    //按顶点写入,返回 draw_arrays 用的起始顶点号;components是每个顶点的分量数,不能为0
+    pub fn push_vertex(&self, data: &[T], components: usize) -> i32 {
+        assert!(components > 0, "push_vertex with 0 components");
+        let stride = components * size_of::<T>();
+        (self.push(data, stride) / stride) as i32
+    }
+}
+
+impl<T: TypeGL> Buffer for StreamBuffer<T> {
+    fn type_as_gl(&self) -> GLenum {
+        T::as_gl()
+    }
+
+    fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    fn id(&self) -> GLuint {
+        self.id
+    }
+
+    fn count(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed) / size_of::<T>()
+    }
+}
+
+impl<T: TypeGL> Drop for StreamBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            self.unbind_target();
+            gl::DeleteBuffers(1, &self.id as *const GLuint);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-gl"))]
+mod test {
+    use crate::gl_unit::{buffer::Buffer, define::BufferTarget, mock};
+
+    use super::StreamBuffer;
+
+    //每次orphan分配的字节数
+    fn orphans() -> Vec<String> {
+        mock::calls()
+            .into_iter()
+            .filter(|call| call.name == "BufferData")
+            .map(|call| call.args[1].clone())
+            .collect()
+    }
+
+    #[test]
+    fn ring_offsets() {
+        mock::load();
+        let stream = StreamBuffer::<f32>::new(BufferTarget::Vertex, 16);
+        assert_eq!(orphans(), ["64"]);
+        //按align对齐往后排
+        assert_eq!(stream.push(&[0f32; 2], 4), 0);
+        assert_eq!(stream.push(&[0f32; 1], 16), 16);
+        assert_eq!(stream.push_vertex(&[0f32; 3], 3), 2);
+        //下一帧接着上次的位置写,正好写满不换存储
+        assert_eq!(stream.push(&[0f32; 7], 4), 36);
+        assert_eq!(orphans(), ["64"]);
+        //写不下就orphan,从头开始
+        assert_eq!(stream.push(&[0f32; 1], 4), 0);
+        assert_eq!(stream.push(&[0f32; 1], 4), 4);
+        assert_eq!(orphans(), ["64", "64"]);
+        assert_eq!(mock::count("MapBufferRange"), 6);
+    }
+
+    #[test]
+    fn grow_when_too_large() {
+        mock::load();
+        let stream = StreamBuffer::<f32>::new(BufferTarget::Vertex, 4);
+        assert_eq!(stream.push(&[0f32; 2], 4), 0);
+        //比整块还大:扩到2的幂再从头写
+        assert_eq!(stream.push(&[0f32; 20], 4), 0);
+        assert_eq!(stream.count(), 32);
+        assert_eq!(orphans(), ["16", "128"]);
+        assert_eq!(stream.push(&[0f32; 2], 4), 80);
+        //空的不映射也不换存储
+        assert_eq!(stream.push(&[], 4), 88);
+        assert_eq!(mock::count("MapBufferRange"), 3);
+        assert_eq!(orphans(), ["16", "128"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "0 components")]
+    fn zero_components() {
+        mock::load();
+        let stream = StreamBuffer::<f32>::new(BufferTarget::Vertex, 4);
+        stream.push_vertex(&[0f32; 2], 0);
+    }
+}
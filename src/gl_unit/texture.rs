@@ -7,20 +7,28 @@ use std::{
     ops::Deref,
     path::Path,
     ptr::null,
+    f32::consts::{PI, TAU},
 };
 
 use gl::types::{GLenum, GLint, GLsizei, GLuint};
-use glam::{vec2, Mat4, Vec2};
+use glam::{vec2, vec3, Mat4, Vec2, Vec3, Vec4};
 use guillotiere::*;
-use image::{DynamicImage, EncodableLayout, ImageBuffer, Rgba};
+use image::{DynamicImage, EncodableLayout, ImageBuffer, Rgba, Rgba32FImage};
 
 use std::fmt::{Debug, Formatter};
 
-use crate::{
-    gl_unit::define::DrawMode, TEX_VERTEX_STATIC, TEX_VERTEX_YFLIP_STATIC, VAO_MUT, VERTEX_MUT,
-};
+use crate::{STREAM_VERTEX, VAO_MUT};
 
-use super::define::{self, Filter, TextureParm, TextureType, VertexArrayAttribPointerGen};
+use super::caps::caps;
+use super::debug;
+use super::compressed::CompressedImage;
+use super::state;
+use super::define::{
+    self, Filter, ImageAccess, TextureParm, TextureType, TextureWarpMode,
+    VertexArrayAttribPointerGen,
+};
+use super::error::{GlError, GlResult};
+use super::framebuffer::DepthFormat;
 use super::{program::PROGRAM2D_TWO, ConstBlend, FrameBuffer};
 const TEXTURE_MAP_SPLIT: i32 = 1;
 
@@ -96,15 +104,16 @@ impl<T: Hash + Eq> TextureMap<T> {
                 .min_filter(Filter::Linear)
                 .mag_filter(Filter::Linear),
         ));
+        //挂纹理要绑fbo,建完还原之前绑的,画到一半建图集(比如新建Font)不会把目标换掉
         let mut frame = FrameBuffer::new();
-        frame.link_texture(texture, gl::COLOR_ATTACHMENT0);
-        FrameBuffer::unbind();
+        state::scoped(|| frame.link_texture(texture, gl::COLOR_ATTACHMENT0));
         Self {
             index: uv_list,
             frame,
             allocator: rect_map,
         }
     }
+    //画进图集时改的blend/fbo/viewport/program都会还原
     pub fn add(
         &mut self,
         vec: Vec<(T, TextureWrapper<Texture2D>)>,
@@ -113,6 +122,13 @@ impl<T: Hash + Eq> TextureMap<T> {
         if vec.is_empty() {
             return Ok(());
         }
+        state::scoped(|| self.draw_into(vec, y_flip))
+    }
+    fn draw_into(
+        &mut self,
+        vec: Vec<(T, TextureWrapper<Texture2D>)>,
+        y_flip: bool,
+    ) -> Result<(), &'static str> {
         crate::gl_unit::const_blend(ConstBlend::SrcOnly);
         self.frame.bind(gl::FRAMEBUFFER);
         self.frame.view_port();
@@ -125,23 +141,14 @@ impl<T: Hash + Eq> TextureMap<T> {
 
         program.put_matrix_name(Mat4::IDENTITY, "model_mat");
         program.put_texture(0, program.get_uniform("image"));
-        VAO_MUT.bind(|vao| {
-            vao.bind_pointer(
-                if y_flip {
-                    TEX_VERTEX_YFLIP_STATIC.deref()
-                } else {
-                    TEX_VERTEX_STATIC.deref()
-                },
-                VertexArrayAttribPointerGen::new::<f32>(1, 2),
-            );
-            vao.bind_pointer(
-                VERTEX_MUT.deref(),
-                VertexArrayAttribPointerGen::new::<f32>(0, 2),
-            );
-        });
+        //uv和位置交错写进同一段流,base vertex对两个属性都对
+        let tex_uv = if y_flip {
+            [0f32, 0f32, 1f32, 0f32, 1f32, 1f32, 0f32, 1f32]
+        } else {
+            [0f32, 1f32, 1f32, 1f32, 1f32, 0f32, 0f32, 0f32]
+        };
         let mut uv_list = HashMap::new();
         for (name, texture) in vec.into_iter() {
-            println!("{},{}", texture.w, texture.h);
             let texture = texture.as_ref();
             let uv;
             if texture.w == 0 || texture.h == 0 {
@@ -169,23 +176,36 @@ impl<T: Hash + Eq> TextureMap<T> {
                     h: texture.h as f32 / size.height as f32,
                 };
 
-                VERTEX_MUT.sub_data(
-                    &[
-                        uv.x,
-                        uv.y + uv.h,
-                        uv.x + uv.w,
-                        uv.y + uv.h,
-                        uv.x + uv.w,
-                        uv.y,
-                        uv.x,
-                        uv.y,
-                    ],
-                    0,
-                );
+                let pos = [
+                    uv.x,
+                    uv.y + uv.h,
+                    uv.x + uv.w,
+                    uv.y + uv.h,
+                    uv.x + uv.w,
+                    uv.y,
+                    uv.x,
+                    uv.y,
+                ];
+                let vertex: Vec<f32> = pos
+                    .chunks(2)
+                    .zip(tex_uv.chunks(2))
+                    .flat_map(|(pos, uv)| [pos[0], pos[1], uv[0], uv[1]])
+                    .collect();
+                let first = STREAM_VERTEX.push_vertex(&vertex, 4);
                 texture.bind_unit(0);
-                // println!("vao bind:{}",crate::gl_unit::debug::now_vao_id());
-                
-                VAO_MUT.bind(|vao|{vao.draw_arrays(DrawMode::Quads, 0, 4);});
+                VAO_MUT.bind(|vao| {
+                    for index in 0..2 {
+                        vao.bind_pointer(
+                            STREAM_VERTEX.deref(),
+                            VertexArrayAttribPointerGen::new::<f32>(index, 2)
+                                .stride_size(4 * size_of::<f32>() as i32)
+                                .pointer(index as usize * 2 * size_of::<f32>()),
+                        );
+                    }
+                    vao.draw_quads(first, 1);
+                    //VAO_MUT的其他用户只配0号属性
+                    vao.disable_pointer(1);
+                });
             }
             uv_list.insert(name, uv);
         }
@@ -205,129 +225,285 @@ impl<T: Hash + Eq> TextureMap<T> {
     }
 
     pub fn get_tex(&self) -> &Texture2D {
-        self.frame.texture.as_ref().unwrap()
+        self.frame.texture().unwrap()
     }
 }
 #[cfg(test)]
 mod test {
-    use std::{
-        fs::{self},
-        ops::Deref,
-        path::Path,
-    };
-
-    use glam::Mat4;
-
-    use crate::{
-        gl_unit::{
-            define::{DrawMode, TextureParm, VertexArrayAttribPointerGen},
-            program::PROGRAM2D_ONE,
-            texture::{Texture, TextureWrapper},
-            window::Window,
-            GLcontext,
-        },
-        TEX_VERTEX_MUT, VAO_MUT, VERTEX_MUT,
-    };
+    use image::{Rgba, Rgba32FImage};
 
-    use super::{Texture2D, TextureMap};
+    use super::{CubeFace, equirect_face};
 
+    //要真的context,mock-gl下不编译
+    #[cfg(not(feature = "mock-gl"))]
     #[test]
     fn texture_map() {
-        let mut window = Window::new(800, 600, "test", false);
-        let mut context = GLcontext::with(&mut window);
-
-        let tex_list = {
-            let mut map = Vec::new();
-            let read_dir = fs::read_dir(Path::new(
-                "E:\\RustroverProjects\\bone_static\\Res\\Texture",
-            ))
-            .unwrap();
-
-            for file in read_dir {
-                let file = match file {
-                    Ok(file) => file,
-                    Err(_) => continue, // 跳过错误项
-                };
-
-                // 确保不是目录
-                if file.file_type().map(|ft| ft.is_dir()).unwrap_or(true) {
-                    continue; // 如果是目录或无法获取文件类型，跳过
-                }
+        use crate::gl_unit::{
+            define::{TextureParm, TextureType},
+            golden::{assert_golden, read_frame, with_shared_context},
+            texture::{Texture2D, TextureMap, TextureWrapper},
+        };
 
-                let file_name = file.file_name();
-                let name = file_name.to_str().unwrap();
-                let set_name = &name[..name.find(".").unwrap()];
-                println!("load:{:?}", file.path());
-                map.push((
-                    set_name.to_string(),
-                    TextureWrapper(Texture2D::load_path(
-                        file.path().as_path(),
+        //TextureMap::add用到全局的STREAM_VERTEX/VAO_MUT,要和别的golden测试在同一个context里
+        let (has_uv, image) = with_shared_context(|_| {
+            let tex_list = [
+                ("red", 40, 30, [255u8, 0, 0, 255]),
+                ("green", 64, 64, [0, 255, 0, 255]),
+                ("blue", 10, 90, [0, 0, 255, 128]),
+            ]
+            .into_iter()
+            .map(|(name, w, h, pixel)| {
+                let raw: Vec<u8> = (0..w * h).flat_map(|_| pixel).collect();
+                (
+                    name.to_string(),
+                    TextureWrapper(Texture2D::load(
+                        Some(raw.as_slice()),
+                        TextureType::RGBA8,
+                        w,
+                        h,
                         TextureParm::new(),
                     )),
-                ));
-            }
-            map
+                )
+            })
+            .collect();
+
+            let mut map = TextureMap::new(256, 256);
+            map.add(tex_list, true).unwrap();
+            (
+                map.get_uv(&"green".to_string()).is_some(),
+                read_frame(&map.frame),
+            )
+        });
+        assert!(has_uv);
+        assert_golden("texture_map", &image, 1);
+    }
+
+    #[test]
+    fn equirect_faces() {
+        //上半红下半蓝的全景图
+        let image = Rgba32FImage::from_fn(64, 32, |_, y| {
+            Rgba(if y < 16 { [1f32, 0f32, 0f32, 1f32] } else { [0f32, 0f32, 1f32, 1f32] })
+        });
+        let center = |face| {
+            let pixels = equirect_face(&image, face, 8);
+            let at = (4 * 8 + 4) * 4;
+            [pixels[at], pixels[at + 1], pixels[at + 2]]
         };
-        unsafe {
-            gl::Enable(gl::TEXTURE_2D);
-        }
-        let mut what = Vec::new();
-        for (str, tex) in tex_list.iter() {
-            what.push((str.to_string(), tex.as_ref()));
-        }
-        // let he = "hehe".to_string();
-        // let what = vec![(&he, &tex)];
-
-        println!("load map...");
-        let mut map = TextureMap::new(4000, 4000);
-        map.add(tex_list, true).unwrap();
-        // map.frame
-        //     .texture
-        //     .as_ref()
-        //     .unwrap()
-        //     .get_image()
-        //     .save(Path::new("test map.png"))
-        //     .unwrap();
-        window.view_port();
-        window.window.show();
-
-        VAO_MUT.bind_pointer(
-            VERTEX_MUT.deref(),
-            VertexArrayAttribPointerGen::new::<f32>(0, 2),
+        assert_eq!(center(CubeFace::PositiveY), [1f32, 0f32, 0f32]);
+        assert_eq!(center(CubeFace::NegativeY), [0f32, 0f32, 1f32]);
+        assert_eq!(CubeFace::NegativeZ.as_gl(), gl::TEXTURE_CUBE_MAP_NEGATIVE_Z);
+    }
+
+    #[test]
+    fn luma_formats() {
+        use image::{DynamicImage, GrayAlphaImage, GrayImage, ImageBuffer, Luma};
+
+        use crate::gl_unit::{
+            caps::{Caps, parse_version, set_caps},
+            define::TextureType,
+            error::GlError,
+        };
+
+        let luma16 = DynamicImage::ImageLuma16(ImageBuffer::<Luma<u16>, _>::new(2, 2));
+        set_caps(Caps::desktop(3, 3));
+        let type_of = |image: &DynamicImage| super::image_data(image).map(|(type_, _)| type_);
+        assert_eq!(
+            type_of(&DynamicImage::ImageLuma8(GrayImage::new(2, 2))),
+            Ok(TextureType::RED8)
         );
-        VAO_MUT.bind_pointer(
-            TEX_VERTEX_MUT.deref(),
-            VertexArrayAttribPointerGen::new::<f32>(1, 2),
+        assert_eq!(
+            type_of(&DynamicImage::ImageLumaA8(GrayAlphaImage::new(2, 2))),
+            Ok(TextureType::RG8)
         );
+        assert_eq!(type_of(&luma16), Ok(TextureType::RED16));
+        //es没有16位unorm
+        set_caps(parse_version("OpenGL ES 3.0"));
+        assert!(matches!(type_of(&luma16), Err(GlError::Unsupported(_))));
+        assert_eq!(
+            type_of(&DynamicImage::ImageLuma8(GrayImage::new(2, 2))),
+            Ok(TextureType::RED8)
+        );
+    }
 
-        while !window.update() {
-            context.draw_option(&mut window, |_, _| {
-                let program = &PROGRAM2D_ONE;
-                program.bind();
-                program.put_matrix_name(Mat4::IDENTITY, "project_mat");
-                program.put_matrix_name(Mat4::IDENTITY, "model_mat");
-                program.put_texture(0, program.get_uniform("image"));
-                map.get_tex().bind_unit(0);
-
-                VERTEX_MUT.sub_data(&[-1f32, 1f32, 1f32, 1f32, 1f32, -1f32, -1f32, -1f32], 0);
-
-                let index = map.get_uv(&"GINO".to_string()).unwrap();
-                TEX_VERTEX_MUT.sub_data(
-                    &[
-                        index.x,
-                        index.y + index.h,
-                        index.x + index.w,
-                        index.y + index.h,
-                        index.x + index.w,
-                        index.y,
-                        index.x,
-                        index.y,
-                    ],
-                    0,
-                );
-                VAO_MUT.draw_arrays(DrawMode::Quads, 0, 4);
-            });
+    #[cfg(feature = "mock-gl")]
+    #[test]
+    fn invalid_layers_no_texture() {
+        use image::{DynamicImage, GrayAlphaImage, RgbaImage};
+
+        use crate::gl_unit::{
+            define::TextureParm,
+            mock,
+            texture::{Texture2DArray, TextureCube},
+        };
+
+        mock::load();
+        let face = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+        let wrong = DynamicImage::ImageLumaA8(GrayAlphaImage::new(4, 4));
+        let mut faces = [&face; 6];
+        faces[5] = &wrong;
+        assert!(TextureCube::load_faces(faces, TextureParm::new()).is_err());
+        let small = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+        assert!(Texture2DArray::load_images(&[face.clone(), small], TextureParm::new()).is_err());
+        //检查失败时还没建纹理
+        assert_eq!(mock::count("GenTextures"), 0);
+    }
+
+    #[cfg(feature = "mock-gl")]
+    #[test]
+    fn norm16_on_es_no_texture() {
+        use crate::gl_unit::{
+            caps::{parse_version, set_caps},
+            define::{TextureParm, TextureType},
+            error::GlError,
+            mock,
+            texture::{Texture1D, Texture2D, Texture2DArray, Texture3D, TextureCube},
+        };
+
+        mock::load();
+        set_caps(parse_version("OpenGL ES 3.0"));
+        let parm = TextureParm::new;
+        let unsupported =
+            |result: Result<(), GlError>| matches!(result, Err(GlError::Unsupported(_)));
+        assert!(unsupported(
+            Texture2D::try_with_size(4, 4, TextureType::RED16, parm()).map(|_| ())
+        ));
+        assert!(unsupported(
+            Texture1D::try_load::<u16>(None, TextureType::RG16, 4, parm()).map(|_| ())
+        ));
+        assert!(unsupported(
+            TextureCube::try_new(4, TextureType::RGBA16, parm()).map(|_| ())
+        ));
+        assert!(unsupported(
+            Texture2DArray::try_new(4, 4, 2, TextureType::RED16, parm()).map(|_| ())
+        ));
+        assert!(unsupported(
+            Texture3D::try_with_size(4, 4, 4, TextureType::RED16, parm()).map(|_| ())
+        ));
+        assert_eq!(mock::count("GenTextures"), 0);
+    }
+
+    #[cfg(not(feature = "mock-gl"))]
+    fn level_width(target: gl::types::GLenum, level: i32) -> i32 {
+        let mut width = 0;
+        unsafe {
+            gl::GetTexLevelParameteriv(target, level, gl::TEXTURE_WIDTH, &mut width);
         }
+        width
+    }
+
+    #[cfg(not(feature = "mock-gl"))]
+    #[test]
+    fn empty_mipmap_levels() {
+        use crate::gl_unit::{
+            define::{TextureParm, TextureType},
+            golden::with_shared_context,
+            texture::{
+                CubeFace, Texture, Texture1D, Texture2D, Texture2DArray, Texture3D, TextureCube,
+                TextureWrapper,
+            },
+        };
+
+        with_shared_context(|_| {
+            //16x8一共5级,最后一级1x1
+            let texture = TextureWrapper(Texture2D::with_size(
+                16,
+                8,
+                TextureType::RGBA8,
+                TextureParm::mipmap(),
+            ));
+            texture.send_to_texture();
+            assert_eq!(level_width(gl::TEXTURE_2D, 3), 2);
+            assert_eq!(level_width(gl::TEXTURE_2D, 4), 1);
+            assert_eq!(level_width(gl::TEXTURE_2D, 5), 0);
+            //不要mip的只有第0级
+            let plain = TextureWrapper(Texture2D::with_size(
+                16,
+                8,
+                TextureType::RGBA8,
+                TextureParm::new(),
+            ));
+            plain.send_to_texture();
+            assert_eq!(level_width(gl::TEXTURE_2D, 1), 0);
+
+            let volume = TextureWrapper(Texture3D::with_size(
+                4,
+                4,
+                8,
+                TextureType::RGBA8,
+                TextureParm::mipmap(),
+            ));
+            volume.send_to_texture();
+            assert_eq!(level_width(gl::TEXTURE_3D, 2), 1);
+            assert_eq!(level_width(gl::TEXTURE_3D, 4), 0);
+
+            let cube = TextureWrapper(TextureCube::new(
+                8,
+                TextureType::RGBA8,
+                TextureParm::mipmap(),
+            ));
+            cube.send_to_texture();
+            for face in CubeFace::ALL {
+                assert_eq!(level_width(face.as_gl(), 3), 1);
+            }
+
+            //层数不减半,只有宽高有mip
+            let array = TextureWrapper(Texture2DArray::new(
+                4,
+                2,
+                6,
+                TextureType::RGBA8,
+                TextureParm::mipmap(),
+            ));
+            array.send_to_texture();
+            assert_eq!(level_width(gl::TEXTURE_2D_ARRAY, 2), 1);
+            assert_eq!(level_width(gl::TEXTURE_2D_ARRAY, 3), 0);
+
+            let line = TextureWrapper(Texture1D::load::<u8>(
+                None,
+                TextureType::RED8,
+                8,
+                TextureParm::mipmap(),
+            ));
+            line.send_to_texture();
+            assert_eq!(level_width(gl::TEXTURE_1D, 3), 1);
+            //带数据的直接GenerateMipmap
+            let line = TextureWrapper(Texture1D::load(
+                Some(&[255u8; 8]),
+                TextureType::RED8,
+                8,
+                TextureParm::mipmap(),
+            ));
+            line.send_to_texture();
+            assert_eq!(level_width(gl::TEXTURE_1D, 3), 1);
+        });
+    }
+
+    #[cfg(not(feature = "mock-gl"))]
+    #[test]
+    fn anisotropy_clamp() {
+        use crate::gl_unit::{
+            caps::caps,
+            define::{TextureParm, TextureType},
+            golden::with_shared_context,
+            texture::{TEXTURE_MAX_ANISOTROPY, Texture, Texture2D, TextureWrapper},
+        };
+
+        with_shared_context(|_| {
+            let texture = TextureWrapper(Texture2D::with_size(
+                4,
+                4,
+                TextureType::RGBA8,
+                TextureParm::mipmap().anisotropy(1e6),
+            ));
+            texture.send_to_texture();
+            let max = caps().max_anisotropy;
+            let mut value = 0f32;
+            unsafe {
+                gl::GetTexParameterfv(gl::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY, &mut value);
+            }
+            //驱动不支持时不设,读回来是默认的1
+            assert_eq!(value, if max > 1f32 { max } else { 1f32 });
+        });
     }
 }
 
@@ -356,7 +532,11 @@ impl<T: Texture> Deref for TextureWrapper<T> {
 }
 
 pub trait Texture {
+    fn id(&self) -> GLuint;
     fn send_to_texture(&self);
+    fn set_label(&self, label: &str) {
+        debug::set_label(gl::TEXTURE, self.id(), label);
+    }
     fn bind_unit(&self, id: u32) {
         active_texture_unit(define::Texture::Unit(id));
         self.send_to_texture();
@@ -370,8 +550,11 @@ pub struct Texture1D {
     pub size: u32,
 }
 impl Texture for Texture1D {
+    fn id(&self) -> GLuint {
+        self.texture
+    }
     fn send_to_texture(&self) {
-        unsafe { gl::BindTexture(gl::TEXTURE_1D, self.texture) }
+        state::bind_texture(gl::TEXTURE_1D, self.texture);
     }
 
     fn delete(&self) {
@@ -379,6 +562,7 @@ impl Texture for Texture1D {
         unsafe {
             gl::DeleteTextures(1, &self.texture as *const GLuint);
         }
+        state::forget_texture(self.texture);
     }
 
     fn send_date<T>(&self, type_: TextureType, x: i32, y: i32, w: i32, h: i32, date: &[T]) {
@@ -401,38 +585,64 @@ impl Texture for Texture1D {
 }
 impl Texture1D {
     pub fn new_size(size: u32) -> Self {
-        Self::new::<u8>(null(), TextureType::RED8, size, TextureParm::new())
+        Self::load::<u8>(None, TextureType::RED8, size, TextureParm::new())
     }
 
     pub fn load<T>(raw: Option<&[T]>, mode: TextureType, size: u32, parm: TextureParm) -> Self {
-        if let Some(raw) = raw {
-            Self::new(raw.as_ptr(), mode, size, parm)
-        } else {
-            Self::new(null::<T>(), mode, size, parm)
-        }
+        Self::try_load(raw, mode, size, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load<T>(
+        raw: Option<&[T]>,
+        mode: TextureType,
+        size: u32,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        Self::try_new(raw.map_or(null(), |raw| raw.as_ptr()), mode, size, parm)
     }
 
-    fn new<T>(raw: *const T, mode: TextureType, size: u32, parm: TextureParm) -> Self {
+    fn try_new<T>(
+        raw: *const T,
+        mode: TextureType,
+        size: u32,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        let internal = internal_format(mode)?;
         let mut id: u32 = 0;
         unsafe {
             gl::GenTextures(1, &mut id as *mut u32);
-            gl::BindTexture(gl::TEXTURE_1D, id);
+            state::bind_texture(gl::TEXTURE_1D, id);
             texture_parm(gl::TEXTURE_1D, parm);
 
-            //(internal fmt,type)
+            //(fmt,type)
             let type_: (GLenum, GLenum) = mode.as_gl();
             gl::TexImage1D(
                 gl::TEXTURE_1D,
                 0,
-                type_.0 as i32,
+                internal,
                 size as i32,
                 0,
                 type_.0,
                 type_.1,
                 raw as *const c_void,
             );
+            if !raw.is_null() {
+                generate_mipmaps(gl::TEXTURE_1D, &parm);
+            } else if parm.generate_mipmaps {
+                for level in 1..mip_levels([size]) {
+                    gl::TexImage1D(
+                        gl::TEXTURE_1D,
+                        level,
+                        internal,
+                        mip_side(size, level),
+                        0,
+                        type_.0,
+                        type_.1,
+                        null(),
+                    );
+                }
+            }
         }
-        Self { texture: id, size }
+        Ok(Self { texture: id, size })
     }
 }
 
@@ -449,10 +659,11 @@ pub struct Texture2D {
 }
 
 impl Texture for Texture2D {
+    fn id(&self) -> GLuint {
+        self.texture
+    }
     fn send_to_texture(&self) {
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.texture);
-        }
+        state::bind_texture(gl::TEXTURE_2D, self.texture);
     }
 
     fn send_date<T>(&self, type_: TextureType, x: i32, y: i32, w: i32, h: i32, date: &[T]) {
@@ -478,10 +689,14 @@ impl Texture for Texture2D {
         unsafe {
             gl::DeleteTextures(1, &self.texture as *const GLuint);
         }
+        state::forget_texture(self.texture);
     }
 }
 impl Texture2D {
     pub fn get_image(&self) -> DynamicImage {
+        if !caps().has_get_tex_image() {
+            return self.read_by_framebuffer();
+        }
         self.send_to_texture();
         unsafe {
             let mut image_date: Vec<u8> = Vec::with_capacity((self.w * self.h * 4) as usize);
@@ -499,6 +714,50 @@ impl Texture2D {
         }
     }
 
+    //es没有glGetTexImage,挂到临时fbo上ReadPixels;只动read绑定,走状态缓存
+    fn read_by_framebuffer(&self) -> DynamicImage {
+        let mut image_date: Vec<u8> = vec![0; (self.w * self.h * 4) as usize];
+        let last = state::current().read_fbo.unwrap_or_else(|| {
+            let mut last = 0;
+            unsafe {
+                gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut last);
+            }
+            last as GLuint
+        });
+        unsafe {
+            let mut fbo = 0;
+            gl::GenFramebuffers(1, &mut fbo);
+            state::bind_framebuffer(gl::READ_FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(
+                gl::READ_FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                self.texture,
+                0,
+            );
+            //外面可能改过对齐,读完换回去
+            let mut align = 0;
+            gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut align);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                self.w as GLsizei,
+                self.h as GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                image_date.as_mut_ptr() as *mut c_void,
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, align);
+            state::bind_framebuffer(gl::READ_FRAMEBUFFER, last);
+            gl::DeleteFramebuffers(1, &fbo);
+            state::forget_framebuffer(fbo);
+        }
+        let image_buffer: ImageBuffer<Rgba<u8>, _> =
+            ImageBuffer::from_raw(self.w, self.h, image_date).unwrap();
+        DynamicImage::ImageRgba8(image_buffer)
+    }
+
     pub unsafe fn new<T>(
         raw: *const T,
         mode: TextureType,
@@ -506,18 +765,28 @@ impl Texture2D {
         h: u32,
         parm: TextureParm,
     ) -> Self {
+        unsafe { Self::try_new(raw, mode, w, h, parm) }.unwrap_or_else(|err| panic!("{}", err))
+    }
+    unsafe fn try_new<T>(
+        raw: *const T,
+        mode: TextureType,
+        w: u32,
+        h: u32,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        let internal = internal_format(mode)?;
         let mut id: u32 = 0;
         unsafe {
             gl::GenTextures(1, &mut id as *mut u32);
-            gl::BindTexture(gl::TEXTURE_2D, id);
+            state::bind_texture(gl::TEXTURE_2D, id);
 
             texture_parm(gl::TEXTURE_2D, parm);
-            //(internal fmt,type)
+            //(fmt,type),internal fmt用sized的,不然32位会被当成8位存
             let type_: (GLenum, GLenum) = mode.as_gl();
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                type_.0 as GLint,
+                internal,
                 w as GLsizei,
                 h as GLsizei,
                 0,
@@ -525,8 +794,25 @@ impl Texture2D {
                 type_.1,
                 raw as *const c_void,
             );
+            if !raw.is_null() {
+                generate_mipmaps(gl::TEXTURE_2D, &parm);
+            } else if parm.generate_mipmaps {
+                for level in 1..mip_levels([w, h]) {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        level,
+                        internal,
+                        mip_side(w, level),
+                        mip_side(h, level),
+                        0,
+                        type_.0,
+                        type_.1,
+                        null(),
+                    );
+                }
+            }
         }
-        Self { texture: id, w, h }
+        Ok(Self { texture: id, w, h })
     }
 
     pub fn load<T>(
@@ -536,111 +822,752 @@ impl Texture2D {
         h: u32,
         parm: TextureParm,
     ) -> Self {
-        if let Some(raw) = raw {
-            unsafe {
-                return Self::new(raw.as_ptr(), mode, w, h, parm);
-            }
-        }
-        unsafe { Self::new(null::<T>(), mode, w, h, parm) }
+        Self::try_load(raw, mode, w, h, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load<T>(
+        raw: Option<&[T]>,
+        mode: TextureType,
+        w: u32,
+        h: u32,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        unsafe { Self::try_new(raw.map_or(null(), |raw| raw.as_ptr()), mode, w, h, parm) }
     }
     pub fn load_path(path: &Path, parm: TextureParm) -> Self {
-        Self::load_image(&image::open(path).unwrap(), parm)
+        Self::try_load_path(path, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load_path(path: &Path, parm: TextureParm) -> GlResult<Self> {
+        let image =
+            image::open(path).map_err(|err| GlError::Asset(format!("{:?}:{}", path, err)))?;
+        Self::try_load_image(&image, parm)
     }
     pub fn load_image(image: &DynamicImage, parm: TextureParm) -> Self {
-        match image {
-            DynamicImage::ImageLuma8(_) => {
-                panic!("Texture2D Luma8 not support")
-            }
-            DynamicImage::ImageLumaA8(_) => {
-                panic!("Texture2D LumaA8 not support")
+        Self::try_load_image(image, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load_image(image: &DynamicImage, parm: TextureParm) -> GlResult<Self> {
+        let (type_, bytes) = image_data(image)?;
+        Self::try_load(Some(bytes), type_, image.width(), image.height(), parm)
+    }
+
+    pub fn vec2(&self) -> Vec2 {
+        vec2(self.w as f32, self.h as f32)
+    }
+    //改了内容(比如画进fbo)之后重新生成
+    pub fn generate_mipmaps(&self) {
+        self.send_to_texture();
+        unsafe {
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+    }
+    pub fn with_size(w: u32, h: u32, type_: TextureType, fmt: TextureParm) -> Self {
+        Self::load::<u8>(None, type_, w, h, fmt)
+    }
+    pub fn try_with_size(w: u32, h: u32, type_: TextureType, fmt: TextureParm) -> GlResult<Self> {
+        Self::try_load::<u8>(None, type_, w, h, fmt)
+    }
+    //深度纹理,挂到fbo上之后可以采样
+    pub fn depth(w: u32, h: u32, format: DepthFormat, parm: TextureParm) -> Self {
+        Self::with_size(w, h, format.texture_type(), parm)
+    }
+    //压缩纹理,mip用文件里带的,不能GenerateMipmap
+    pub fn load_compressed(image: &CompressedImage, parm: TextureParm) -> Self {
+        Self::try_load_compressed(image, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load_compressed(image: &CompressedImage, parm: TextureParm) -> GlResult<Self> {
+        let format = image.format;
+        for (level, data) in image.levels.iter().enumerate() {
+            let level = level as u32;
+            let size = format.level_size(level_side(image.w, level), level_side(image.h, level))?;
+            if data.len() != size {
+                return Err(GlError::Asset(format!(
+                    "{:?} level {} has {} bytes, expected {}",
+                    format,
+                    level,
+                    data.len(),
+                    size
+                )));
             }
-            DynamicImage::ImageLuma16(_) => {
-                panic!("Texture2D Luma16 not support")
+        }
+        let mut id: u32 = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id as *mut u32);
+            state::bind_texture(gl::TEXTURE_2D, id);
+            texture_parm(gl::TEXTURE_2D, parm);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAX_LEVEL,
+                image.levels.len().saturating_sub(1) as GLint,
+            );
+            for (level, data) in image.levels.iter().enumerate() {
+                gl::CompressedTexImage2D(
+                    gl::TEXTURE_2D,
+                    level as GLint,
+                    format.as_gl(),
+                    mip_side(image.w, level as GLint),
+                    mip_side(image.h, level as GLint),
+                    0,
+                    data.len() as GLsizei,
+                    data.as_ptr() as *const c_void,
+                );
             }
-            DynamicImage::ImageLumaA16(_) => {
-                panic!("Texture2D LumaA16 not support")
+        }
+        Ok(Self {
+            texture: id,
+            w: image.w,
+            h: image.h,
+        })
+    }
+
+    pub fn unbind() {
+        state::bind_texture(gl::TEXTURE_2D, 0);
+    }
+    //compute shader里的image2D,format要用sized格式(gl::RGBA8,gl::RGBA32F...)
+    pub fn bind_image(&self, unit: u32, access: ImageAccess, format: GLenum) {
+        unsafe {
+            gl::BindImageTexture(unit, self.texture, 0, gl::FALSE, 0, access.as_gl(), format);
+        }
+    }
+}
+
+//图片对应的上传格式和像素数据
+fn image_data(image: &DynamicImage) -> GlResult<(TextureType, &[u8])> {
+    let data = match image {
+        DynamicImage::ImageRgb8(image_buffer) => (TextureType::RGB8, image_buffer.as_bytes()),
+        DynamicImage::ImageRgba8(image_buffer) => (TextureType::RGBA8, image_buffer.as_bytes()),
+        DynamicImage::ImageRgb16(image_buffer) => (TextureType::RGB16, image_buffer.as_bytes()),
+        DynamicImage::ImageRgba16(image_buffer) => (TextureType::RGBA16, image_buffer.as_bytes()),
+        DynamicImage::ImageRgb32F(image_buffer) => (TextureType::RGB32, image_buffer.as_bytes()),
+        DynamicImage::ImageRgba32F(image_buffer) => {
+            (TextureType::RGBA32, image_buffer.as_bytes())
+        }
+        DynamicImage::ImageLuma8(image_buffer) => (TextureType::RED8, image_buffer.as_bytes()),
+        DynamicImage::ImageLumaA8(image_buffer) => (TextureType::RG8, image_buffer.as_bytes()),
+        DynamicImage::ImageLuma16(image_buffer) => (TextureType::RED16, image_buffer.as_bytes()),
+        DynamicImage::ImageLumaA16(image_buffer) => (TextureType::RG16, image_buffer.as_bytes()),
+        other => {
+            return Err(GlError::TextureFormat(format!("{:?}", other.color())));
+        }
+    };
+    //es上16位的格式建不出来,在建纹理之前就报
+    data.0.sized()?;
+    Ok(data)
+}
+
+//建纹理之前先取,格式不支持(es上的16位unorm)就不会漏掉texture id
+fn internal_format(type_: TextureType) -> GlResult<GLint> {
+    Ok(type_.sized()? as GLint)
+}
+
+//只能挂到fbo上画,或者shader里texelFetch;不能上传数据
+pub struct Texture2DMultisample {
+    pub texture: GLuint,
+    pub w: u32,
+    pub h: u32,
+    pub samples: u32,
+}
+impl Texture for Texture2DMultisample {
+    fn id(&self) -> GLuint {
+        self.texture
+    }
+    fn send_to_texture(&self) {
+        state::bind_texture(gl::TEXTURE_2D_MULTISAMPLE, self.texture);
+    }
+
+    fn send_date<T>(&self, _type: TextureType, _x: i32, _y: i32, _w: i32, _h: i32, _date: &[T]) {
+        eprintln!("[texture]multisample texture can't upload data");
+    }
+
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture as *const GLuint);
+        }
+        state::forget_texture(self.texture);
+    }
+}
+impl Texture2DMultisample {
+    //format要用sized格式,见TextureType::sized
+    pub fn new(format: GLenum, w: u32, h: u32, samples: u32) -> Self {
+        let mut id: u32 = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id as *mut u32);
+            state::bind_texture(gl::TEXTURE_2D_MULTISAMPLE, id);
+            //es 3.1只有immutable的TexStorage
+            if caps().es {
+                gl::TexStorage2DMultisample(
+                    gl::TEXTURE_2D_MULTISAMPLE,
+                    samples as GLsizei,
+                    format,
+                    w as GLsizei,
+                    h as GLsizei,
+                    gl::TRUE,
+                );
+            } else {
+                gl::TexImage2DMultisample(
+                    gl::TEXTURE_2D_MULTISAMPLE,
+                    samples as GLsizei,
+                    format,
+                    w as GLsizei,
+                    h as GLsizei,
+                    gl::TRUE,
+                );
             }
+        }
+        Self {
+            texture: id,
+            w,
+            h,
+            samples,
+        }
+    }
+}
 
-            DynamicImage::ImageRgb8(image_buffer) => Self::load(
-                Some(image_buffer.as_bytes()),
-                TextureType::RGB8,
-                image_buffer.width(),
-                image_buffer.height(),
-                parm,
-            ),
-            DynamicImage::ImageRgba8(image_buffer) => Self::load(
-                Some(image_buffer.as_bytes()),
-                TextureType::RGBA8,
-                image_buffer.width(),
-                image_buffer.height(),
-                parm,
-            ),
-            DynamicImage::ImageRgb16(image_buffer) => Self::load(
-                Some(image_buffer.as_bytes()),
-                TextureType::RGB16,
-                image_buffer.width(),
-                image_buffer.height(),
-                parm,
-            ),
-            DynamicImage::ImageRgba16(image_buffer) => Self::load(
-                Some(image_buffer.as_bytes()),
-                TextureType::RGBA16,
-                image_buffer.width(),
-                image_buffer.height(),
-                parm,
-            ),
-            DynamicImage::ImageRgb32F(image_buffer) => Self::load(
-                Some(image_buffer.as_bytes()),
-                TextureType::RGB32,
-                image_buffer.width(),
-                image_buffer.height(),
-                parm,
-            ),
-            DynamicImage::ImageRgba32F(image_buffer) => Self::load(
-                Some(image_buffer.as_bytes()),
-                TextureType::RGBA32,
-                image_buffer.width(),
-                image_buffer.height(),
-                parm,
-            ),
-            _ => {
-                panic!("Texture2D load error,what is that?!");
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+    pub const fn as_gl(self) -> GLenum {
+        gl::TEXTURE_CUBE_MAP_POSITIVE_X + self as GLenum
+    }
+    //面上(s,t)∈[-1,1]对应的方向,t向下,和gl规范里的表一致
+    pub fn direction(self, s: f32, t: f32) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => vec3(1f32, -t, -s),
+            CubeFace::NegativeX => vec3(-1f32, -t, s),
+            CubeFace::PositiveY => vec3(s, 1f32, t),
+            CubeFace::NegativeY => vec3(s, -1f32, -t),
+            CubeFace::PositiveZ => vec3(s, -t, 1f32),
+            CubeFace::NegativeZ => vec3(-s, -t, -1f32),
+        }
+    }
+}
+
+//天空盒;图片第一行是面的上边
+pub struct TextureCube {
+    pub texture: GLuint,
+    pub size: u32,
+}
+impl Texture for TextureCube {
+    fn id(&self) -> GLuint {
+        self.texture
+    }
+    fn send_to_texture(&self) {
+        state::bind_texture(gl::TEXTURE_CUBE_MAP, self.texture);
+    }
+    //写到+X面,其他面用send_face
+    fn send_date<T>(&self, type_: TextureType, x: i32, y: i32, w: i32, h: i32, date: &[T]) {
+        self.send_face(CubeFace::PositiveX, type_, x, y, w, h, date);
+    }
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture as *const GLuint);
+        }
+        state::forget_texture(self.texture);
+    }
+}
+impl TextureCube {
+    pub fn new(size: u32, type_: TextureType, parm: TextureParm) -> Self {
+        Self::try_new(size, type_, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_new(size: u32, type_: TextureType, parm: TextureParm) -> GlResult<Self> {
+        let internal = internal_format(type_)?;
+        let mut id: u32 = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id as *mut u32);
+            state::bind_texture(gl::TEXTURE_CUBE_MAP, id);
+            texture_parm(gl::TEXTURE_CUBE_MAP, parm);
+            let (format, real) = type_.as_gl();
+            //面的内容之后才传,要mip就先把每一级都分配出来
+            let levels = if parm.generate_mipmaps {
+                mip_levels([size])
+            } else {
+                1
+            };
+            for level in 0..levels {
+                for face in CubeFace::ALL {
+                    gl::TexImage2D(
+                        face.as_gl(),
+                        level,
+                        internal,
+                        mip_side(size, level),
+                        mip_side(size, level),
+                        0,
+                        format,
+                        real,
+                        null(),
+                    );
+                }
             }
         }
+        Ok(Self { texture: id, size })
     }
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_face<T>(
+        &self,
+        face: CubeFace,
+        type_: TextureType,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        date: &[T],
+    ) {
+        self.send_to_texture();
+        let (format, real) = type_.as_gl();
+        unsafe {
+            gl::TexSubImage2D(
+                face.as_gl(),
+                0,
+                x,
+                y,
+                w,
+                h,
+                format,
+                real,
+                date.as_ptr() as *const c_void,
+            );
+        }
+    }
+    //顺序同CubeFace::ALL(+X,-X,+Y,-Y,+Z,-Z),要求都是一样大的正方形
+    pub fn load_faces(images: [&DynamicImage; 6], parm: TextureParm) -> GlResult<Self> {
+        let size = images[0].width();
+        let (type_, _) = image_data(images[0])?;
+        if images
+            .iter()
+            .any(|image| image.width() != size || image.height() != size)
+        {
+            return Err(GlError::TextureFormat(
+                "cube faces must be square and the same size".to_string(),
+            ));
+        }
+        //先把六个面都检查完再建纹理,出错不会漏掉texture id
+        let mut faces = Vec::with_capacity(6);
+        for image in images {
+            let (face_type, bytes) = image_data(image)?;
+            if face_type != type_ {
+                return Err(GlError::TextureFormat(format!("{:?}", image.color())));
+            }
+            faces.push(bytes);
+        }
+        let cube = Self::try_new(size, type_, parm)?;
+        for (face, bytes) in CubeFace::ALL.into_iter().zip(faces) {
+            let size = size as i32;
+            cube.send_face(face, type_, 0, 0, size, size, bytes);
+        }
+        generate_mipmaps(gl::TEXTURE_CUBE_MAP, &parm);
+        Ok(cube)
+    }
+    pub fn load_paths(paths: [&Path; 6], parm: TextureParm) -> GlResult<Self> {
+        let mut images = Vec::with_capacity(6);
+        for path in paths {
+            images.push(
+                image::open(path).map_err(|err| GlError::Asset(format!("{:?}:{}", path, err)))?,
+            );
+        }
+        let faces: [&DynamicImage; 6] = std::array::from_fn(|index| &images[index]);
+        Self::load_faces(faces, parm)
+    }
+    //经纬度展开的全景图(.hdr/.exr),在cpu上重采样成六个面
+    pub fn from_equirect(image: &DynamicImage, size: u32, parm: TextureParm) -> Self {
+        let image = image.to_rgba32f();
+        let cube = Self::new(size, TextureType::RGBA32, parm);
+        for face in CubeFace::ALL {
+            let pixels = equirect_face(&image, face, size);
+            let size = size as i32;
+            cube.send_face(face, TextureType::RGBA32, 0, 0, size, size, &pixels);
+        }
+        generate_mipmaps(gl::TEXTURE_CUBE_MAP, &parm);
+        cube
+    }
+    pub fn try_from_equirect_path(path: &Path, size: u32, parm: TextureParm) -> GlResult<Self> {
+        let image =
+            image::open(path).map_err(|err| GlError::Asset(format!("{:?}:{}", path, err)))?;
+        Ok(Self::from_equirect(&image, size, parm))
+    }
+}
 
-    pub fn vec2(&self) -> Vec2 {
-        vec2(self.w as f32, self.h as f32)
+//一个面size*size的rgba f32,按方向在全景图里双线性采样
+fn equirect_face(image: &Rgba32FImage, face: CubeFace, size: u32) -> Vec<f32> {
+    let (w, h) = (image.width() as f32, image.height() as f32);
+    let fetch = |x: i64, y: i64| {
+        let x = x.rem_euclid(image.width() as i64) as u32;
+        let y = y.clamp(0, image.height() as i64 - 1) as u32;
+        Vec4::from_array(image.get_pixel(x, y).0)
+    };
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let s = (x as f32 + 0.5) / size as f32 * 2f32 - 1f32;
+            let t = (y as f32 + 0.5) / size as f32 * 2f32 - 1f32;
+            let dir = face.direction(s, t).normalize();
+            let u = dir.z.atan2(dir.x) / TAU + 0.5f32;
+            let v = dir.y.clamp(-1f32, 1f32).acos() / PI;
+            let (px, py) = (u * w - 0.5f32, v * h - 0.5f32);
+            let (x0, y0) = (px.floor(), py.floor());
+            let (fx, fy) = (px - x0, py - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top = fetch(x0, y0).lerp(fetch(x0 + 1, y0), fx);
+            let bottom = fetch(x0, y0 + 1).lerp(fetch(x0 + 1, y0 + 1), fx);
+            pixels.extend_from_slice(&top.lerp(bottom, fy).to_array());
+        }
     }
-    pub fn with_size(w: u32, h: u32, type_: TextureType, fmt: TextureParm) -> Self {
-        Self::load::<u8>(None, type_, w, h, fmt)
+    pixels
+}
+
+//同样大小的一组图,shader里用sampler2DArray按层取,不会像图集那样串色
+pub struct Texture2DArray {
+    pub texture: GLuint,
+    pub w: u32,
+    pub h: u32,
+    pub layers: u32,
+}
+impl Texture for Texture2DArray {
+    fn id(&self) -> GLuint {
+        self.texture
+    }
+    fn send_to_texture(&self) {
+        state::bind_texture(gl::TEXTURE_2D_ARRAY, self.texture);
+    }
+    //写到第0层,其他层用send_layer
+    fn send_date<T>(&self, type_: TextureType, x: i32, y: i32, w: i32, h: i32, date: &[T]) {
+        self.send_layer(0, type_, x, y, w, h, date);
+    }
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture as *const GLuint);
+        }
+        state::forget_texture(self.texture);
+    }
+}
+impl Texture2DArray {
+    pub fn new(w: u32, h: u32, layers: u32, type_: TextureType, parm: TextureParm) -> Self {
+        Self::try_new(w, h, layers, type_, parm).unwrap_or_else(|err| panic!("{}", err))
     }
+    pub fn try_new(
+        w: u32,
+        h: u32,
+        layers: u32,
+        type_: TextureType,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        let internal = internal_format(type_)?;
+        let mut id: u32 = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id as *mut u32);
+            state::bind_texture(gl::TEXTURE_2D_ARRAY, id);
+            texture_parm(gl::TEXTURE_2D_ARRAY, parm);
+            let (format, real) = type_.as_gl();
+            //层数不跟着mip减半
+            let levels = if parm.generate_mipmaps {
+                mip_levels([w, h])
+            } else {
+                1
+            };
+            for level in 0..levels {
+                gl::TexImage3D(
+                    gl::TEXTURE_2D_ARRAY,
+                    level,
+                    internal,
+                    mip_side(w, level),
+                    mip_side(h, level),
+                    layers as GLsizei,
+                    0,
+                    format,
+                    real,
+                    null(),
+                );
+            }
+        }
+        Ok(Self {
+            texture: id,
+            w,
+            h,
+            layers,
+        })
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_layer<T>(
+        &self,
+        layer: u32,
+        type_: TextureType,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        date: &[T],
+    ) {
+        self.send_to_texture();
+        let (format, real) = type_.as_gl();
+        unsafe {
+            gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                x,
+                y,
+                layer as GLint,
+                w,
+                h,
+                1,
+                format,
+                real,
+                date.as_ptr() as *const c_void,
+            );
+        }
+    }
+    //每张图一层,大小和格式都要一样
+    pub fn load_images(images: &[DynamicImage], parm: TextureParm) -> GlResult<Self> {
+        let Some(first) = images.first() else {
+            return Err(GlError::TextureFormat("empty texture array".to_string()));
+        };
+        let (w, h) = (first.width(), first.height());
+        let (type_, _) = image_data(first)?;
+        //同load_faces,全部层检查完再建纹理
+        let mut layers = Vec::with_capacity(images.len());
+        for (layer, image) in images.iter().enumerate() {
+            let (layer_type, bytes) = image_data(image)?;
+            if layer_type != type_ || image.width() != w || image.height() != h {
+                return Err(GlError::TextureFormat(format!(
+                    "layer {} {}x{} {:?}",
+                    layer,
+                    image.width(),
+                    image.height(),
+                    image.color()
+                )));
+            }
+            layers.push(bytes);
+        }
+        let array = Self::try_new(w, h, images.len() as u32, type_, parm)?;
+        for (layer, bytes) in layers.into_iter().enumerate() {
+            array.send_layer(layer as u32, type_, 0, 0, w as i32, h as i32, bytes);
+        }
+        generate_mipmaps(gl::TEXTURE_2D_ARRAY, &parm);
+        Ok(array)
+    }
+}
 
-    pub fn unbind() {
+//体纹理,3d lut/体积雾
+pub struct Texture3D {
+    pub texture: GLuint,
+    pub w: u32,
+    pub h: u32,
+    pub d: u32,
+}
+impl Texture for Texture3D {
+    fn id(&self) -> GLuint {
+        self.texture
+    }
+    fn send_to_texture(&self) {
+        state::bind_texture(gl::TEXTURE_3D, self.texture);
+    }
+    //写到z=0那一片,整块用send_box
+    fn send_date<T>(&self, type_: TextureType, x: i32, y: i32, w: i32, h: i32, date: &[T]) {
+        self.send_box(type_, [x, y, 0], [w, h, 1], date);
+    }
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture as *const GLuint);
+        }
+        state::forget_texture(self.texture);
+    }
+}
+impl Texture3D {
+    pub fn load<T>(
+        raw: Option<&[T]>,
+        type_: TextureType,
+        w: u32,
+        h: u32,
+        d: u32,
+        parm: TextureParm,
+    ) -> Self {
+        Self::try_load(raw, type_, w, h, d, parm).unwrap_or_else(|err| panic!("{}", err))
+    }
+    pub fn try_load<T>(
+        raw: Option<&[T]>,
+        type_: TextureType,
+        w: u32,
+        h: u32,
+        d: u32,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        let internal = internal_format(type_)?;
+        let mut id: u32 = 0;
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::GenTextures(1, &mut id as *mut u32);
+            state::bind_texture(gl::TEXTURE_3D, id);
+            texture_parm(gl::TEXTURE_3D, parm);
+            let (format, real) = type_.as_gl();
+            gl::TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                internal,
+                w as GLsizei,
+                h as GLsizei,
+                d as GLsizei,
+                0,
+                format,
+                real,
+                raw.map_or(null(), |raw| raw.as_ptr() as *const c_void),
+            );
+            if raw.is_some() {
+                generate_mipmaps(gl::TEXTURE_3D, &parm);
+            } else if parm.generate_mipmaps {
+                for level in 1..mip_levels([w, h, d]) {
+                    gl::TexImage3D(
+                        gl::TEXTURE_3D,
+                        level,
+                        internal,
+                        mip_side(w, level),
+                        mip_side(h, level),
+                        mip_side(d, level),
+                        0,
+                        format,
+                        real,
+                        null(),
+                    );
+                }
+            }
+        }
+        Ok(Self {
+            texture: id,
+            w,
+            h,
+            d,
+        })
+    }
+    pub fn with_size(w: u32, h: u32, d: u32, type_: TextureType, parm: TextureParm) -> Self {
+        Self::load::<u8>(None, type_, w, h, d, parm)
+    }
+    pub fn try_with_size(
+        w: u32,
+        h: u32,
+        d: u32,
+        type_: TextureType,
+        parm: TextureParm,
+    ) -> GlResult<Self> {
+        Self::try_load::<u8>(None, type_, w, h, d, parm)
+    }
+    pub fn send_box<T>(&self, type_: TextureType, offset: [i32; 3], size: [i32; 3], date: &[T]) {
+        self.send_to_texture();
+        let (format, real) = type_.as_gl();
+        unsafe {
+            gl::TexSubImage3D(
+                gl::TEXTURE_3D,
+                0,
+                offset[0],
+                offset[1],
+                offset[2],
+                size[0],
+                size[1],
+                size[2],
+                format,
+                real,
+                date.as_ptr() as *const c_void,
+            );
         }
     }
 }
 
+//es 3.0/webgl2没有clamp to border,退回clamp to edge
+fn wrap_mode(mode: TextureWarpMode) -> GLenum {
+    match mode {
+        TextureWarpMode::ClampBorder if !caps().has_clamp_border() => {
+            TextureWarpMode::ClampEdge.as_gl()
+        }
+        mode => mode.as_gl(),
+    }
+}
+//gl 4.6/EXT_texture_filter_anisotropic,gl crate里没有
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+pub(crate) const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+//纹理和sampler对象共用的采样参数,int/float/color分别对应glXxxParameteri/f/fv
+pub(crate) fn sampler_parm(
+    parm: &TextureParm,
+    mut int: impl FnMut(GLenum, GLint),
+    mut float: impl FnMut(GLenum, f32),
+    color: impl FnOnce(GLenum, &[f32; 4]),
+) {
+    let caps = caps();
+    int(gl::TEXTURE_MIN_FILTER, parm.min_filter_gl() as GLint);
+    int(gl::TEXTURE_MAG_FILTER, parm.mag_filter.as_gl() as GLint);
+    int(gl::TEXTURE_WRAP_S, wrap_mode(parm.wrap_s) as GLint);
+    int(gl::TEXTURE_WRAP_T, wrap_mode(parm.wrap_t) as GLint);
+    int(gl::TEXTURE_WRAP_R, wrap_mode(parm.wrap_r) as GLint);
+    float(gl::TEXTURE_MIN_LOD, parm.min_lod);
+    float(gl::TEXTURE_MAX_LOD, parm.max_lod);
+    if !caps.es {
+        float(gl::TEXTURE_LOD_BIAS, parm.lod_bias);
+    }
+    if caps.max_anisotropy > 1f32 {
+        float(
+            TEXTURE_MAX_ANISOTROPY,
+            parm.anisotropy.clamp(1f32, caps.max_anisotropy),
+        );
+    }
+    if caps.has_clamp_border() {
+        color(gl::TEXTURE_BORDER_COLOR, &parm.border_color);
+    }
+}
+
 pub fn texture_parm(target: GLenum, parm: TextureParm) {
     unsafe {
-        gl::TexParameteri(
-            target,
-            gl::TEXTURE_MIN_FILTER,
-            parm.min_filter.as_gl() as GLint,
-        );
-        gl::TexParameteri(
-            target,
-            gl::TEXTURE_MAG_FILTER,
-            parm.mag_filter.as_gl() as GLint,
+        sampler_parm(
+            &parm,
+            |name, value| gl::TexParameteri(target, name, value),
+            |name, value| gl::TexParameterf(target, name, value),
+            |name, value| gl::TexParameterfv(target, name, value.as_ptr()),
         );
-        gl::TexParameteri(target, gl::TEXTURE_WRAP_S, parm.wrap_s.as_gl() as GLint);
-        gl::TexParameteri(target, gl::TEXTURE_WRAP_T, parm.wrap_t.as_gl() as GLint);
+        for (name, swizzle) in [
+            gl::TEXTURE_SWIZZLE_R,
+            gl::TEXTURE_SWIZZLE_G,
+            gl::TEXTURE_SWIZZLE_B,
+            gl::TEXTURE_SWIZZLE_A,
+        ]
+        .into_iter()
+        .zip(parm.swizzle)
+        {
+            gl::TexParameteri(target, name, swizzle.as_gl() as GLint);
+        }
         gl::PixelStorei(gl::UNPACK_ALIGNMENT, parm.once_load_size);
     }
 }
 
+//数据上传完之后调用;parm里没开generate_mipmaps时什么都不做
+fn generate_mipmaps(target: GLenum, parm: &TextureParm) {
+    if parm.generate_mipmaps {
+        unsafe {
+            gl::GenerateMipmap(target);
+        }
+    }
+}
+
+//空纹理没有内容可GenerateMipmap,开了mip过滤又缺级就是不完整纹理(采样全黑),
+//所以先把每一级都分配出来,内容等画进去之后再generate_mipmaps()
+fn mip_levels<const N: usize>(sides: [u32; N]) -> GLint {
+    let max = sides.into_iter().max().unwrap_or(1).max(1);
+    (u32::BITS - max.leading_zeros()) as GLint
+}
+//移位超过32位不会panic,多出来的级都是1
+fn level_side(side: u32, level: u32) -> u32 {
+    side.checked_shr(level).unwrap_or(0).max(1)
+}
+fn mip_side(side: u32, level: GLint) -> GLsizei {
+    level_side(side, level as u32) as GLsizei
+}
+
 // pub fn base_parm(target: GLenum) {
 //     unsafe {
 //         gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
@@ -664,7 +1591,5 @@ pub fn texture_parm(target: GLenum, parm: TextureParm) {
 //     }
 //}
 fn active_texture_unit(id: define::Texture) {
-    unsafe {
-        gl::ActiveTexture(id.as_gl());
-    }
+    state::active_texture(id.as_gl() - gl::TEXTURE0);
 }
@@ -0,0 +1,139 @@
+use gl::types::GLenum;
+
+use super::define::TypeGL;
+
+//交错顶点结构里的一个属性
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VertexAttrib {
+    pub index: u32,
+    //分量个数,1~4
+    pub len: i32,
+    pub type_: GLenum,
+    //整数归一化到0~1/-1~1
+    pub normalized: bool,
+    //走glVertexAttribIPointer,shader里是int/uint/ivec/uvec
+    pub integer: bool,
+    //在结构体里的字节偏移
+    pub offset: usize,
+}
+
+//一个顶点的内存布局,用vertex_layout!生成
+pub trait Vertex: TypeGL + Copy {
+    const ATTRIBS: &'static [VertexAttrib];
+}
+
+//能当顶点属性的字段类型
+pub trait VertexField {
+    const LEN: i32;
+    const TYPE: GLenum;
+}
+macro_rules! vertex_field {
+    ($($type:ty:$len:literal:$gl:path),*) => {
+        $(
+            impl VertexField for $type {
+                const LEN: i32 = $len;
+                const TYPE: GLenum = $gl;
+            }
+        )*
+    };
+}
+vertex_field! {
+    f32:1:gl::FLOAT,
+    i32:1:gl::INT,
+    u32:1:gl::UNSIGNED_INT,
+    i16:1:gl::SHORT,
+    u16:1:gl::UNSIGNED_SHORT,
+    i8:1:gl::BYTE,
+    u8:1:gl::UNSIGNED_BYTE,
+    glam::Vec2:2:gl::FLOAT,
+    glam::Vec3:3:gl::FLOAT,
+    glam::Vec4:4:gl::FLOAT,
+    glam::IVec4:4:gl::INT,
+    glam::UVec4:4:gl::UNSIGNED_INT
+}
+impl<T: VertexField, const N: usize> VertexField for [T; N] {
+    const LEN: i32 = T::LEN * N as i32;
+    const TYPE: GLenum = T::TYPE;
+}
+
+//生成repr(C)的顶点结构和它的Vertex实现
+//字段写成 `名字: 类型 = location`,后面可以跟 `as int`(整数属性) 或 `as norm`(归一化)
+#[macro_export]
+macro_rules! vertex_layout {
+    (@flag) => {
+        (false, false)
+    };
+    (@flag int) => {
+        (false, true)
+    };
+    (@flag norm) => {
+        (true, false)
+    };
+    ($(#[$meta:meta])* $vis:vis struct $name:ident {
+        $($field_vis:vis $field:ident: $type:ty = $index:literal $(as $flag:ident)?),* $(,)?
+    }) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $name {
+            $($field_vis $field: $type,)*
+        }
+        impl $crate::gl_unit::vertex::Vertex for $name {
+            const ATTRIBS: &'static [$crate::gl_unit::vertex::VertexAttrib] = &[
+                $(
+                    $crate::gl_unit::vertex::VertexAttrib {
+                        index: $index,
+                        len: <$type as $crate::gl_unit::vertex::VertexField>::LEN,
+                        type_: <$type as $crate::gl_unit::vertex::VertexField>::TYPE,
+                        normalized: $crate::vertex_layout!(@flag $($flag)?).0,
+                        integer: $crate::vertex_layout!(@flag $($flag)?).1,
+                        offset: ::std::mem::offset_of!($name, $field),
+                    },
+                )*
+            ];
+        }
+        //结构体没有单一的gl类型,只是为了能放进BufferConst
+        impl $crate::gl_unit::define::TypeGL for $name {
+            fn as_gl() -> $crate::gl::types::GLenum {
+                $crate::gl::FLOAT
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Vertex, VertexAttrib};
+
+    crate::vertex_layout! {
+        #[derive(Clone, Copy)]
+        struct SkinVertex {
+            pos: [f32; 3] = 0,
+            color: [u8; 4] = 2 as norm,
+            joint: [u16; 4] = 3 as int,
+            weight: [f32; 4] = 4,
+        }
+    }
+
+    #[test]
+    fn layout_offsets() {
+        assert_eq!(size_of::<SkinVertex>(), 12 + 4 + 8 + 16);
+        let attribs = SkinVertex::ATTRIBS;
+        assert_eq!(
+            attribs[1],
+            VertexAttrib {
+                index: 2,
+                len: 4,
+                type_: gl::UNSIGNED_BYTE,
+                normalized: true,
+                integer: false,
+                offset: 12,
+            }
+        );
+        assert!(attribs[2].integer && !attribs[2].normalized);
+        assert_eq!(
+            (attribs[2].offset, attribs[2].type_),
+            (16, gl::UNSIGNED_SHORT)
+        );
+        assert_eq!((attribs[3].len, attribs[3].offset), (4, 24));
+    }
+}
@@ -4,9 +4,12 @@ use std::{
 };
 
 use glfw::{
-    Context, Glfw, GlfwReceiver, PWindow, SwapInterval, WindowEvent, WindowHint, WindowMode,
+    ClientApiHint, Context, ContextCreationApi, Glfw, GlfwReceiver, InitHint, OpenGlProfileHint,
+    PWindow, Platform, SwapInterval, WindowEvent, WindowHint, WindowMode,
 };
 
+use crate::setter_gen;
+
 pub struct Timer {
     pub delta: f64,
     pub time_count: f64,
@@ -35,6 +38,74 @@ impl Timer {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ContextProfile {
+    //不设profile/version,驱动给什么用什么(一般是最高版本的compat),version不起作用
+    Default,
+    //3.2+ 严格core,没有GL_QUADS等旧接口,macOS只能用这个
+    Core,
+    Compat,
+    //OpenGL ES,version填(3,0)以上,不是ES版本时按(3,0)建
+    Es,
+}
+
+setter_gen! {
+    WindowConfig {
+        profile: ContextProfile,
+        version: (u32, u32),
+        //默认帧缓冲的MSAA采样数,0是关掉
+        samples: u32
+    }
+}
+
+impl WindowConfig {
+    pub const fn new() -> Self {
+        Self {
+            profile: ContextProfile::Default,
+            version: (3, 3),
+            samples: 0,
+        }
+    }
+    //ES只有2.0/3.0/3.1/3.2,默认的(3,3)之类桌面版本换成(3,0)
+    fn context_version(&self) -> (u32, u32) {
+        match (self.profile, self.version) {
+            (ContextProfile::Es, (2, 0) | (3, 0..=2)) => self.version,
+            (ContextProfile::Es, (major, minor)) => {
+                eprintln!("[window]no OpenGL ES {}.{}, use 3.0", major, minor);
+                (3, 0)
+            }
+            _ => self.version,
+        }
+    }
+    fn hint(&self, glfw: &mut Glfw) {
+        //None是GLFW_DONT_CARE,0才是关掉
+        glfw.window_hint(WindowHint::Samples(Some(self.samples)));
+        if self.profile != ContextProfile::Default {
+            let (major, minor) = self.context_version();
+            glfw.window_hint(WindowHint::ContextVersion(major, minor));
+        }
+        match self.profile {
+            ContextProfile::Default => {}
+            ContextProfile::Core => {
+                glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
+                glfw.window_hint(WindowHint::OpenGlForwardCompat(true));
+            }
+            ContextProfile::Compat => {
+                glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Compat));
+            }
+            ContextProfile::Es => {
+                glfw.window_hint(WindowHint::ClientApi(ClientApiHint::OpenGlEs));
+            }
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Window {
     pub window: PWindow,
     pub events: GlfwReceiver<(f64, WindowEvent)>,
@@ -64,6 +135,15 @@ unsafe impl Send for Window {}
 unsafe impl Sync for Window {}
 impl Window {
     pub fn new(w: usize, h: usize, name: &str, is_full: bool) -> Window {
+        Self::with_config(w, h, name, is_full, WindowConfig::new())
+    }
+    pub fn with_config(
+        w: usize,
+        h: usize,
+        name: &str,
+        is_full: bool,
+        config: WindowConfig,
+    ) -> Window {
         GLFW.with(|glfw| {
             let glfw_lock = &mut glfw
                 .get_or_init(|| RefCell::new(GLFWwrapper(glfw::init_no_callbacks().unwrap())))
@@ -71,6 +151,7 @@ impl Window {
                 .0;
 
             glfw_lock.window_hint(WindowHint::Visible(false));
+            config.hint(glfw_lock);
             let mut window: (PWindow, GlfwReceiver<(f64, WindowEvent)>);
             match is_full {
                 true => {
@@ -99,6 +180,7 @@ impl Window {
                     );
                 }
             }
+            glfw_lock.default_window_hints();
             window.0.make_current();
             glfw_lock.set_swap_interval(SwapInterval::None);
             window.0.glfw.make_context_current(None);
@@ -113,6 +195,46 @@ impl Window {
             }
         })
     }
+    //隐藏窗口+离屏context,没有显示器时走glfw null平台(egl surfaceless/osmesa)
+    pub fn headless(w: usize, h: usize) -> Window {
+        Self::headless_with(w, h, WindowConfig::new())
+    }
+    pub fn headless_with(w: usize, h: usize, config: WindowConfig) -> Window {
+        GLFW.with(|glfw| {
+            let glfw_lock = &mut glfw
+                .get_or_init(|| {
+                    if std::env::var_os("DISPLAY").is_none()
+                        && std::env::var_os("WAYLAND_DISPLAY").is_none()
+                    {
+                        glfw::init_hint(InitHint::Platform(Platform::Null));
+                    }
+                    RefCell::new(GLFWwrapper(glfw::init_no_callbacks().unwrap()))
+                })
+                .borrow_mut()
+                .0;
+
+            let mut window = [ContextCreationApi::Egl, ContextCreationApi::OsMesa]
+                .into_iter()
+                .find_map(|api| {
+                    glfw_lock.window_hint(WindowHint::Visible(false));
+                    glfw_lock.window_hint(WindowHint::ContextCreationApi(api));
+                    config.hint(glfw_lock);
+                    glfw_lock.create_window(w as u32, h as u32, "headless", WindowMode::Windowed)
+                })
+                .expect("can't create headless context (egl/osmesa)");
+            glfw_lock.default_window_hints();
+
+            window.0.make_current();
+            glfw_lock.set_swap_interval(SwapInterval::None);
+            window.0.glfw.make_context_current(None);
+
+            Self {
+                events: window.1,
+                delta_count: Timer::new(),
+                window: window.0,
+            }
+        })
+    }
     pub fn update(&mut self) -> bool {
         // let now_time = self.window.0.glfw.get_time();
         //
@@ -128,10 +250,8 @@ impl Window {
         self.window.make_current();
     }
     pub fn view_port(&self) {
-        unsafe {
-            let size = self.window.get_framebuffer_size();
-            gl::Viewport(0, 0, size.0, size.1);
-        }
+        let size = self.window.get_framebuffer_size();
+        super::state::viewport(0, 0, size.0, size.1);
     }
     pub fn get_char(&self, char: char) -> bool {
         if let Some(event) = self.window_event() {
@@ -149,3 +269,19 @@ impl Window {
         Some(self.events.receive()?.1)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ContextProfile, WindowConfig};
+
+    #[test]
+    fn es_version() {
+        let es = || WindowConfig::default().profile(ContextProfile::Es);
+        assert_eq!(es().context_version(), (3, 0));
+        assert_eq!(es().version((3, 2)).context_version(), (3, 2));
+        assert_eq!(es().version((2, 0)).context_version(), (2, 0));
+        assert_eq!(es().version((4, 6)).context_version(), (3, 0));
+        let core = WindowConfig::default().profile(ContextProfile::Core);
+        assert_eq!(core.context_version(), (3, 3));
+    }
+}
@@ -3,8 +3,8 @@ use std::{any::{Any, TypeId}, collections::HashSet, ops::Deref, sync::LazyLock};
 
 use crate::{
     draws::window_ort, gl_unit::{
-        define::{DrawMode, VertexArrayAttribPointerGen}, program::{Program, PROGRAM2D_TWO}, texture::{Texture, Texture2D, TextureMap}, window::Window, FrameBuffer
-    }, ui::{layout::{LayoutPos, ListLayout, WindowLayout}, object::UItext}, TEX_VERTEX_YFLIP_STATIC, VAO_MUT, VAO_STATIC, VERTEX_MUT
+        define::VertexArrayAttribPointerGen, VertexArray, program::{Program, PROGRAM2D_TWO}, texture::{Texture, Texture2D, TextureMap}, window::Window, FrameBuffer
+    }, ui::{layout::{LayoutPos, ListLayout, WindowLayout}, object::UItext}, STREAM_VERTEX, TEX_VERTEX_YFLIP_STATIC, VAO_MUT, VAO_STATIC, VERTEX_MUT
 };
 
 pub mod font;
@@ -14,6 +14,8 @@ const UI_PROGRAM_FARG: &str = include_str!("../shaders/ui/frag.glsl");
 
 static UI_PROGRAM: LazyLock<Program> =
     LazyLock::new(|| Program::basic_new(UI_PROGRAM_VERT, UI_PROGRAM_FARG, None));
+//只开0号属性,不受VAO_MUT上别人留下的属性影响
+static UI_VAO: LazyLock<VertexArray> = LazyLock::new(VertexArray::new);
 //left down
 pub fn color(
     window_size: (i32, i32),
@@ -37,9 +39,13 @@ pub fn color(
 
     let (x, y) = pos;
     let (w, h) = size;
-    VERTEX_MUT.sub_data(&[x, y, x + w, y, x + w, y - h, x, y - h], 0);
-    VAO_STATIC.bind(|vao| {
-        vao.draw_arrays(DrawMode::Quads, 0, 4);
+    let first = STREAM_VERTEX.push_vertex(&[x, y, x + w, y, x + w, y - h, x, y - h], 2);
+    UI_VAO.bind(|vao| {
+        vao.bind_pointer(
+            STREAM_VERTEX.deref(),
+            VertexArrayAttribPointerGen::new::<f32>(0, 2),
+        );
+        vao.draw_quads(first, 1);
     });
 }
 
@@ -64,7 +70,8 @@ pub fn texture_y_flip(window_size: (i32, i32), texture: &Texture2D, pos: Vec2, s
             VERTEX_MUT.deref(),
             VertexArrayAttribPointerGen::new::<f32>(0, 2),
         );
-        vao.draw_element(DrawMode::Quads, 0, 4);
+        vao.draw_quads(0, 1);
+        vao.disable_pointer(1);
     });
 }
 
@@ -72,28 +79,24 @@ pub fn texture_map(texture: &TextureMap<String>, name: &str, pos: Vec2) {
     todo!()
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "mock-gl")))]
 mod test {
-    use crate::gl_unit::{GLcontext, window::Window};
+    use crate::gl_unit::golden::{assert_golden, render_headless};
 
     use super::color;
 
     #[test]
     fn ui_color() {
-        let mut window = Window::new(800, 600, "reanim test", false);
-        let mut context = GLcontext::with(&mut window);
-        window.window.show();
-        while !window.update() {
-            context.draw_option(&mut window, |_, window| {
-                color(
-                    window.window.get_size(),
-                    (255, 0, 255, 255),
-                    (0f32, 0f32),
-                    (100f32, 100f32),
-                    0,
-                );
-            });
-        }
+        let image = render_headless(800, 600, |_| {
+            color(
+                (800, 600),
+                (255, 0, 255, 255),
+                (0f32, 0f32),
+                (100f32, 100f32),
+                0,
+            );
+        });
+        assert_golden("ui_color", &image, 2);
     }
 }
 pub struct KeyStream {
@@ -160,7 +163,7 @@ impl Frame {
         for obj in self.object.iter() {
             if let Some(fb) = obj.draw() {
                 fb.view_port();
-                fb.texture.as_ref().unwrap().bind_unit(0);
+                fb.texture().unwrap().bind_unit(0);
                 PROGRAM2D_TWO.put_texture(0, PROGRAM2D_TWO.get_uniform("image"));
 
                 VERTEX_MUT.sub_data(&[-1f32, 1f32, 1f32, 1f32, 1f32, -1f32, -1f32, -1f32], 0);
@@ -168,7 +171,7 @@ impl Frame {
                 PROGRAM2D_TWO.put_matrix_name(Mat4::IDENTITY, "project_mat");
                 PROGRAM2D_TWO.put_matrix_name(Mat4::IDENTITY, "model_mat");
                 VAO_STATIC.bind(|vao| {
-                    vao.draw_arrays(DrawMode::Quads, 0, 4);
+                    vao.draw_quads(0, 1);
                 });
             }
             window.view_port();
@@ -197,6 +200,7 @@ trait UIObject: UIrender + UIlayout {}
 impl<T: UIrender + UIlayout> UIObject for T {}
 pub mod layout;
 pub mod object;
+pub mod profiler;
 
 macro_rules! Layout {
     ($type:ident($($value:expr),*) pak $($obj:expr)*) => {
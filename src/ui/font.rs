@@ -11,12 +11,12 @@ use glam::{Mat4, vec3};
 use std::collections::HashMap;
 
 use crate::draws::window_ort;
-use crate::gl_unit::define::{DrawMode, TextureParm, TextureType, VertexArrayAttribPointerGen};
+use crate::gl_unit::define::{TextureParm, TextureType, VertexArrayAttribPointerGen};
 use crate::gl_unit::program::Program;
 use crate::gl_unit::texture::Texture2D;
 use crate::gl_unit::texture::{Texture, TextureMap, TextureWrapper};
 use crate::gl_unit::{self, view_port};
-use crate::{VAO_MUT, VERTEX_BIG_MUT};
+use crate::{STREAM_VERTEX, VAO_MUT};
 use std::path::Path;
 
 pub fn font<T>(func: impl FnOnce(&mut Font) -> T) -> T {
@@ -258,67 +258,46 @@ impl Font {
             [color.0, color.1, color.2, color.3],
             FT_PROGRAM.get_uniform("text_color"),
         );
-        VERTEX_BIG_MUT.sub_data(&vertex, 0);
+        let first = STREAM_VERTEX.push_vertex(&vertex, 4);
         VAO_MUT.bind(|vao| {
             vao.bind_pointer(
-                VERTEX_BIG_MUT.deref(),
+                STREAM_VERTEX.deref(),
                 VertexArrayAttribPointerGen::new::<f32>(0, 4),
             );
-            vao.draw_arrays(DrawMode::Quads, 0, str.chars().count() as i32 * 4);
+            vao.draw_quads(first, vertex.len() as i32 / 16);
         });
     }
 }
-#[cfg(test)]
+#[cfg(all(test, not(feature = "mock-gl")))]
 mod test {
     use std::path::Path;
 
-    use crate::gl_unit::{GLcontext, window::Window};
+    use crate::gl_unit::golden::{assert_golden, render_headless};
 
     use super::Font;
 
+    //DejaVu Sans Mono,授权见test_res/font/LICENSE
     #[test]
     fn font() {
-        let mut window = Window::new(800, 600, "test font", false);
-        let mut context = GLcontext::with(&mut window);
-        window.window.show();
-
-        let mut font = Font::new_file(Path::new("./font.otf"), 0);
-
-        while !window.update() {
-            context.draw_option(&mut window, |_, window| {
-                font.draw(
-                    "hello? this is a test:) 牛逼",
-                    window.window.get_size(),
-                    -400f32,
-                    0f32,
-                    10,
-                    (1f32, 0f32, 1f32, 1f32),
-                );
-
-                // let mut y = -300;
-                // while y < 300 {
-                //     font.draw(
-                //         "hello? this is a test:)",
-                //         size.0,
-                //         size.1,
-                //         -400f32,
-                //         y as f32,
-                //         10,
-                //         (1f32, 0f32, 1f32, 1f32),
-                //     );
-                //     y += 5;
-                // }
-                font.draw(
-                    &format!("fps:{}", window.delta_count.fps()),
-                    window.window.get_size(),
-                    0f32,
-                    0f32,
-                    25,
-                    (1f32, 1f32, 1f32, 1f32),
-                );
-
-                // font.draw("cao cao zhongyu",size.0,size.1,-400f32, -300f32, 300, (0f32,1f32,1f32,1f32));
-            });
-        }
+        let image = render_headless(800, 600, |_| {
+            let mut font = Font::new_file(Path::new("./test_res/font/DejaVuSansMono.ttf"), 0);
+            font.draw(
+                "hello? this is a test:) äöü",
+                (800, 600),
+                -400f32,
+                0f32,
+                10,
+                (1f32, 0f32, 1f32, 1f32),
+            );
+            font.draw(
+                "fps:60",
+                (800, 600),
+                0f32,
+                0f32,
+                25,
+                (1f32, 1f32, 1f32, 1f32),
+            );
+        });
+        assert_golden("font", &image, 8);
     }
 }
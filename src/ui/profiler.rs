@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use crate::{
+    gl_unit::profiler::FrameRecord,
+    ui::{color, font::font},
+};
+
+//60帧一帧的时间算满条
+const BUDGET_MS: f32 = 1000f32 / 60f32;
+const ROW: f32 = 20f32;
+const TEXT_SIZE: i32 = 14;
+
+fn ms(time: Duration) -> f32 {
+    time.as_secs_f32() * 1000f32
+}
+
+//pos是左上角;每个scope一行,左边是名字和毫秒数,右边绿条cpu橙条gpu
+pub fn draw_overlay(window_size: (i32, i32), frame: &FrameRecord, pos: (f32, f32), width: f32) {
+    let (x, y) = pos;
+    let rows = frame.scopes.len() + 1;
+    color(
+        window_size,
+        (0, 0, 0, 160),
+        pos,
+        (width, rows as f32 * ROW),
+        0,
+    );
+    let bar_x = x + width * 0.6;
+    let bar_w = width * 0.4 - 4f32;
+    let bar = |row: usize, time: Duration, offset: f32, rgba: (u8, u8, u8, u8)| {
+        let w = (ms(time) / BUDGET_MS).min(1f32) * bar_w;
+        let top = y - row as f32 * ROW - 4f32 - offset;
+        color(window_size, rgba, (bar_x, top), (w.max(1f32), 5f32), 0);
+    };
+    let gpu = |time: Option<Duration>| match time {
+        Some(time) => format!("{:.2}", ms(time)),
+        None => "-".to_string(),
+    };
+
+    let mut lines = Vec::with_capacity(rows);
+    lines.push(format!(
+        "frame {}  cpu {:.2}  gpu {}",
+        frame.index,
+        ms(frame.cpu_time),
+        gpu(frame.gpu_time())
+    ));
+    bar(0, frame.cpu_time, 0f32, (80, 220, 80, 255));
+    for (row, scope) in frame.scopes.iter().enumerate() {
+        lines.push(format!(
+            "{}{}  {:.2}  {}",
+            "  ".repeat(scope.depth as usize),
+            scope.name,
+            ms(scope.cpu_time),
+            gpu(scope.gpu_time)
+        ));
+        bar(row + 1, scope.cpu_time, 0f32, (80, 220, 80, 255));
+        if let Some(time) = scope.gpu_time {
+            bar(row + 1, time, 7f32, (240, 150, 40, 255));
+        }
+    }
+    font(|font| {
+        for (row, line) in lines.iter().enumerate() {
+            let baseline = y - (row + 1) as f32 * ROW + 5f32;
+            font.draw(
+                line,
+                window_size,
+                x + 4f32,
+                baseline,
+                TEXT_SIZE,
+                (1f32, 1f32, 1f32, 1f32),
+            );
+        }
+    });
+}